@@ -0,0 +1,128 @@
+use crate::AppState;
+use crate::models::auth::{LoginRequest, LogoutRequest, RefreshRequest, TokenResponse};
+use crate::shared::error::{ApiError, AuthError, UserRepoError};
+use crate::shared::jwt::TokenKind;
+use axum::extract::rejection::JsonRejection;
+use axum::response::IntoResponse;
+use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+use std::sync::Arc;
+use validator::Validate;
+
+// a bcrypt hash of the same cost as the stored ones, verified against when the
+// email is unknown so that a miss takes as long as a wrong password
+const DUMMY_HASH: &str = "$2b$12$mGKR.dLCHJf5m1pqhp36iueNB.hLWX9JpmEWJ4IOw8LyFPGNJZZty";
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Credentials accepted, tokens issued", body = TokenResponse),
+        (status = 401, description = "Invalid email or password"),
+        (status = 422, description = "Validation failed")
+    ),
+    tag = "Auth"
+)]
+pub async fn login(
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<LoginRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Json(payload) = payload?;
+
+    payload.validate()?;
+
+    let user = match state.user_repo.get_user_by_email(&payload.email).await {
+        Ok(user) => Some(user),
+        Err(UserRepoError::NotFound) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    // bcrypt is deliberately slow, keep it off the async workers
+    let hash = user
+        .as_ref()
+        .map_or(DUMMY_HASH, |user| user.parola.as_str())
+        .to_string();
+    let matches = tokio::task::spawn_blocking(move || bcrypt::verify(payload.parola, &hash))
+        .await
+        .map_err(|e| AuthError::InternalError(e.to_string()))?
+        .map_err(|e| AuthError::InternalError(e.to_string()))?;
+
+    let Some(user) = user.filter(|_| matches) else {
+        return Err(AuthError::InvalidCredentials.into());
+    };
+
+    let tokens = state.jwt.issue_for(&user)?;
+
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Refresh token rotated, new tokens issued", body = TokenResponse),
+        (status = 401, description = "Refresh token invalid, expired or revoked")
+    ),
+    tag = "Auth"
+)]
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<RefreshRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Json(payload) = payload?;
+
+    payload.validate()?;
+
+    let claims = state
+        .jwt
+        .decode(&payload.refresh_token, TokenKind::Refresh)?;
+
+    // revoked before anything is awaited, so the token can be used only once
+    if !state.revoked.revoke(&claims) {
+        return Err(AuthError::RevokedToken.into());
+    }
+
+    // the role is read again so that demoted or deleted users
+    // can't keep extending their session
+    let user = state.user_repo.get_user(claims.sub).await?;
+
+    let tokens = state.jwt.issue_for(&user)?;
+
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    request_body = LogoutRequest,
+    responses(
+        (status = 204, description = "Refresh token revoked"),
+        (status = 401, description = "Refresh token invalid or expired")
+    ),
+    tag = "Auth"
+)]
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<LogoutRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Json(payload) = payload?;
+
+    payload.validate()?;
+
+    let claims = state
+        .jwt
+        .decode(&payload.refresh_token, TokenKind::Refresh)?;
+
+    // logging out twice is not an error
+    state.revoked.revoke(&claims);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn auth_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/login", post(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+}
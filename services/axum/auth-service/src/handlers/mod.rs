@@ -0,0 +1,17 @@
+pub mod auth;
+
+use crate::AppState;
+use crate::handlers::auth::auth_router;
+use crate::shared::doc::ApiDoc;
+use axum::Router;
+use std::sync::Arc;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+pub fn api_router() -> Router<Arc<AppState>> {
+    Router::new().merge(auth_router())
+}
+
+pub fn swagger_router() -> Router<Arc<AppState>> {
+    Router::new().merge(SwaggerUi::new("/swagger-ui").url("/openapi.json", ApiDoc::openapi()))
+}
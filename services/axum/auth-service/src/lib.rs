@@ -0,0 +1,15 @@
+pub mod handlers;
+pub mod models;
+pub mod repositories;
+pub mod shared;
+
+use crate::repositories::user_repo::UserRepository;
+use crate::shared::jwt::{JwtKeys, RevokedTokens};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub user_repo: Arc<dyn UserRepository>,
+    pub jwt: Arc<JwtKeys>,
+    pub revoked: Arc<RevokedTokens>,
+}
@@ -1,3 +1,68 @@
-fn main() {
-    println!("Hello, world!");
+use anyhow::{Context, Result};
+use auth_service::{
+    AppState, handlers,
+    repositories::user_repo::UserRepo,
+    shared::jwt::{DEFAULT_ACCESS_TTL_SECS, DEFAULT_REFRESH_TTL_SECS, JwtKeys, RevokedTokens},
+};
+use axum::{Router, extract::State, routing::get};
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use tower_http::trace::TraceLayer;
+use tracing::{Level, info};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .compact()
+        .init();
+
+    let db_url = std::env::var("DATABASE_URL").context("DATABASE_URL env var is not set!")?;
+    let jwt_secret = std::env::var("JWT_SECRET").context("JWT_SECRET env var is not set!")?;
+
+    let access_ttl = env_secs("ACCESS_TOKEN_TTL_SECS", DEFAULT_ACCESS_TTL_SECS)?;
+    let refresh_ttl = env_secs("REFRESH_TOKEN_TTL_SECS", DEFAULT_REFRESH_TTL_SECS)?;
+
+    let pool = PgPoolOptions::new()
+        .max_connections(10)
+        .connect(&db_url)
+        .await?;
+
+    info!("{:<12} - Database connection pool created.", "DB");
+
+    let app_state = Arc::new(AppState {
+        user_repo: Arc::new(UserRepo::new(pool.clone())),
+        jwt: Arc::new(JwtKeys::new(jwt_secret.as_bytes(), access_ttl, refresh_ttl)),
+        revoked: Arc::new(RevokedTokens::default()),
+    });
+
+    let app = Router::new()
+        .route("/api", get(check_state))
+        .nest("/api/auth", handlers::api_router())
+        .merge(handlers::swagger_router())
+        .layer(TraceLayer::new_for_http())
+        .with_state(app_state);
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+    info!("{:<12} - {:?}\n", "LISTENING", listener.local_addr());
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+fn env_secs(name: &str, default: i64) -> Result<i64> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .with_context(|| format!("{} must be a number of seconds", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+async fn check_state(State(status): State<Arc<AppState>>) -> &'static str {
+    match status.user_repo.check().await {
+        Ok(_) => "PostgreSQL works! :p",
+        Err(_) => "PostgreSQL DOESN'T work! :(",
+    }
 }
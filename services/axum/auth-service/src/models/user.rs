@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use std::fmt;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Admin,
+    OwnerEvent,
+    Client,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::OwnerEvent => "owner-event",
+            Role::Client => "client",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// UTILIZATORI.rol is a plain VARCHAR guarded by a CHECK constraint,
+// so the conversion happens here instead of through a postgres enum type
+impl TryFrom<String> for Role {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "admin" => Ok(Role::Admin),
+            "owner-event" => Ok(Role::OwnerEvent),
+            "client" => Ok(Role::Client),
+            other => Err(format!("Unknown role `{}`", other)),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct User {
+    pub id: i32,
+    pub email: String,
+    pub parola: String,
+    #[sqlx(try_from = "String")]
+    pub rol: Role,
+}
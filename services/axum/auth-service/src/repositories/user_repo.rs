@@ -0,0 +1,60 @@
+use crate::models::user::User;
+use crate::shared::error::{UserRepoError, map_sqlx_user_error};
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn check(&self) -> Result<(), Error>;
+    async fn get_user_by_email(&self, email: &str) -> Result<User, UserRepoError>;
+    async fn get_user(&self, user_id: i32) -> Result<User, UserRepoError>;
+}
+
+pub struct UserRepo {
+    pool: PgPool,
+}
+
+impl UserRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for UserRepo {
+    async fn check(&self) -> Result<(), Error> {
+        sqlx::query("select 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> Result<User, UserRepoError> {
+        let result = sqlx::query_as::<_, User>(
+            r#"
+            SELECT ID, email, parola, rol
+            FROM UTILIZATORI
+            WHERE email = $1
+            "#,
+        )
+        .bind(email)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map_err(map_sqlx_user_error)
+    }
+
+    async fn get_user(&self, user_id: i32) -> Result<User, UserRepoError> {
+        let result = sqlx::query_as::<_, User>(
+            r#"
+            SELECT ID, email, parola, rol
+            FROM UTILIZATORI
+            WHERE ID = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map_err(map_sqlx_user_error)
+    }
+}
@@ -0,0 +1,17 @@
+use crate::handlers::auth::*;
+use crate::models::auth::{LoginRequest, LogoutRequest, RefreshRequest, TokenResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(login, refresh, logout),
+    components(schemas(LoginRequest, RefreshRequest, LogoutRequest, TokenResponse)),
+    tags(
+        (name = "Auth", description = "Login, logout and token refresh endpoints")
+    )
+)]
+pub struct ApiDoc;
+
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
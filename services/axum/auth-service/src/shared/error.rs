@@ -0,0 +1,188 @@
+use axum::Json;
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use sqlx::Error;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+#[derive(Debug)]
+pub enum ApiError {
+    Validation(ValidationErrors),
+    Json(JsonRejection),
+    Auth(AuthError),
+    User(UserRepoError),
+}
+
+#[derive(Serialize)]
+struct ApiErrorResponse {
+    error: String,
+    details: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    InvalidCredentials,
+    InvalidToken,
+    ExpiredToken,
+    RevokedToken,
+    InternalError(String),
+}
+
+#[derive(Debug)]
+pub enum UserRepoError {
+    NotFound,
+    InternalError(Error),
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::Json(rejection)
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        ApiError::Validation(errors)
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(error: AuthError) -> Self {
+        ApiError::Auth(error)
+    }
+}
+
+impl From<UserRepoError> for ApiError {
+    fn from(error: UserRepoError) -> Self {
+        ApiError::User(error)
+    }
+}
+
+fn flatten_validation_errors(errors: &ValidationErrors) -> Vec<String> {
+    let mut messages = Vec::new();
+
+    for kind in errors.errors().values() {
+        match kind {
+            ValidationErrorsKind::Struct(nested_errors) => {
+                messages.extend(flatten_validation_errors(nested_errors));
+            }
+            ValidationErrorsKind::List(list_errors) => {
+                for nested_errors in list_errors.values() {
+                    messages.extend(flatten_validation_errors(nested_errors));
+                }
+            }
+            ValidationErrorsKind::Field(field_errors) => {
+                for error in field_errors {
+                    if let Some(message) = &error.message {
+                        messages.push(message.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    messages
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            ApiError::Validation(errors) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ApiErrorResponse {
+                    error: "Validation Failed".to_string(),
+                    details: flatten_validation_errors(&errors),
+                },
+            ),
+
+            ApiError::Json(rejection) => {
+                let (status, title) = match rejection {
+                    JsonRejection::JsonDataError(_) => {
+                        (StatusCode::UNPROCESSABLE_ENTITY, "Invalid JSON Data")
+                    }
+                    JsonRejection::JsonSyntaxError(_) => {
+                        (StatusCode::BAD_REQUEST, "Invalid JSON Syntax")
+                    }
+                    JsonRejection::MissingJsonContentType(_) => {
+                        (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Missing Content-Type")
+                    }
+                    _ => (StatusCode::BAD_REQUEST, "Bad JSON Request"),
+                };
+                (
+                    status,
+                    ApiErrorResponse {
+                        error: title.to_string(),
+                        details: vec![rejection.body_text()],
+                    },
+                )
+            }
+
+            ApiError::Auth(e) => match e {
+                AuthError::InvalidCredentials => (
+                    StatusCode::UNAUTHORIZED,
+                    ApiErrorResponse {
+                        error: "Unauthorized".to_string(),
+                        details: vec!["Invalid email or password.".to_string()],
+                    },
+                ),
+                AuthError::InvalidToken => (
+                    StatusCode::UNAUTHORIZED,
+                    ApiErrorResponse {
+                        error: "Unauthorized".to_string(),
+                        details: vec!["The provided token is invalid.".to_string()],
+                    },
+                ),
+                AuthError::ExpiredToken => (
+                    StatusCode::UNAUTHORIZED,
+                    ApiErrorResponse {
+                        error: "Unauthorized".to_string(),
+                        details: vec!["The provided token has expired.".to_string()],
+                    },
+                ),
+                AuthError::RevokedToken => (
+                    StatusCode::UNAUTHORIZED,
+                    ApiErrorResponse {
+                        error: "Unauthorized".to_string(),
+                        details: vec!["The provided token has been revoked.".to_string()],
+                    },
+                ),
+                AuthError::InternalError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiErrorResponse {
+                        error: "Internal Server Error".to_string(),
+                        details: vec!["An internal server error occurred.".to_string()],
+                    },
+                ),
+            },
+
+            ApiError::User(e) => match e {
+                // a missing user is reported exactly like a wrong password,
+                // otherwise the endpoint could be used to enumerate emails
+                UserRepoError::NotFound => (
+                    StatusCode::UNAUTHORIZED,
+                    ApiErrorResponse {
+                        error: "Unauthorized".to_string(),
+                        details: vec!["Invalid email or password.".to_string()],
+                    },
+                ),
+                UserRepoError::InternalError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiErrorResponse {
+                        error: "Internal Server Error".to_string(),
+                        details: vec!["An internal server error occurred.".to_string()],
+                    },
+                ),
+            },
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
+
+pub fn map_sqlx_user_error(err: Error) -> UserRepoError {
+    match err {
+        Error::RowNotFound => UserRepoError::NotFound,
+        e => UserRepoError::InternalError(e),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::models::auth::TokenResponse;
+use crate::models::user::{Role, User};
+use crate::shared::error::AuthError;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode, get_current_timestamp,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+pub const DEFAULT_ACCESS_TTL_SECS: i64 = 15 * 60;
+pub const DEFAULT_REFRESH_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+// the event-service decodes the same claims, so any change here
+// has to be mirrored in event-service/src/shared/auth.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: i32,
+    pub rol: Role,
+    pub typ: TokenKind,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    access_ttl: i64,
+    refresh_ttl: i64,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &[u8], access_ttl: i64, refresh_ttl: i64) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            access_ttl,
+            refresh_ttl,
+        }
+    }
+
+    pub fn issue_pair(&self, user_id: i32, rol: Role) -> Result<TokenResponse, AuthError> {
+        let access_token = self.issue(user_id, rol, TokenKind::Access, self.access_ttl)?;
+        let refresh_token = self.issue(user_id, rol, TokenKind::Refresh, self.refresh_ttl)?;
+
+        Ok(TokenResponse {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.access_ttl,
+        })
+    }
+
+    pub fn issue_for(&self, user: &User) -> Result<TokenResponse, AuthError> {
+        self.issue_pair(user.id, user.rol)
+    }
+
+    fn issue(
+        &self,
+        user_id: i32,
+        rol: Role,
+        typ: TokenKind,
+        ttl: i64,
+    ) -> Result<String, AuthError> {
+        let now = get_current_timestamp() as i64;
+        let claims = Claims {
+            sub: user_id,
+            rol,
+            typ,
+            jti: Uuid::new_v4().to_string(),
+            iat: now,
+            exp: now + ttl,
+        };
+
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)
+            .map_err(|e| AuthError::InternalError(e.to_string()))
+    }
+
+    pub fn decode(&self, token: &str, expected: TokenKind) -> Result<Claims, AuthError> {
+        let claims = decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                _ => AuthError::InvalidToken,
+            })?;
+
+        if claims.typ != expected {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+}
+
+// refresh tokens are revoked by their `jti` until they would have expired anyway;
+// the list lives in memory, so it is per instance and cleared on restart
+#[derive(Default)]
+pub struct RevokedTokens {
+    tokens: Mutex<HashMap<String, i64>>,
+}
+
+impl RevokedTokens {
+    // false when the token was already revoked; the check and the insert share
+    // the lock, so of two concurrent refreshes with the same token only one wins
+    pub fn revoke(&self, claims: &Claims) -> bool {
+        let now = get_current_timestamp() as i64;
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+
+        tokens.retain(|_, exp| *exp > now);
+        tokens.insert(claims.jti.clone(), claims.exp).is_none()
+    }
+}
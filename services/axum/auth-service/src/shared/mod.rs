@@ -0,0 +1,3 @@
+pub mod doc;
+pub mod error;
+pub mod jwt;
@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use auth_service::AppState;
+use auth_service::handlers;
+use auth_service::models::user::{Role, User};
+use auth_service::repositories::user_repo::UserRepository;
+use auth_service::shared::error::UserRepoError;
+use auth_service::shared::jwt::{JwtKeys, RevokedTokens};
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode, header};
+use serde_json::{Value, json};
+use sqlx::Error;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const EMAIL: &str = "ana@example.com";
+const PASSWORD: &str = "correct horse";
+
+// the users the handlers can find, instead of UTILIZATORI
+struct Users(Vec<User>);
+
+#[async_trait]
+impl UserRepository for Users {
+    async fn check(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> Result<User, UserRepoError> {
+        self.0
+            .iter()
+            .find(|user| user.email == email)
+            .map(copy)
+            .ok_or(UserRepoError::NotFound)
+    }
+
+    async fn get_user(&self, user_id: i32) -> Result<User, UserRepoError> {
+        self.0
+            .iter()
+            .find(|user| user.id == user_id)
+            .map(copy)
+            .ok_or(UserRepoError::NotFound)
+    }
+}
+
+fn copy(user: &User) -> User {
+    User {
+        id: user.id,
+        email: user.email.clone(),
+        parola: user.parola.clone(),
+        rol: user.rol,
+    }
+}
+
+fn app() -> Router {
+    // the lowest cost bcrypt accepts, the tests don't need a slow hash
+    let user = User {
+        id: 1,
+        email: EMAIL.to_string(),
+        parola: bcrypt::hash(PASSWORD, 4).unwrap(),
+        rol: Role::Client,
+    };
+
+    let state = Arc::new(AppState {
+        user_repo: Arc::new(Users(vec![user])),
+        jwt: Arc::new(JwtKeys::new(b"secret", 60, 600)),
+        revoked: Arc::new(RevokedTokens::default()),
+    });
+
+    Router::new()
+        .nest("/api/auth", handlers::api_router())
+        .with_state(state)
+}
+
+async fn post(app: &Router, path: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/auth{}", path))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn login(app: &Router) -> Value {
+    let (status, body) = post(app, "/login", json!({ "email": EMAIL, "parola": PASSWORD })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    body
+}
+
+#[tokio::test]
+async fn unknown_emails_and_wrong_passwords_look_the_same() {
+    let app = app();
+
+    let wrong_password = post(
+        &app,
+        "/login",
+        json!({ "email": EMAIL, "parola": "wrong password" }),
+    )
+    .await;
+    let unknown_email = post(
+        &app,
+        "/login",
+        json!({ "email": "nobody@example.com", "parola": PASSWORD }),
+    )
+    .await;
+
+    assert_eq!(wrong_password.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(wrong_password, unknown_email);
+}
+
+#[tokio::test]
+async fn a_refresh_token_is_used_once() {
+    let app = app();
+    let tokens = login(&app).await;
+    assert_eq!(tokens["token_type"], "Bearer");
+
+    let refresh = json!({ "refresh_token": tokens["refresh_token"] });
+    let (status, rotated) = post(&app, "/refresh", refresh.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", rotated);
+    assert_ne!(rotated["refresh_token"], tokens["refresh_token"]);
+
+    let (status, body) = post(&app, "/refresh", refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["details"][0], "The provided token has been revoked.");
+}
+
+#[tokio::test]
+async fn a_logged_out_refresh_token_is_revoked() {
+    let app = app();
+    let tokens = login(&app).await;
+    let refresh = json!({ "refresh_token": tokens["refresh_token"] });
+
+    let (status, _) = post(&app, "/logout", refresh.clone()).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = post(&app, "/logout", refresh.clone()).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, body) = post(&app, "/refresh", refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["details"][0], "The provided token has been revoked.");
+}
+
+#[tokio::test]
+async fn access_tokens_dont_refresh() {
+    let app = app();
+    let tokens = login(&app).await;
+
+    let (status, body) = post(
+        &app,
+        "/refresh",
+        json!({ "refresh_token": tokens["access_token"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["details"][0], "The provided token is invalid.");
+}
@@ -1,12 +1,6 @@
-DROP TABLE IF EXISTS UTILIZATORI CASCADE;
-
-DROP TABLE IF EXISTS EVENIMENTE CASCADE;
-
-DROP TABLE IF EXISTS PACHETE CASCADE;
-
-DROP TABLE IF EXISTS JOIN_PE CASCADE;
-
-DROP TABLE IF EXISTS BILETE CASCADE;
+-- the schema as db-init/events/init.sql bootstrapped it. a database created by
+-- that script has no migration history and has to be recreated, the service
+-- won't adopt it. never edit an applied migration, add a new one instead
 
 CREATE EXTENSION IF NOT EXISTS unaccent;
 
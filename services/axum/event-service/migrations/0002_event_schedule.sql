@@ -0,0 +1,14 @@
+-- when an event takes place. the timezone is the IANA name it is shown in,
+-- the instants themselves are stored in UTC
+
+ALTER TABLE EVENIMENTE
+    ADD COLUMN data_inceput TIMESTAMPTZ NULL,
+    ADD COLUMN data_sfarsit TIMESTAMPTZ NULL,
+    ADD COLUMN fus_orar VARCHAR(64) NULL;
+
+ALTER TABLE EVENIMENTE
+    ADD CONSTRAINT chk_interval_eveniment CHECK (
+        data_sfarsit IS NULL
+        OR data_inceput IS NULL
+        OR data_sfarsit > data_inceput
+    );
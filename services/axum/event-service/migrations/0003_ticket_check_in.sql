@@ -0,0 +1,14 @@
+-- check-ins. an event ticket records its entry on the ticket itself, a packet
+-- ticket admits to every event of the packet, once each, so it gets one row in
+-- INTRARI per event
+
+ALTER TABLE BILETE
+    ADD COLUMN checked_in_at TIMESTAMPTZ NULL;
+
+CREATE TABLE
+    INTRARI (
+        COD VARCHAR(50) REFERENCES BILETE (COD) ON DELETE CASCADE,
+        EvenimentID INTEGER REFERENCES EVENIMENTE (ID) ON DELETE CASCADE,
+        checked_in_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (COD, EvenimentID)
+    );
@@ -49,133 +49,187 @@ VALUES
     );
 
 INSERT INTO
-    EVENIMENTE (ID_OWNER, nume, locatie, descriere, numarLocuri)
+    EVENIMENTE (ID_OWNER, nume, locatie, descriere, numarLocuri, data_inceput, data_sfarsit, fus_orar)
 VALUES
     (
         2,
         'Concert Vama Veche',
         'Cluj-Napoca, BT Arena',
         'Concert de muzică rock alternativ cu trupa Vama Veche. Atmosphere electrizantă și hit-uri legendare!',
-        5000
+        5000,
+        '2025-07-12 20:00+03',
+        '2025-07-12 23:30+03',
+        'Europe/Bucharest'
     ),
     (
         2,
         'Festival Electric Castle 2025',
         'Cluj, Domeniul Banffy',
         'Cel mai mare festival de muzică electronică din România cu artiști internaționali.',
-        50000
+        50000,
+        '2025-07-17 16:00+03',
+        '2025-07-20 04:00+03',
+        'Europe/Bucharest'
     ),
     (
         2,
         'Concert Simfonic de Crăciun',
         'București, Sala Palatului',
         'Orchestra Filarmonică București prezintă concerte clasice de sărbători.',
-        2500
+        2500,
+        '2025-12-20 19:00+02',
+        '2025-12-20 21:30+02',
+        'Europe/Bucharest'
     ),
     (
         3,
         'Untold Festival 2025',
         'Cluj-Napoca, Cluj Arena',
         'Festival internațional de muzică electronică, dans și cultură.',
-        80000
+        80000,
+        '2025-08-07 18:00+03',
+        '2025-08-11 06:00+03',
+        'Europe/Bucharest'
     ),
     (
         3,
         'Festivalul de Teatru',
         'Iași, Teatrul Național',
         'Săptămâna dedicată pieselor de teatru clasic și modern. Reprezentații zilnice cu trupe din toată țara.',
-        1000
+        1000,
+        '2025-10-13 18:00+03',
+        '2025-10-19 22:00+03',
+        'Europe/Bucharest'
     ),
     (
         3,
         'Spectacol Shakespeare',
         'Sibiu, Teatrul Radu Stanca',
         'Adaptare modernă a piesei "Hamlet" de către regizorul Ion Caramitru.',
-        450
+        450,
+        '2025-11-08 19:00+02',
+        '2025-11-08 22:00+02',
+        'Europe/Bucharest'
     ),
     (
         4,
         'Noaptea Albă a Galeriilor',
         'București, Centrul Vechi',
         'Eveniment cultural cu expoziții de artă contemporană în 30+ galerii.',
-        10000
+        10000,
+        '2025-05-17 18:00+03',
+        '2025-05-18 02:00+03',
+        'Europe/Bucharest'
     ),
     (
         4,
         'Târg de Crăciun 2025',
         'București, Piața Constituției',
         'Târg anual de sărbători cu decorațiuni handmade, meșteșuguri tradiționale și delicii culinare.',
-        20000
+        20000,
+        '2025-11-28 10:00+02',
+        '2025-12-26 22:00+02',
+        'Europe/Bucharest'
     ),
     (
         4,
         'Festivalul Medieval Sighișoara',
         'Sighișoara, Cetate',
         'Reconstituire medievală cu cavaleri, meșteșugari și spectacole de epocă.',
-        15000
+        15000,
+        '2025-07-25 10:00+03',
+        '2025-07-27 23:00+03',
+        'Europe/Bucharest'
     ),
     (
         5,
         'Târgul de Paște',
         'Brașov, Piața Sfatului',
         'Târg tradițional cu produse pascale, ouă decorate și muzică populară.',
-        8000
+        8000,
+        '2025-04-11 09:00+03',
+        '2025-04-20 21:00+03',
+        'Europe/Bucharest'
     ),
     (
         2,
         'Maraton București 2025',
         'București, Piața Constituției',
         'Competiție sportivă internațională - maraton complet și semimaraton.',
-        30000
+        30000,
+        '2025-10-12 08:00+03',
+        '2025-10-12 15:00+03',
+        'Europe/Bucharest'
     ),
     (
         5,
         'Cupa României la Escaladă',
         'Brașov, Sala Sporturilor',
         'Competiție națională de escaladă sportivă pentru toate categoriile de vârstă.',
-        800
+        800,
+        '2025-06-14 09:00+03',
+        '2025-06-15 18:00+03',
+        'Europe/Bucharest'
     ),
     (
         4,
         'Street Food Festival',
         'Timișoara, Piața Victoriei',
         'Festival culinar cu food trucks, cuisine internațională și muzică live.',
-        12000
+        12000,
+        '2025-09-05 12:00+03',
+        '2025-09-07 23:00+03',
+        'Europe/Bucharest'
     ),
     (
         5,
         'Festivalul Vinului și Bucatelor',
         'Alba Iulia, Cetatea Alba Carolina',
         'Degustări de vinuri românești premium și preparate gastronomice locale.',
-        5000
+        5000,
+        '2025-09-19 11:00+03',
+        '2025-09-21 22:00+03',
+        'Europe/Bucharest'
     ),
     (
         5,
         'Expoziție de Artă Modernă',
         'Timișoara, Galeria Delta',
         'Colecție de artă contemporană: picturi, sculpturi și instalații multimedia.',
-        500
+        500,
+        '2025-03-03 10:00+02',
+        '2025-03-30 18:00+02',
+        'Europe/Bucharest'
     ),
     (
         3,
         'Bienala de Arhitectură',
         'București, MNAC',
         'Expoziție internațională dedicată arhitecturii contemporane și urbanismului.',
-        2000
+        2000,
+        '2025-10-01 10:00+03',
+        '2025-11-30 18:00+02',
+        'Europe/Bucharest'
     ),
     (
         2,
         'Tech Summit România 2025',
         'Cluj-Napoca, Grand Hotel Italia',
         'Conferință de tehnologie cu speakeri internaționali, workshop-uri AI și networking.',
-        1500
+        1500,
+        '2025-11-20 09:00+02',
+        '2025-11-21 18:00+02',
+        'Europe/Bucharest'
     ),
     (
         4,
         'Innovation Fest',
         'Iași, Palas Mall',
         'Expoziție de startup-uri, roboti, VR/AR și tehnologii emergente.',
-        3000
+        3000,
+        '2025-05-09 10:00+03',
+        '2025-05-11 20:00+03',
+        'Europe/Bucharest'
     );
 
 INSERT INTO
@@ -1,15 +1,22 @@
 use crate::AppState;
 use crate::handlers::ticket;
+use crate::models::check_in::CheckInStats;
 use crate::models::event::{CreateEvent, Event, EventQuery, UpdateEvent};
+use crate::shared::auth::AuthUser;
+use crate::shared::base_url::BaseUrl;
 use crate::shared::error::ApiError;
-use crate::shared::links::{Response, build_filtered_event, build_simple_event};
-use axum::extract::rejection::JsonRejection;
+use crate::shared::links::{
+    CollectionResponse, Response, build_check_in_stats, build_collection, build_filtered_event,
+    build_simple_event, event_filter_parts,
+};
+use crate::shared::routes::Route;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
 use axum::response::IntoResponse;
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
     http::StatusCode,
-    routing::get,
+    routing::{get, post},
 };
 use std::sync::Arc;
 use validator::Validate;
@@ -19,33 +26,56 @@ use validator::Validate;
     path = "/api/event-manager/events",
     params(
         ("location" = Option<String>, Query, description = "Filter by location of the event"),
-        ("name" = Option<String>, Query, description = "Filter by event name")
+        ("name" = Option<String>, Query, description = "Filter by event name"),
+        ("available_tickets" = Option<i32>, Query, description = "Only events with at least this many seats left"),
+        ("from" = Option<String>, Query, description = "Only events starting at or after this RFC 3339 timestamp"),
+        ("to" = Option<String>, Query, description = "Only events starting at or before this RFC 3339 timestamp"),
+        ("upcoming" = Option<bool>, Query, description = "Only events that haven't ended yet"),
+        ("page" = Option<i64>, Query, description = "Pagination page number"),
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `name`, `location`, `seats` or `id`"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
     ),
     responses(
-        (status = 200, description = "List events (optionally filtered by location or name)", body = [Response<Event>]),
+        (status = 200, description = "A page of events (optionally filtered by location or name)", body = CollectionResponse<Event>),
         (status = 500, description = "Internal server error")
     ),
     tag = "Events"
 )]
 pub async fn list_events(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<EventQuery>,
+    BaseUrl(base_url): BaseUrl,
+    query: Result<Query<EventQuery>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Query(params) = query?;
     params.validate()?;
+    params.paginare.ensure_cursor_fits::<Event>()?;
 
-    let events: Vec<Event> = state.event_repo.list_events(params.clone()).await?;
+    let events = state.event_repo.list_events(params.clone()).await?;
 
-    let has_filters = params.locatie.is_some() || params.nume.is_some();
+    let has_filters = params.locatie.is_some()
+        || params.nume.is_some()
+        || params.bilete.is_some()
+        || params.from.is_some()
+        || params.to.is_some()
+        || params.upcoming.is_some();
 
-    let response: Vec<Response<Event>> = if has_filters {
-        build_filtered_event(events, &params, &state.base_url)
+    let events = if has_filters {
+        events.map_items(|events| build_filtered_event(events, &params, &base_url))
     } else {
-        events
-            .into_iter()
-            .map(|event| build_simple_event(event, &state.base_url))
-            .collect()
+        events.map(|event| build_simple_event(event, &base_url))
     };
 
+    let response = build_collection::<CreateEvent, _>(
+        events,
+        "events",
+        &Route::Events.href(&base_url, &[]),
+        event_filter_parts(&params),
+        &params.paginare,
+    );
+
     Ok((StatusCode::OK, Json(response)))
 }
 
@@ -63,14 +93,16 @@ pub async fn list_events(
 )]
 pub async fn get_event(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let event = state.event_repo.get_event(id).await?;
 
-    let event_response = build_simple_event(event, &state.base_url);
+    let event_response = build_simple_event(event, &base_url);
 
     Ok(Json(event_response))
 }
@@ -84,25 +116,29 @@ pub async fn get_event(
     request_body = UpdateEvent,
     responses(
         (status = 200, description = "Updated event", body = Response<Event>),
+        (status = 403, description = "Event is owned by another user"),
         (status = 404, description = "Event not found")
     ),
     tag = "Events"
 )]
 pub async fn update_event(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
     payload: Result<Json<UpdateEvent>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let Json(payload) = payload?;
 
     payload.validate()?;
 
-    let event = state.event_repo.update_event(id, payload).await?;
+    let event = state.event_repo.update_event(id, payload, &caller).await?;
 
-    let event_response = build_simple_event(event, &state.base_url);
+    let event_response = build_simple_event(event, &base_url);
 
     Ok(Json(event_response))
 }
@@ -118,15 +154,17 @@ pub async fn update_event(
 )]
 pub async fn create_event(
     State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
     payload: Result<Json<CreateEvent>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
     let Json(payload) = payload?;
 
     payload.validate()?;
 
-    let event = state.event_repo.create_event(payload).await?;
+    let event = state.event_repo.create_event(caller.id, payload).await?;
 
-    let event_response = build_simple_event(event, &state.base_url);
+    let event_response = build_simple_event(event, &base_url);
 
     Ok((StatusCode::CREATED, Json(event_response)))
 }
@@ -139,35 +177,72 @@ pub async fn create_event(
     ),
     responses(
         (status = 204, description = "Event deleted successfully"),
-        (status = 404, description = "Event not found")
+        (status = 403, description = "Event is owned by another user"),
+        (status = 404, description = "Event not found"),
+        (status = 409, description = "Event still has tickets")
     ),
     tag = "Events"
 )]
 pub async fn delete_event(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
-    state.event_repo.delete_event(id).await?;
+    state.event_repo.delete_event(id, &caller).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/event-manager/events/{id}/check-in-stats",
+    params(
+        ("id" = i32, Path, description = "ID of the event")
+    ),
+    responses(
+        (status = 200, description = "Issued tickets and check-ins for the event, packet tickets included", body = Response<CheckInStats>),
+        (status = 404, description = "Event not found")
+    ),
+    tag = "Events"
+)]
+pub async fn get_check_in_stats(
+    State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
+    if id < 0 {
+        return Err(ApiError::NegativeId);
+    }
+    let stats = state.event_repo.get_check_in_stats(id).await?;
+
+    let stats_response = build_check_in_stats(stats, &base_url);
+
+    Ok(Json(stats_response))
+}
+
 pub fn event_manager_router() -> Router<Arc<AppState>> {
     Router::new()
-        .route("/events", get(list_events).post(create_event))
+        .route(Route::Events.path(), get(list_events).post(create_event))
         .route(
-            "/events/{id}",
+            Route::Event.path(),
             get(get_event).put(update_event).delete(delete_event),
         )
+        .route(Route::EventCheckInStats.path(), get(get_check_in_stats))
         .route(
-            "/events/{id}/tickets",
+            Route::EventTickets.path(),
             get(ticket::list_tickets_for_event).post(ticket::create_ticket_for_event),
         )
         .route(
-            "/events/{id}/tickets/{cod}",
+            Route::EventTicketsBulk.path(),
+            post(ticket::create_tickets_for_event),
+        )
+        .route(
+            Route::EventTicket.path(),
             get(ticket::get_ticket_for_event)
                 .put(ticket::update_ticket_for_event)
                 .delete(ticket::delete_ticket_for_event),
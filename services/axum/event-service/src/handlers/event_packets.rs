@@ -3,10 +3,16 @@ use crate::handlers::ticket;
 use crate::models::event_packets::{
     CreateEventPacket, EventPacketQuery, EventPackets, UpdateEventPacket,
 };
+use crate::shared::auth::AuthUser;
+use crate::shared::base_url::BaseUrl;
 use crate::shared::error::ApiError;
-use crate::shared::links::{Response, build_filtered_event_packets, build_simple_event_packet};
+use crate::shared::links::{
+    CollectionResponse, Response, build_collection, build_filtered_event_packets,
+    build_simple_event_packet, packet_filter_parts,
+};
+use crate::shared::routes::Route;
 use axum::extract::Query;
-use axum::extract::rejection::JsonRejection;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
 use axum::response::IntoResponse;
 use axum::{
     Json, Router,
@@ -22,21 +28,28 @@ use validator::Validate;
     path = "/api/event-manager/event-packets",
     params(
         ("type" = Option<String>, Query, description = "Filter event packets by description/type"),
-        ("available_tickets" = Option<i32>, Query, description = "Filter event packets by available tickets"),
+        ("available_tickets" = Option<i32>, Query, description = "Only event packets with at least this many seats left"),
         ("page" = Option<i64>, Query, description = "Pagination page number"),
-        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination")
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `name`, `location`, `seats` or `id`"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
     ),
     responses(
-        (status = 200, description = "List all event packets (optionally filtered)", body = [Response<EventPackets>]),
+        (status = 200, description = "A page of event packets (optionally filtered)", body = CollectionResponse<EventPackets>),
         (status = 500, description = "Internal server error")
     ),
     tag = "Event Packets"
 )]
 pub async fn list_event_packets(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<EventPacketQuery>,
+    BaseUrl(base_url): BaseUrl,
+    query: Result<Query<EventPacketQuery>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Query(params) = query?;
     params.validate()?;
+    params.paginare.ensure_cursor_fits::<EventPackets>()?;
 
     let event_packets = state
         .event_packet_repo
@@ -48,15 +61,20 @@ pub async fn list_event_packets(
         || params.paginare.page.is_some()
         || params.paginare.items_per_page.is_some();
 
-    let response: Vec<Response<EventPackets>> = if has_filters {
-        build_filtered_event_packets(event_packets, &params, &state.base_url)
+    let event_packets = if has_filters {
+        event_packets.map_items(|packets| build_filtered_event_packets(packets, &params, &base_url))
     } else {
-        event_packets
-            .into_iter()
-            .map(|e| build_simple_event_packet(e, &state.base_url))
-            .collect()
+        event_packets.map(|e| build_simple_event_packet(e, &base_url))
     };
 
+    let response = build_collection::<CreateEventPacket, _>(
+        event_packets,
+        "event-packets",
+        &Route::Packets.href(&base_url, &[]),
+        packet_filter_parts(&params),
+        &params.paginare,
+    );
+
     Ok(Json(response))
 }
 
@@ -72,14 +90,16 @@ pub async fn list_event_packets(
 )]
 pub async fn get_event_packet(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let event_packet = state.event_packet_repo.get_event_packet(id).await?;
 
-    let packet_response = build_simple_event_packet(event_packet, &state.base_url);
+    let packet_response = build_simple_event_packet(event_packet, &base_url);
 
     Ok(Json(packet_response))
 }
@@ -91,17 +111,21 @@ pub async fn get_event_packet(
     request_body = UpdateEventPacket,
     responses(
         (status = 200, description = "Update an existing event packet", body = Response<EventPackets>),
+        (status = 403, description = "Event packet is owned by another user"),
         (status = 404, description = "Event packet not found")
     ),
     tag = "Event Packets"
 )]
 pub async fn update_event_packet(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
     payload: Result<Json<UpdateEventPacket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let Json(payload) = payload?;
 
@@ -109,10 +133,10 @@ pub async fn update_event_packet(
 
     let event_packet = state
         .event_packet_repo
-        .update_event_packet(id, payload)
+        .update_event_packet(id, payload, &caller)
         .await?;
 
-    let packet_response = build_simple_event_packet(event_packet, &state.base_url);
+    let packet_response = build_simple_event_packet(event_packet, &base_url);
 
     Ok(Json(packet_response))
 }
@@ -129,15 +153,20 @@ pub async fn update_event_packet(
 )]
 pub async fn create_event_packet(
     State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
     payload: Result<Json<CreateEventPacket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
     let Json(payload) = payload?;
 
     payload.validate()?;
 
-    let event_packet = state.event_packet_repo.create_event_packet(payload).await?;
+    let event_packet = state
+        .event_packet_repo
+        .create_event_packet(caller.id, payload)
+        .await?;
 
-    let packet_response = build_simple_event_packet(event_packet, &state.base_url);
+    let packet_response = build_simple_event_packet(event_packet, &base_url);
 
     Ok((StatusCode::CREATED, Json(packet_response)))
 }
@@ -148,39 +177,50 @@ pub async fn create_event_packet(
     params(("id" = i32, Path, description = "Event packet ID")),
     responses(
         (status = 204, description = "Event packet deleted successfully"),
-        (status = 404, description = "Event packet not found")
+        (status = 403, description = "Event packet is owned by another user"),
+        (status = 404, description = "Event packet not found"),
+        (status = 409, description = "Event packet still has tickets")
     ),
     tag = "Event Packets"
 )]
 pub async fn delete_event_packet(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
-    state.event_packet_repo.delete_event_packet(id).await?;
+    state
+        .event_packet_repo
+        .delete_event_packet(id, &caller)
+        .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub fn event_packet_manager_router() -> Router<Arc<AppState>> {
     Router::new()
         .route(
-            "/event-packets",
+            Route::Packets.path(),
             post(create_event_packet).get(list_event_packets),
         )
         .route(
-            "/event-packets/{id}",
+            Route::Packet.path(),
             get(get_event_packet)
                 .put(update_event_packet)
                 .delete(delete_event_packet),
         )
         .route(
-            "/event-packets/{id}/tickets",
+            Route::PacketTickets.path(),
             get(ticket::list_tickets_for_packet).post(ticket::create_ticket_for_packet),
         )
         .route(
-            "/event-packets/{id}/tickets/{ticket_cod}",
+            Route::PacketTicketsBulk.path(),
+            post(ticket::create_tickets_for_packet),
+        )
+        .route(
+            Route::PacketTicket.path(),
             get(ticket::get_ticket_for_packet)
                 .put(ticket::update_ticket_for_packet)
                 .delete(ticket::delete_ticket_for_packet),
@@ -0,0 +1,139 @@
+use crate::AppState;
+use crate::models::health::{HealthCheck, HealthReport, HealthStatus};
+use axum::response::IntoResponse;
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{StatusCode, header},
+    routing::get,
+};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const LIVE_PATH: &str = "/health/live";
+pub const READY_PATH: &str = "/health/ready";
+
+// a probe that hangs is as bad as one that fails, orchestrators give up after a few seconds
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn health_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(LIVE_PATH, get(live))
+        .route(READY_PATH, get(ready))
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    responses(
+        (status = 200, description = "The process is running", body = HealthReport)
+    ),
+    security(()),
+    tag = "Health"
+)]
+pub async fn live() -> impl IntoResponse {
+    // no dependencies here, restarting the service won't bring the database back
+    respond(HealthReport::new(Vec::new()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Ready to serve traffic, possibly degraded", body = HealthReport),
+        (status = 503, description = "A dependency is down or the service is shutting down", body = HealthReport)
+    ),
+    security(()),
+    tag = "Health"
+)]
+pub async fn ready(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    // read before the checks below take connections of their own
+    let usage = state.health_repo.pool_usage();
+
+    let (database, migrations) = tokio::join!(
+        timed("database", async {
+            state
+                .event_repo
+                .check()
+                .await
+                .map(|_| (HealthStatus::Up, None))
+                .map_err(|e| e.to_string())
+        }),
+        timed("migrations", async {
+            let pending = state
+                .health_repo
+                .pending_migrations()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            Ok(if pending.is_empty() {
+                (HealthStatus::Up, None)
+            } else {
+                (
+                    HealthStatus::Down,
+                    Some(format!("pending migrations: {:?}", pending)),
+                )
+            })
+        }),
+    );
+
+    let pool = HealthCheck {
+        name: "pool".to_string(),
+        // saturation slows requests down, it doesn't stop them
+        status: if usage.is_saturated() {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Up
+        },
+        detail: Some(format!(
+            "{} of {} connections open, {} idle",
+            usage.open, usage.max, usage.idle
+        )),
+        duration_ms: 0,
+    };
+
+    let mut checks = vec![database, pool, migrations];
+    if state.shutdown.is_draining() {
+        checks.push(HealthCheck {
+            name: "shutdown".to_string(),
+            status: HealthStatus::Down,
+            detail: Some("draining in-flight requests".to_string()),
+            duration_ms: 0,
+        });
+    }
+
+    respond(HealthReport::new(checks))
+}
+
+async fn timed<F>(name: &str, check: F) -> HealthCheck
+where
+    F: Future<Output = Result<(HealthStatus, Option<String>), String>>,
+{
+    let started = Instant::now();
+
+    let (status, detail) = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(e)) => (HealthStatus::Down, Some(e)),
+        Err(_) => (
+            HealthStatus::Down,
+            Some(format!("no answer within {:?}", CHECK_TIMEOUT)),
+        ),
+    };
+
+    HealthCheck {
+        name: name.to_string(),
+        status,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn respond(report: HealthReport) -> impl IntoResponse {
+    let status = match report.status {
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Up | HealthStatus::Degraded => StatusCode::OK,
+    };
+
+    (status, [(header::CACHE_CONTROL, "no-store")], Json(report))
+}
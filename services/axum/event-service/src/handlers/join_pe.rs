@@ -2,26 +2,40 @@ use crate::AppState;
 use crate::models::event::Event;
 use crate::models::event_packets::EventPackets;
 use crate::models::join_pe::{AddEventToPacket, AddPacketToEvent};
+use crate::models::pagination::PaginationParams;
+use crate::shared::auth::AuthUser;
+use crate::shared::base_url::BaseUrl;
 use crate::shared::error::ApiError;
-use crate::shared::links::{Response, build_event_over_packet, build_packet_over_event};
+use crate::shared::links::{
+    CollectionResponse, build_collection, build_event_over_packet, build_packet_over_event,
+};
+use crate::shared::routes::Route;
 use axum::Router;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
 use axum::response::IntoResponse;
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     routing::get,
 };
 use std::sync::Arc;
+use validator::Validate;
 
 #[utoipa::path(
     get,
     path = "/api/event-manager/events/{id}/event-packets",
     params(
-        ("id" = i32, Path, description = "ID of the event")
+        ("id" = i32, Path, description = "ID of the event"),
+        ("page" = Option<i64>, Query, description = "Pagination page number"),
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `name`, `location`, `seats` or `id`"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
     ),
     responses(
-        (status = 200, description = "List packets linked to the specified event", body = [Response<EventPackets>]),
+        (status = 200, description = "A page of packets linked to the specified event", body = CollectionResponse<EventPackets>),
         (status = 404, description = "Event not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -29,17 +43,27 @@ use std::sync::Arc;
 )]
 pub async fn list_packets_for_event(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
+    query: Result<Query<PaginationParams>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
+    let Query(params) = query?;
     if id < 0 {
-        return Err(ApiError::BadRequest("Cannot get with negative ID".into()));
+        return Err(ApiError::NegativeId);
     }
-    let packets = state.join_repo.get_packets_for_event(id).await?;
+    params.validate()?;
+    params.ensure_cursor_fits::<EventPackets>()?;
+
+    let packets = state.join_repo.get_packets_for_event(id, &params).await?;
 
-    let wrapped: Vec<Response<EventPackets>> = packets
-        .into_iter()
-        .map(|e| build_packet_over_event(e, id, &state.base_url))
-        .collect();
+    let wrapped = build_collection::<AddPacketToEvent, _>(
+        packets.map(|e| build_packet_over_event(e, id, &base_url)),
+        "event-packets",
+        &Route::EventPackets.href(&base_url, &[&id]),
+        vec![],
+        &params,
+    );
 
     Ok(Json(wrapped))
 }
@@ -48,10 +72,16 @@ pub async fn list_packets_for_event(
     get,
     path = "/api/event-manager/event-packets/{id}/events",
     params(
-        ("id" = i32, Path, description = "ID of the event packet")
+        ("id" = i32, Path, description = "ID of the event packet"),
+        ("page" = Option<i64>, Query, description = "Pagination page number"),
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `name`, `location`, `seats` or `id`"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
     ),
     responses(
-        (status = 200, description = "List events linked to the specified event packet", body = [Response<Event>]),
+        (status = 200, description = "A page of events linked to the specified event packet", body = CollectionResponse<Event>),
         (status = 404, description = "Event packet not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -59,17 +89,27 @@ pub async fn list_packets_for_event(
 )]
 pub async fn list_events_for_packet(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
+    query: Result<Query<PaginationParams>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
+    let Query(params) = query?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
-    let events = state.join_repo.get_events_for_packet(id).await?;
+    params.validate()?;
+    params.ensure_cursor_fits::<Event>()?;
+
+    let events = state.join_repo.get_events_for_packet(id, &params).await?;
 
-    let wrapped: Vec<Response<Event>> = events
-        .into_iter()
-        .map(|e| build_event_over_packet(e, id, &state.base_url))
-        .collect();
+    let wrapped = build_collection::<AddEventToPacket, _>(
+        events.map(|e| build_event_over_packet(e, id, &base_url)),
+        "events",
+        &Route::PacketEvents.href(&base_url, &[&id]),
+        vec![],
+        &params,
+    );
 
     Ok(Json(wrapped))
 }
@@ -83,21 +123,29 @@ pub async fn list_events_for_packet(
     ),
     responses(
         (status = 201, description = "Event successfully linked to event packet"),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Event or packet not found"),
+        (status = 409, description = "Already linked, or the event has no seats left for the packet's tickets"),
         (status = 500, description = "Internal server error")
     ),
     tag = "JoinPE"
 )]
 pub async fn add_event_to_packet(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
-    Json(payload): Json<AddEventToPacket>,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
+    payload: Result<Json<AddEventToPacket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
+    let Json(payload) = payload?;
 
-    let relation = state.join_repo.add_event_to_packet(id, payload).await?;
+    let relation = state
+        .join_repo
+        .add_event_to_packet(id, payload, &caller)
+        .await?;
     Ok((StatusCode::CREATED, Json(relation)))
 }
 
@@ -110,31 +158,39 @@ pub async fn add_event_to_packet(
     ),
     responses(
         (status = 201, description = "Event packet successfully linked to event"),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Event or packet not found"),
+        (status = 409, description = "Already linked, or the event has no seats left for the packet's tickets"),
         (status = 500, description = "Internal server error")
     ),
     tag = "JoinPE"
 )]
 pub async fn add_packet_to_event(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
-    Json(payload): Json<AddPacketToEvent>,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
+    payload: Result<Json<AddPacketToEvent>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(id) = path?;
     if id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
-    let relation = state.join_repo.add_packet_to_event(id, payload).await?;
+    let Json(payload) = payload?;
+    let relation = state
+        .join_repo
+        .add_packet_to_event(id, payload, &caller)
+        .await?;
     Ok((StatusCode::CREATED, Json(relation)))
 }
 
 pub fn join_pe_manager_router() -> Router<Arc<AppState>> {
     Router::new()
         .route(
-            "/events/{id}/event-packets",
+            Route::EventPackets.path(),
             get(list_packets_for_event).post(add_packet_to_event),
         )
         .route(
-            "/event-packets/{id}/events",
+            Route::PacketEvents.path(),
             get(list_events_for_packet).post(add_event_to_packet),
         )
 }
@@ -0,0 +1,22 @@
+use crate::AppState;
+use crate::shared::metrics::record_pool;
+use axum::{Router, extract::State, http::header, response::IntoResponse, routing::get};
+use metrics_exporter_prometheus::PrometheusHandle;
+use std::sync::Arc;
+
+pub const METRICS_PATH: &str = "/metrics";
+
+pub fn metrics_router(handle: PrometheusHandle) -> Router<Arc<AppState>> {
+    Router::new().route(
+        METRICS_PATH,
+        get(move |State(state): State<Arc<AppState>>| async move {
+            record_pool(state.health_repo.pool_usage());
+
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                handle.render(),
+            )
+                .into_response()
+        }),
+    )
+}
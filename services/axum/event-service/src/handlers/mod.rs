@@ -1,6 +1,8 @@
 pub mod event;
 pub mod event_packets;
+pub mod health;
 pub mod join_pe;
+pub mod metrics;
 pub mod ticket;
 
 use crate::AppState;
@@ -8,18 +10,26 @@ use crate::handlers::event::event_manager_router;
 use crate::handlers::event_packets::event_packet_manager_router;
 use crate::handlers::join_pe::join_pe_manager_router;
 use crate::handlers::ticket::ticket_manager_router;
+use crate::shared::auth::require_auth;
 use crate::shared::doc::ApiDoc;
-use axum::Router;
+use crate::shared::error::problem_instance;
+use crate::shared::hypermedia::negotiate_media_type;
+use crate::shared::i18n::negotiate_locale;
+use axum::{Router, middleware};
 use std::sync::Arc;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-pub fn api_router() -> Router<Arc<AppState>> {
+pub fn api_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
         .merge(event_manager_router())
         .merge(event_packet_manager_router())
         .merge(ticket_manager_router())
         .merge(join_pe_manager_router())
+        .route_layer(middleware::from_fn_with_state(state, require_auth))
+        .layer(middleware::from_fn(negotiate_media_type))
+        .layer(middleware::from_fn(problem_instance))
+        .layer(middleware::from_fn(negotiate_locale))
 }
 
 pub fn swagger_router() -> Router<Arc<AppState>> {
@@ -1,19 +1,63 @@
 use crate::AppState;
-use crate::models::ticket::{CreateTicket, Ticket, UpdateTicket};
+use crate::models::check_in::{CheckIn, CheckInTicket};
+use crate::models::pagination::PaginationParams;
+use crate::models::ticket::{
+    BulkCreateTickets, CreateTicket, QrQuery, Ticket, UpdateTicket, VerifyTicketToken,
+};
+use crate::repositories::ticket_repo::TICKET_SORT_FIELDS;
+use crate::shared::auth::AuthUser;
+use crate::shared::base_url::BaseUrl;
 use crate::shared::error::ApiError;
 use crate::shared::links;
-use crate::shared::links::{Response, build_ticket_over_event, build_ticket_over_packet};
-use axum::extract::rejection::JsonRejection;
+use crate::shared::links::{
+    CollectionResponse, Response, build_collection, build_ticket_over_event,
+    build_ticket_over_packet,
+};
+use crate::shared::qr;
+use crate::shared::routes::Route;
+use crate::shared::ticket_token::TicketTokenClaims;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
 use axum::response::IntoResponse;
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
     routing::{get, post},
 };
 use std::sync::Arc;
 use validator::Validate;
 
+fn validate_ticket_pagination(params: &PaginationParams) -> Result<(), ApiError> {
+    params.validate()?;
+    params.ensure_cursor_fits::<Ticket>()?;
+    params
+        .ensure_sortable_by(TICKET_SORT_FIELDS)
+        .map_err(ApiError::BadRequest)
+}
+
+// typed in codes are checked before the lookup, so a typo is reported as
+// such instead of as a missing ticket
+fn check_code(state: &AppState, cod: &str) -> Result<(), ApiError> {
+    if state.ticket_codes.is_mistyped(cod) {
+        Err(ApiError::MistypedTicketCode)
+    } else {
+        Ok(())
+    }
+}
+
+// a client chosen code that looks like a mistyped generated one is refused,
+// check_code would otherwise reject every later lookup of the ticket
+fn with_generated_code(
+    state: &AppState,
+    mut payload: CreateTicket,
+) -> Result<CreateTicket, ApiError> {
+    match &payload.cod {
+        Some(cod) => check_code(state, cod)?,
+        None => payload.cod = Some(state.ticket_codes.generate()),
+    }
+    Ok(payload)
+}
+
 #[utoipa::path(
     get,
     path = "/api/event-manager/tickets/{cod}",
@@ -23,17 +67,21 @@ use validator::Validate;
     responses(
         (status = 200, description = "Ticket found", body = Response<Ticket>),
         (status = 404, description = "Ticket not found"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn get_ticket(
     State(state): State<Arc<AppState>>,
-    Path(cod): Path<String>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<String>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(cod) = path?;
+    check_code(&state, &cod)?;
     let ticket = state.ticket_repo.get_ticket(&cod).await?;
 
-    let ticket_response = links::build_simple_ticket(ticket, &state.base_url);
+    let ticket_response = links::build_simple_ticket(ticket, &base_url);
 
     Ok(Json(ticket_response))
 }
@@ -41,21 +89,38 @@ pub async fn get_ticket(
 #[utoipa::path(
     get,
     path = "/api/event-manager/tickets",
+    params(
+        ("page" = Option<i64>, Query, description = "Pagination page number"),
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `id` (the ticket code)"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
+    ),
     responses(
-        (status = 200, description = "List of all tickets", body = [Response<Ticket>]),
+        (status = 200, description = "A page of tickets", body = CollectionResponse<Ticket>),
+        (status = 400, description = "Invalid pagination or sort parameters"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn list_tickets(
     State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    query: Result<Query<PaginationParams>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let tickets = state.ticket_repo.list_tickets().await?;
+    let Query(params) = query?;
+    validate_ticket_pagination(&params)?;
 
-    let wrapped: Vec<Response<Ticket>> = tickets
-        .into_iter()
-        .map(|e| links::build_simple_ticket(e, &state.base_url))
-        .collect();
+    let tickets = state.ticket_repo.list_tickets(&params).await?;
+
+    let wrapped = build_collection::<CreateTicket, _>(
+        tickets.map(|e| links::build_simple_ticket(e, &base_url)),
+        "tickets",
+        &Route::Tickets.href(&base_url, &[]),
+        vec![],
+        &params,
+    );
 
     Ok(Json(wrapped))
 }
@@ -69,6 +134,7 @@ pub async fn list_tickets(
     ),
     responses(
         (status = 200, description = "Ticket updated", body = Response<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Ticket not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -76,16 +142,22 @@ pub async fn list_tickets(
 )]
 pub async fn update_ticket(
     State(state): State<Arc<AppState>>,
-    Path(cod): Path<String>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<String>, PathRejection>,
     payload: Result<Json<UpdateTicket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(cod) = path?;
     let Json(payload) = payload?;
 
     payload.validate()?;
 
-    let ticket = state.ticket_repo.update_ticket(&cod, payload).await?;
+    let ticket = state
+        .ticket_repo
+        .update_ticket(&cod, payload, &caller)
+        .await?;
 
-    let ticket_response = links::build_simple_ticket(ticket, &state.base_url);
+    let ticket_response = links::build_simple_ticket(ticket, &base_url);
 
     Ok(Json(ticket_response))
 }
@@ -96,21 +168,28 @@ pub async fn update_ticket(
     request_body = CreateTicket,
     responses(
         (status = 201, description = "Ticket created", body = Response<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
+        (status = 409, description = "No seats left or ticket code already used"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn create_ticket(
     State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
     payload: Result<Json<CreateTicket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
     let Json(payload) = payload?;
 
     payload.validate()?;
 
-    let ticket = state.ticket_repo.create_ticket(payload).await?;
+    let payload = with_generated_code(&state, payload)?;
 
-    let ticket_response = links::build_simple_ticket(ticket, &state.base_url);
+    let ticket = state.ticket_repo.create_ticket(payload, &caller).await?;
+
+    let ticket_response = links::build_simple_ticket(ticket, &base_url);
 
     Ok((StatusCode::CREATED, Json(ticket_response)))
 }
@@ -123,6 +202,7 @@ pub async fn create_ticket(
     ),
     responses(
         (status = 204, description = "Ticket deleted"),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Ticket not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -130,9 +210,11 @@ pub async fn create_ticket(
 )]
 pub async fn delete_ticket(
     State(state): State<Arc<AppState>>,
-    Path(cod): Path<String>,
+    caller: AuthUser,
+    path: Result<Path<String>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
-    state.ticket_repo.delete_ticket(&cod).await?;
+    let Path(cod) = path?;
+    state.ticket_repo.delete_ticket(&cod, &caller).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -146,23 +228,27 @@ pub async fn delete_ticket(
     responses(
         (status = 200, description = "Get ticket for event", body = Response<Ticket>),
         (status = 404, description = "Ticket not found"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn get_ticket_for_event(
     State(state): State<Arc<AppState>>,
-    Path((event_id, ticket_cod)): Path<(i32, String)>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<(i32, String)>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path((event_id, ticket_cod)) = path?;
     if event_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
+    check_code(&state, &ticket_cod)?;
     let ticket = state
         .ticket_repo
         .get_ticket_for_event(event_id, &ticket_cod)
         .await?;
 
-    let ticket_response = build_ticket_over_event(ticket, event_id, &state.base_url);
+    let ticket_response = build_ticket_over_event(ticket, event_id, &base_url);
 
     Ok(Json(ticket_response))
 }
@@ -171,27 +257,46 @@ pub async fn get_ticket_for_event(
     get,
     path = "/api/event-manager/events/{event_id}/tickets",
     params(
-        ("event_id" = i32, Path, description = "Event ID")
+        ("event_id" = i32, Path, description = "Event ID"),
+        ("page" = Option<i64>, Query, description = "Pagination page number"),
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `id` (the ticket code)"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
     ),
     responses(
-        (status = 200, description = "List of tickets for the event", body = [Response<Ticket>]),
+        (status = 200, description = "A page of tickets for the event", body = CollectionResponse<Ticket>),
+        (status = 400, description = "Invalid pagination or sort parameters"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn list_tickets_for_event(
     State(state): State<Arc<AppState>>,
-    Path(event_id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
+    query: Result<Query<PaginationParams>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(event_id) = path?;
+    let Query(params) = query?;
     if event_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
-    let tickets = state.ticket_repo.list_tickets_for_event(event_id).await?;
+    validate_ticket_pagination(&params)?;
 
-    let wrapped: Vec<Response<Ticket>> = tickets
-        .into_iter()
-        .map(|t| build_ticket_over_event(t, event_id, &state.base_url))
-        .collect();
+    let tickets = state
+        .ticket_repo
+        .list_tickets_for_event(event_id, &params)
+        .await?;
+
+    let wrapped = build_collection::<CreateTicket, _>(
+        tickets.map(|t| build_ticket_over_event(t, event_id, &base_url)),
+        "tickets",
+        &Route::EventTickets.href(&base_url, &[&event_id]),
+        vec![],
+        &params,
+    );
 
     Ok(Json(wrapped))
 }
@@ -206,6 +311,7 @@ pub async fn list_tickets_for_event(
     ),
     responses(
         (status = 200, description = "Ticket updated for event", body = Response<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Ticket not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -213,11 +319,14 @@ pub async fn list_tickets_for_event(
 )]
 pub async fn update_ticket_for_event(
     State(state): State<Arc<AppState>>,
-    Path((event_id, ticket_cod)): Path<(i32, String)>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<(i32, String)>, PathRejection>,
     payload: Result<Json<UpdateTicket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path((event_id, ticket_cod)) = path?;
     if event_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let Json(payload) = payload?;
 
@@ -225,10 +334,10 @@ pub async fn update_ticket_for_event(
 
     let ticket = state
         .ticket_repo
-        .update_ticket_for_event(event_id, &ticket_cod, payload)
+        .update_ticket_for_event(event_id, &ticket_cod, payload, &caller)
         .await?;
 
-    let ticket_response = build_ticket_over_event(ticket, event_id, &state.base_url);
+    let ticket_response = build_ticket_over_event(ticket, event_id, &base_url);
 
     Ok(Json(ticket_response))
 }
@@ -242,33 +351,93 @@ pub async fn update_ticket_for_event(
     ),
     responses(
         (status = 201, description = "Ticket created for event", body = Response<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
+        (status = 409, description = "No seats left or ticket code already used"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn create_ticket_for_event(
     State(state): State<Arc<AppState>>,
-    Path(event_id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
     payload: Result<Json<CreateTicket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(event_id) = path?;
     if event_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
 
     let Json(payload) = payload?;
 
     payload.validate()?;
 
+    let payload = with_generated_code(&state, payload)?;
+
     let ticket = state
         .ticket_repo
-        .create_ticket_for_event(event_id, payload)
+        .create_ticket_for_event(event_id, payload, &caller)
         .await?;
 
-    let ticket_response = build_ticket_over_event(ticket, event_id, &state.base_url);
+    let ticket_response = build_ticket_over_event(ticket, event_id, &base_url);
 
     Ok((StatusCode::CREATED, Json(ticket_response)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/event-manager/events/{event_id}/tickets/bulk",
+    request_body = BulkCreateTickets,
+    params(
+        ("event_id" = i32, Path, description = "Event ID")
+    ),
+    responses(
+        (status = 201, description = "Tickets created for event", body = CollectionResponse<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
+        (status = 409, description = "Not enough seats left"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Tickets"
+)]
+pub async fn create_tickets_for_event(
+    State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
+    payload: Result<Json<BulkCreateTickets>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Path(event_id) = path?;
+    if event_id < 0 {
+        return Err(ApiError::NegativeId);
+    }
+
+    let Json(payload) = payload?;
+
+    payload.validate()?;
+
+    let codes = state.ticket_codes.generate_many(payload.count);
+
+    let tickets = state
+        .ticket_repo
+        .create_tickets_for_event(event_id, codes, &caller)
+        .await?;
+
+    let wrapped: Vec<Response<Ticket>> = tickets
+        .into_iter()
+        .map(|t| build_ticket_over_event(t, event_id, &base_url))
+        .collect();
+
+    let response = links::build_ticket_batch(
+        wrapped,
+        Route::EventTickets.href(&base_url, &[&event_id]),
+        Route::EventTicketsBulk.href(&base_url, &[&event_id]),
+    );
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
 #[utoipa::path(
     delete,
     path = "/api/event-manager/events/{event_id}/tickets/{ticket_cod}",
@@ -278,6 +447,7 @@ pub async fn create_ticket_for_event(
     ),
     responses(
         (status = 204, description = "Ticket deleted for event"),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Ticket not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -285,14 +455,16 @@ pub async fn create_ticket_for_event(
 )]
 pub async fn delete_ticket_for_event(
     State(state): State<Arc<AppState>>,
-    Path((event_id, ticket_cod)): Path<(i32, String)>,
+    caller: AuthUser,
+    path: Result<Path<(i32, String)>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path((event_id, ticket_cod)) = path?;
     if event_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     state
         .ticket_repo
-        .delete_ticket_for_event(event_id, ticket_cod)
+        .delete_ticket_for_event(event_id, ticket_cod, &caller)
         .await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -301,27 +473,46 @@ pub async fn delete_ticket_for_event(
     get,
     path = "/api/event-manager/event-packets/{packet_id}/tickets",
     params(
-        ("packet_id" = i32, Path, description = "Packet ID")
+        ("packet_id" = i32, Path, description = "Packet ID"),
+        ("page" = Option<i64>, Query, description = "Pagination page number"),
+        ("items_per_page" = Option<i64>, Query, description = "Items per page for pagination"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `id` (the ticket code)"),
+        ("order" = Option<String>, Query, description = "Sort direction, `asc` (default) or `desc`"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next` link, switches to keyset pagination"),
+        ("limit" = Option<i64>, Query, description = "Items per page for keyset pagination")
     ),
     responses(
-        (status = 200, description = "List of tickets for the packet", body = [Response<Ticket>]),
+        (status = 200, description = "A page of tickets for the packet", body = CollectionResponse<Ticket>),
+        (status = 400, description = "Invalid pagination or sort parameters"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn list_tickets_for_packet(
     State(state): State<Arc<AppState>>,
-    Path(packet_id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<i32>, PathRejection>,
+    query: Result<Query<PaginationParams>, QueryRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(packet_id) = path?;
+    let Query(params) = query?;
     if packet_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
-    let tickets = state.ticket_repo.list_tickets_for_packet(packet_id).await?;
+    validate_ticket_pagination(&params)?;
 
-    let wrapped: Vec<Response<Ticket>> = tickets
-        .into_iter()
-        .map(|t| build_ticket_over_packet(t, packet_id, &state.base_url))
-        .collect();
+    let tickets = state
+        .ticket_repo
+        .list_tickets_for_packet(packet_id, &params)
+        .await?;
+
+    let wrapped = build_collection::<CreateTicket, _>(
+        tickets.map(|t| build_ticket_over_packet(t, packet_id, &base_url)),
+        "tickets",
+        &Route::PacketTickets.href(&base_url, &[&packet_id]),
+        vec![],
+        &params,
+    );
 
     Ok(Json(wrapped))
 }
@@ -336,23 +527,27 @@ pub async fn list_tickets_for_packet(
     responses(
         (status = 200, description = "Get ticket for packet", body = Response<Ticket>),
         (status = 404, description = "Ticket not found"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn get_ticket_for_packet(
     State(state): State<Arc<AppState>>,
-    Path((packet_id, ticket_cod)): Path<(i32, String)>,
+    BaseUrl(base_url): BaseUrl,
+    path: Result<Path<(i32, String)>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path((packet_id, ticket_cod)) = path?;
     if packet_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
+    check_code(&state, &ticket_cod)?;
     let ticket = state
         .ticket_repo
         .get_ticket_for_packet(packet_id, &ticket_cod)
         .await?;
 
-    let ticket_response = build_ticket_over_packet(ticket, packet_id, &state.base_url);
+    let ticket_response = build_ticket_over_packet(ticket, packet_id, &base_url);
 
     Ok(Json(ticket_response))
 }
@@ -366,32 +561,92 @@ pub async fn get_ticket_for_packet(
     ),
     responses(
         (status = 201, description = "Ticket created for packet", body = Response<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
+        (status = 409, description = "No seats left or ticket code already used"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Tickets"
 )]
 pub async fn create_ticket_for_packet(
     State(state): State<Arc<AppState>>,
-    Path(packet_id): Path<i32>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
     payload: Result<Json<CreateTicket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path(packet_id) = path?;
     if packet_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let Json(payload) = payload?;
 
     payload.validate()?;
 
+    let payload = with_generated_code(&state, payload)?;
+
     let ticket = state
         .ticket_repo
-        .create_ticket_for_packet(packet_id, payload)
+        .create_ticket_for_packet(packet_id, payload, &caller)
         .await?;
 
-    let ticket_response = build_ticket_over_packet(ticket, packet_id, &state.base_url);
+    let ticket_response = build_ticket_over_packet(ticket, packet_id, &base_url);
 
     Ok((StatusCode::CREATED, Json(ticket_response)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/event-manager/event-packets/{packet_id}/tickets/bulk",
+    request_body = BulkCreateTickets,
+    params(
+        ("packet_id" = i32, Path, description = "Packet ID")
+    ),
+    responses(
+        (status = 201, description = "Tickets created for packet", body = CollectionResponse<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
+        (status = 409, description = "Not enough seats left"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Tickets"
+)]
+pub async fn create_tickets_for_packet(
+    State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<i32>, PathRejection>,
+    payload: Result<Json<BulkCreateTickets>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Path(packet_id) = path?;
+    if packet_id < 0 {
+        return Err(ApiError::NegativeId);
+    }
+
+    let Json(payload) = payload?;
+
+    payload.validate()?;
+
+    let codes = state.ticket_codes.generate_many(payload.count);
+
+    let tickets = state
+        .ticket_repo
+        .create_tickets_for_packet(packet_id, codes, &caller)
+        .await?;
+
+    let wrapped: Vec<Response<Ticket>> = tickets
+        .into_iter()
+        .map(|t| build_ticket_over_packet(t, packet_id, &base_url))
+        .collect();
+
+    let response = links::build_ticket_batch(
+        wrapped,
+        Route::PacketTickets.href(&base_url, &[&packet_id]),
+        Route::PacketTicketsBulk.href(&base_url, &[&packet_id]),
+    );
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
 #[utoipa::path(
     put,
     path = "/api/event-manager/event-packets/{packet_id}/tickets/{ticket_cod}",
@@ -402,6 +657,7 @@ pub async fn create_ticket_for_packet(
     ),
     responses(
         (status = 200, description = "Ticket updated for packet", body = Response<Ticket>),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Ticket not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -409,11 +665,14 @@ pub async fn create_ticket_for_packet(
 )]
 pub async fn update_ticket_for_packet(
     State(state): State<Arc<AppState>>,
-    Path((packet_id, ticket_cod)): Path<(i32, String)>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<(i32, String)>, PathRejection>,
     payload: Result<Json<UpdateTicket>, JsonRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path((packet_id, ticket_cod)) = path?;
     if packet_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     let Json(payload) = payload?;
 
@@ -421,10 +680,10 @@ pub async fn update_ticket_for_packet(
 
     let ticket = state
         .ticket_repo
-        .update_ticket_for_packet(packet_id, &ticket_cod, payload)
+        .update_ticket_for_packet(packet_id, &ticket_cod, payload, &caller)
         .await?;
 
-    let ticket_response = build_ticket_over_packet(ticket, packet_id, &state.base_url);
+    let ticket_response = build_ticket_over_packet(ticket, packet_id, &base_url);
 
     Ok(Json(ticket_response))
 }
@@ -438,6 +697,7 @@ pub async fn update_ticket_for_packet(
     ),
     responses(
         (status = 204, description = "Ticket deleted for packet"),
+        (status = 403, description = "Event or packet is owned by another user"),
         (status = 404, description = "Ticket not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -445,23 +705,138 @@ pub async fn update_ticket_for_packet(
 )]
 pub async fn delete_ticket_for_packet(
     State(state): State<Arc<AppState>>,
-    Path((packet_id, ticket_cod)): Path<(i32, String)>,
+    caller: AuthUser,
+    path: Result<Path<(i32, String)>, PathRejection>,
 ) -> Result<impl IntoResponse, ApiError> {
+    let Path((packet_id, ticket_cod)) = path?;
     if packet_id < 0 {
-        return Err(ApiError::BadRequest("ID cannot be negative".into()));
+        return Err(ApiError::NegativeId);
     }
     state
         .ticket_repo
-        .delete_ticket_for_packet(packet_id, &ticket_cod)
+        .delete_ticket_for_packet(packet_id, &ticket_cod, &caller)
         .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/event-manager/tickets/{cod}/check-in",
+    request_body(content = Option<CheckInTicket>, description = "The event being entered, required for packet tickets"),
+    params(
+        ("cod" = String, Path, description = "Ticket code")
+    ),
+    responses(
+        (status = 200, description = "Ticket checked in", body = Response<CheckIn>),
+        (status = 403, description = "Event or packet is owned by another user"),
+        (status = 404, description = "Ticket not found"),
+        (status = 409, description = "Ticket already used for this event"),
+        (status = 422, description = "Ticket code mistyped, not valid for the event, or no event given for a packet ticket"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Tickets"
+)]
+pub async fn check_in_ticket(
+    State(state): State<Arc<AppState>>,
+    BaseUrl(base_url): BaseUrl,
+    caller: AuthUser,
+    path: Result<Path<String>, PathRejection>,
+    payload: Result<Option<Json<CheckInTicket>>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Path(cod) = path?;
+    let payload = payload?.map(|Json(payload)| payload).unwrap_or_default();
+
+    if payload.id_event.is_some_and(|id| id < 0) {
+        return Err(ApiError::NegativeId);
+    }
+    check_code(&state, &cod)?;
+
+    let check_in = state
+        .ticket_repo
+        .check_in(&cod, payload.id_event, &caller)
+        .await?;
+
+    let check_in_response = links::build_check_in(check_in, &base_url);
+
+    Ok(Json(check_in_response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/event-manager/tickets/{cod}/qr",
+    params(
+        ("cod" = String, Path, description = "Ticket code"),
+        ("format" = Option<String>, Query, description = "Image format: `png` (default) or `svg`")
+    ),
+    responses(
+        (status = 200, description = "QR code encoding a signed ticket token", content(
+            (Vec<u8> = "image/png"),
+            (String = "image/svg+xml")
+        )),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 404, description = "Ticket not found"),
+        (status = 422, description = "Ticket code mistyped, its check character doesn't match"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Tickets"
+)]
+pub async fn get_ticket_qr(
+    State(state): State<Arc<AppState>>,
+    path: Result<Path<String>, PathRejection>,
+    query: Result<Query<QrQuery>, QueryRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Path(cod) = path?;
+    let Query(query) = query?;
+    let format = query.format.unwrap_or_default();
+
+    check_code(&state, &cod)?;
+    let ticket = state.ticket_repo.get_ticket(&cod).await?;
+    let token = state.ticket_signer.sign(&ticket);
+
+    let image = qr::render(&token, format).map_err(ApiError::Internal)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            // the token inside expires, so the image must not be reused
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        image,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/event-manager/ticket-tokens/verify",
+    request_body = VerifyTicketToken,
+    responses(
+        (status = 200, description = "Token is valid", body = TicketTokenClaims),
+        (status = 400, description = "Invalid request body"),
+        (status = 422, description = "Token is malformed, tampered with or expired"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Tickets"
+)]
+pub async fn verify_ticket_token(
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<VerifyTicketToken>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Json(payload) = payload?;
+    payload.validate()?;
+
+    let claims = state.ticket_signer.verify(&payload.token)?;
+
+    Ok(Json(claims))
+}
+
 pub fn ticket_manager_router() -> Router<Arc<AppState>> {
     Router::new()
-        .route("/tickets", post(create_ticket).get(list_tickets))
+        .route(Route::Tickets.path(), post(create_ticket).get(list_tickets))
         .route(
-            "/tickets/{cod}",
+            Route::Ticket.path(),
             get(get_ticket).put(update_ticket).delete(delete_ticket),
         )
+        .route(Route::TicketTokenVerify.path(), post(verify_ticket_token))
+        .route(Route::TicketQr.path(), get(get_ticket_qr))
+        .route(Route::TicketCheckIn.path(), post(check_in_ticket))
 }
@@ -3,17 +3,28 @@ pub mod models;
 pub mod repositories;
 pub mod shared;
 
-use crate::repositories::event_packets_repo::EventPacketRepo;
-use crate::repositories::event_repo::EventRepo;
-use crate::repositories::join_pe_repo::JoinPeRepo;
-use crate::repositories::ticket_repo::TicketRepo;
+use crate::repositories::event_packets_repo::EventPacketRepository;
+use crate::repositories::event_repo::EventRepository;
+use crate::repositories::health_repo::HealthRepo;
+use crate::repositories::join_pe_repo::JoinPeRepository;
+use crate::repositories::ticket_repo::TicketRepository;
+use crate::shared::auth::JwtVerifier;
+use crate::shared::base_url::BaseUrlResolver;
+use crate::shared::shutdown::ShutdownState;
+use crate::shared::ticket_code::TicketCodeGenerator;
+use crate::shared::ticket_token::TicketSigner;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub event_repo: Arc<EventRepo>,
-    pub event_packet_repo: Arc<EventPacketRepo>,
-    pub ticket_repo: Arc<TicketRepo>,
-    pub join_repo: Arc<JoinPeRepo>,
-    pub base_url: String,
+    pub event_repo: Arc<dyn EventRepository>,
+    pub event_packet_repo: Arc<dyn EventPacketRepository>,
+    pub ticket_repo: Arc<dyn TicketRepository>,
+    pub join_repo: Arc<dyn JoinPeRepository>,
+    pub health_repo: Arc<HealthRepo>,
+    pub jwt: Arc<JwtVerifier>,
+    pub ticket_codes: Arc<TicketCodeGenerator>,
+    pub ticket_signer: Arc<TicketSigner>,
+    pub base_url: Arc<BaseUrlResolver>,
+    pub shutdown: Arc<ShutdownState>,
 }
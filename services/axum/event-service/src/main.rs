@@ -1,58 +1,188 @@
-use anyhow::Result;
-use axum::{Router, extract::State, routing::get};
+use anyhow::{Context, Result, anyhow, bail};
+use axum::{Router, middleware};
 use event_service::{
     AppState, handlers,
     repositories::{
-        event_packets_repo::EventPacketRepo, event_repo::EventRepo, join_pe_repo::JoinPeRepo,
-        ticket_repo::TicketRepo,
+        event_packets_repo::EventPacketRepo, event_repo::EventRepo, health_repo::HealthRepo,
+        join_pe_repo::JoinPeRepo, ticket_repo::TicketRepo,
+    },
+    shared::{
+        auth::JwtVerifier, db, metrics, request_id, settings::Settings, shutdown, telemetry,
+        ticket_code::TicketCodeGenerator, ticket_token::TicketSigner,
     },
 };
 use sqlx::postgres::PgPoolOptions;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::trace::TraceLayer;
-use tracing::{Level, info};
+use tracing::{info, warn};
+
+// how long the pool gets to close once the drain timeout has passed
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .compact()
-        .init();
+    let settings = Settings::load().context("Failed to load configuration")?;
+    if let Err(problems) = settings.validate() {
+        bail!("Invalid configuration:\n  - {}", problems.join("\n  - "));
+    }
+
+    let telemetry = telemetry::init(&settings).context("Failed to set up trace export")?;
 
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL env var is not set!");
+    let command = std::env::args().nth(1);
 
+    let database = &settings.database;
     let pool = PgPoolOptions::new()
-        .max_connections(50)
-        .connect(&db_url)
-        .await?;
+        .max_connections(database.max_connections)
+        .min_connections(database.min_connections)
+        .acquire_timeout(database.acquire_timeout())
+        .idle_timeout(database.idle_timeout())
+        .connect(&database.url)
+        .await
+        .context("Failed to connect to the database")?;
 
     info!("{:<12} - Database connection pool created.", "DB");
 
+    // `migrate` and `seed` do their job and exit, anything else serves
+    match command.as_deref() {
+        None | Some("serve") => {}
+        Some("migrate") => {
+            db::migrate(&pool).await?;
+            info!("{:<12} - Migrations applied.", "DB");
+            return Ok(());
+        }
+        Some("seed") => {
+            db::migrate(&pool).await?;
+            db::seed(&pool).await?;
+            info!("{:<12} - Demo data loaded.", "DB");
+            return Ok(());
+        }
+        Some(other) => bail!(
+            "Unknown command `{}`, expected serve, migrate or seed",
+            other
+        ),
+    }
+
+    if database.migrate_on_startup {
+        db::migrate(&pool).await?;
+        info!("{:<12} - Migrations applied.", "DB");
+    }
+
+    let tickets = &settings.tickets;
+    let ticket_codes = TicketCodeGenerator::new(&tickets.code_prefix, tickets.code_length)
+        .map_err(|e| anyhow!("Invalid ticket code format: {}", e))?;
+
+    let base_url = settings
+        .server
+        .base_url_resolver()
+        .map_err(|e| anyhow!("Invalid trusted proxy: {}", e))?;
+
     let app_state = Arc::new(AppState {
         event_repo: Arc::new(EventRepo::new(pool.clone())),
         event_packet_repo: Arc::new(EventPacketRepo::new(pool.clone())),
         ticket_repo: Arc::new(TicketRepo::new(pool.clone())),
         join_repo: Arc::new(JoinPeRepo::new(pool.clone())),
-        base_url: "http://localhost:8001/api/event-manager".to_string(),
+        health_repo: Arc::new(HealthRepo::new(pool.clone())),
+        jwt: Arc::new(JwtVerifier::new(settings.auth.jwt_secret.as_bytes())),
+        ticket_codes: Arc::new(ticket_codes),
+        ticket_signer: Arc::new(TicketSigner::new(
+            tickets.signing_key.as_str(),
+            tickets.token_ttl_secs,
+        )),
+        base_url: Arc::new(base_url),
+        shutdown: Arc::default(),
     });
+    let shutdown_state = app_state.shutdown.clone();
 
-    let app = Router::new()
-        .route("/api", get(check_state))
-        .nest("/api/event-manager", handlers::api_router())
-        .merge(handlers::swagger_router())
-        .layer(TraceLayer::new_for_http())
+    let mut app = Router::new().merge(handlers::health::health_router()).nest(
+        "/api/event-manager",
+        handlers::api_router(app_state.clone()),
+    );
+    if settings.features.swagger_ui {
+        app = app.merge(handlers::swagger_router());
+    }
+    if settings.features.metrics {
+        let handle =
+            metrics::install_recorder().context("Failed to install the metrics recorder")?;
+        app = app
+            .merge(handlers::metrics::metrics_router(handle))
+            .layer(middleware::from_fn(metrics::track_requests));
+    }
+    let app = app
+        .layer(TraceLayer::new_for_http().make_span_with(request_id::request_span))
+        .layer(middleware::from_fn(request_id::propagate_request_id))
         .with_state(app_state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+    let addr = settings.server.listen_addr();
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind {}", addr))?;
     info!("{:<12} - {:?}\n", "LISTENING", listener.local_addr());
 
-    axum::serve(listener, app).await?;
+    let server = &settings.server;
+    let stop = {
+        let shutdown_state = shutdown_state.clone();
+        let delay = server.shutdown_delay();
+        async move {
+            shutdown::signal().await;
+            info!("{:<12} - Stop requested, readiness now fails.", "SHUTDOWN");
+            shutdown_state.begin();
+            tokio::time::sleep(delay).await;
+            info!("{:<12} - Refusing new connections, draining.", "SHUTDOWN");
+        }
+    };
 
-    Ok(())
-}
+    // the peer address decides whether forwarded headers are trusted
+    let serve = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(stop)
+    .into_future();
 
-async fn check_state(State(status): State<Arc<AppState>>) -> &'static str {
-    match status.event_repo.check().await {
-        Ok(_) => "PostgreSQL works! :p",
-        Err(_) => "PostgreSQL DOESN'T work! :(",
+    // past the drain timeout we stop waiting. the connection tasks of the
+    // requests still running keep going until the runtime is dropped when
+    // main returns, and they may hold pool connections meanwhile
+    let deadline = async {
+        shutdown_state.begun().await;
+        tokio::time::sleep(server.shutdown_delay() + server.drain_timeout()).await;
+    };
+
+    let drained = tokio::select! {
+        served = serve => {
+            served?;
+            true
+        }
+        _ = deadline => {
+            warn!(
+                "{:<12} - Requests still running after {:?}, dropping them.",
+                "SHUTDOWN",
+                server.drain_timeout()
+            );
+            false
+        }
+    };
+
+    // `close` waits for every connection to be returned, which the requests
+    // left behind might never do
+    if drained {
+        pool.close().await;
+        info!("{:<12} - Database connections closed.", "SHUTDOWN");
+    } else if tokio::time::timeout(CLOSE_TIMEOUT, pool.close())
+        .await
+        .is_ok()
+    {
+        info!("{:<12} - Database connections closed.", "SHUTDOWN");
+    } else {
+        warn!(
+            "{:<12} - Database connections still in use, not waiting for them.",
+            "SHUTDOWN"
+        );
     }
+
+    telemetry.shutdown();
+
+    Ok(())
 }
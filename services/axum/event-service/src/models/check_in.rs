@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct CheckIn {
+    pub cod: String,
+    #[sqlx(rename = "evenimentid")]
+    #[serde(rename = "evenimentid")]
+    pub id_event: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub checked_in_at: OffsetDateTime,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CheckInTicket {
+    // required for packet tickets, since they grant entry to every event in the packet
+    #[serde(rename = "evenimentid")]
+    pub id_event: Option<i32>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct CheckInStats {
+    #[sqlx(rename = "evenimentid")]
+    #[serde(rename = "evenimentid")]
+    pub id_event: i32,
+    pub bilete_emise: i64,
+    pub intrari: i64,
+    pub ramase: i64,
+}
@@ -1,9 +1,11 @@
+use crate::models::pagination::PaginationParams;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Deserialize, Serialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, ToSchema)]
 pub struct Event {
     pub id: i32,
     pub id_owner: i32,
@@ -13,63 +15,132 @@ pub struct Event {
     #[serde(rename = "numarlocuri")]
     #[sqlx(rename = "numarlocuri")]
     pub locuri: Option<i32>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub data_inceput: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub data_sfarsit: Option<OffsetDateTime>,
+    pub fus_orar: Option<String>,
+    // capacity minus the tickets already issued, null when the capacity is unlimited
+    pub locuri_disponibile: Option<i64>,
 }
 
 #[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate)]
+#[validate(schema(function = "validate_create_event"))]
 #[serde(deny_unknown_fields)]
 pub struct CreateEvent {
-    pub id_owner: i32,
-    #[validate(length(
-        min = 3,
-        max = 100,
-        message = "Name must be between 3 and 100 characters"
-    ))]
+    #[validate(length(min = 3, max = 100, code = "name_length"))]
+    #[schema(min_length = 3, max_length = 100)]
     pub nume: String,
-    #[validate(length(max = 255, message = "Location must be less than 255 characters"))]
+    #[validate(length(max = 255, code = "location_length"))]
+    #[schema(max_length = 255)]
     pub locatie: Option<String>,
-    #[validate(length(
-        min = 10,
-        max = 500,
-        message = "Description must be between 10 and 500 characters"
-    ))]
+    #[validate(length(min = 10, max = 500, code = "description_length"))]
+    #[schema(min_length = 10, max_length = 500)]
     pub descriere: Option<String>,
-    #[validate(range(min = 1, max = 50000, message = "Seats must be between 1 and 50,000"))]
+    #[validate(range(min = 1, max = 50000, code = "seats_range"))]
+    #[schema(minimum = 1, maximum = 50000)]
     #[serde(rename = "numarlocuri")]
     #[sqlx(rename = "numarlocuri")]
     pub locuri: Option<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub data_inceput: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub data_sfarsit: Option<OffsetDateTime>,
+    #[validate(custom(function = "validate_timezone"))]
+    pub fus_orar: Option<String>,
 }
 
+// fields left out or null keep their current value, so an end time or a
+// timezone once set can be replaced but not cleared
 #[derive(Debug, Deserialize, Serialize, FromRow, ToSchema, Validate)]
+#[validate(schema(function = "validate_update_event"))]
 #[serde(deny_unknown_fields)]
 pub struct UpdateEvent {
-    pub id_owner: Option<i32>,
-    #[validate(length(
-        min = 3,
-        max = 100,
-        message = "Name must be between 3 and 100 characters"
-    ))]
+    #[validate(length(min = 3, max = 100, code = "name_length"))]
+    #[schema(min_length = 3, max_length = 100)]
     pub nume: String,
-    #[validate(length(max = 255, message = "Location must be less than 255 characters"))]
+    #[validate(length(max = 255, code = "location_length"))]
+    #[schema(max_length = 255)]
     pub locatie: Option<String>,
-    #[validate(length(
-        min = 10,
-        max = 500,
-        message = "Description must be between 10 and 500 characters"
-    ))]
+    #[validate(length(min = 10, max = 500, code = "description_length"))]
+    #[schema(min_length = 10, max_length = 500)]
     pub descriere: Option<String>,
-    #[validate(range(min = 1, max = 50000, message = "Seats must be between 1 and 50,000"))]
+    #[validate(range(min = 1, max = 50000, code = "seats_range"))]
+    #[schema(minimum = 1, maximum = 50000)]
     #[serde(rename = "numarlocuri")]
     #[sqlx(rename = "numarlocuri")]
     pub locuri: Option<i32>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub data_inceput: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub data_sfarsit: Option<OffsetDateTime>,
+    #[validate(custom(function = "validate_timezone"))]
+    pub fus_orar: Option<String>,
 }
 
 #[derive(Deserialize, Clone, ToSchema, Validate)]
+#[validate(schema(function = "validate_event_query"))]
 #[serde(deny_unknown_fields)]
 pub struct EventQuery {
-    #[validate(length(max = 50, message = "Location filter must be less than 50 characters"))]
+    #[validate(length(max = 50, code = "location_filter_length"))]
     #[serde(rename = "location")]
     pub locatie: Option<String>,
-    #[validate(length(max = 50, message = "Name filter must be less than 50 characters"))]
+    #[validate(length(max = 50, code = "name_filter_length"))]
     #[serde(rename = "name")]
     pub nume: Option<String>,
+    #[validate(range(min = 1, code = "available_tickets_min"))]
+    #[serde(rename = "available_tickets")]
+    pub bilete: Option<i32>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+    pub upcoming: Option<bool>,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub paginare: PaginationParams,
+}
+
+fn validate_interval(
+    start: Option<OffsetDateTime>,
+    end: Option<OffsetDateTime>,
+) -> Result<(), ValidationError> {
+    match (start, end) {
+        (Some(start), Some(end)) if end <= start => Err(ValidationError::new("invalid_interval")),
+        _ => Ok(()),
+    }
+}
+
+fn validate_create_event(event: &CreateEvent) -> Result<(), ValidationError> {
+    validate_interval(Some(event.data_inceput), event.data_sfarsit)
+}
+
+fn validate_update_event(event: &UpdateEvent) -> Result<(), ValidationError> {
+    validate_interval(event.data_inceput, event.data_sfarsit)
+}
+
+fn validate_event_query(query: &EventQuery) -> Result<(), ValidationError> {
+    match (query.from, query.to) {
+        (Some(from), Some(to)) if to < from => Err(ValidationError::new("invalid_range")),
+        _ => Ok(()),
+    }
+}
+
+// there is no tz database in the service, so only the shape of an
+// IANA name is checked (e.g. `UTC`, `Europe/Bucharest`, `America/Argentina/Salta`)
+fn validate_timezone(tz: &str) -> Result<(), ValidationError> {
+    let well_formed = !tz.is_empty()
+        && tz.len() <= 64
+        && tz.split('/').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        });
+
+    if well_formed {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_timezone"))
+    }
 }
@@ -1,10 +1,10 @@
+use crate::models::pagination::PaginationParams;
 use serde::{Deserialize, Serialize};
-use serde_with::{DisplayFromStr, serde_as};
 use sqlx::prelude::FromRow;
 use utoipa::ToSchema;
 use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct EventPackets {
     pub id: i32,
     pub id_owner: i32,
@@ -12,49 +12,41 @@ pub struct EventPackets {
     pub locatie: Option<String>,
     pub descriere: Option<String>,
     pub numarlocuri: Option<i32>,
+    // capacity minus the tickets already issued, null when the capacity is unlimited
+    pub locuri_disponibile: Option<i64>,
 }
 
 #[derive(Debug, Deserialize, ToSchema, Validate)]
 #[serde(deny_unknown_fields)]
 pub struct CreateEventPacket {
-    pub id_owner: i32,
-    #[validate(length(
-        min = 3,
-        max = 100,
-        message = "Name must be between 3 and 100 characters"
-    ))]
+    #[validate(length(min = 3, max = 100, code = "name_length"))]
+    #[schema(min_length = 3, max_length = 100)]
     pub nume: String,
-    #[validate(length(max = 255, message = "Location must be less than 255 characters"))]
+    #[validate(length(max = 255, code = "location_length"))]
+    #[schema(max_length = 255)]
     pub locatie: Option<String>,
-    #[validate(length(
-        min = 10,
-        max = 500,
-        message = "Description must be between 10 and 500 characters"
-    ))]
+    #[validate(length(min = 10, max = 500, code = "description_length"))]
+    #[schema(min_length = 10, max_length = 500)]
     pub descriere: Option<String>,
-    #[validate(range(min = 1, max = 50000, message = "Seats must be between 1 and 50,000"))]
+    #[validate(range(min = 1, max = 50000, code = "seats_range"))]
+    #[schema(minimum = 1, maximum = 50000)]
     pub numarlocuri: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, ToSchema, Validate)]
 #[serde(deny_unknown_fields)]
 pub struct UpdateEventPacket {
-    pub id_owner: Option<i32>,
-    #[validate(length(
-        min = 3,
-        max = 100,
-        message = "Name must be between 3 and 100 characters"
-    ))]
+    #[validate(length(min = 3, max = 100, code = "name_length"))]
+    #[schema(min_length = 3, max_length = 100)]
     pub nume: String,
-    #[validate(length(max = 255, message = "Location must be less than 255 characters"))]
+    #[validate(length(max = 255, code = "location_length"))]
+    #[schema(max_length = 255)]
     pub locatie: Option<String>,
-    #[validate(length(
-        min = 10,
-        max = 500,
-        message = "Description must be between 10 and 500 characters"
-    ))]
+    #[validate(length(min = 10, max = 500, code = "description_length"))]
+    #[schema(min_length = 10, max_length = 500)]
     pub descriere: Option<String>,
-    #[validate(range(min = 1, max = 50000, message = "Seats must be between 1 and 50,000"))]
+    #[validate(range(min = 1, max = 50000, code = "seats_range"))]
+    #[schema(minimum = 1, maximum = 50000)]
     pub numarlocuri: Option<i32>,
 }
 
@@ -62,32 +54,12 @@ pub struct UpdateEventPacket {
 #[serde(deny_unknown_fields)]
 pub struct EventPacketQuery {
     #[serde(rename = "type")]
-    #[validate(length(
-        min = 3,
-        max = 50,
-        message = "Description filter must be less than 50 characters"
-    ))]
+    #[validate(length(min = 3, max = 50, code = "description_filter_length"))]
     pub descriere: Option<String>,
-    #[validate(range(min = 1, message = "Available tickets must be at least 1"))]
+    #[validate(range(min = 1, code = "available_tickets_min"))]
     #[serde(rename = "available_tickets")]
     pub bilete: Option<i32>,
     #[serde(flatten)]
     #[validate(nested)]
     pub paginare: PaginationParams,
 }
-
-#[serde_as]
-#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
-#[serde(deny_unknown_fields)]
-pub struct PaginationParams {
-    #[validate(range(min = 1, message = "Page must be at least 1"))]
-    #[serde_as(as = "Option<DisplayFromStr>")]
-    pub page: Option<i64>,
-    #[serde_as(as = "Option<DisplayFromStr>")]
-    #[validate(range(
-        min = 1,
-        max = 100,
-        message = "Items per page must be between 1 and 100"
-    ))]
-    pub items_per_page: Option<i64>,
-}
@@ -1,4 +1,7 @@
+pub mod check_in;
 pub mod event;
 pub mod event_packets;
+pub mod health;
 pub mod join_pe;
+pub mod pagination;
 pub mod ticket;
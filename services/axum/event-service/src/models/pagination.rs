@@ -0,0 +1,343 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+pub const DEFAULT_PAGE: i64 = 1;
+pub const DEFAULT_ITEMS_PER_PAGE: i64 = 10;
+// keeps `offset()` far from overflowing an i64 at 100 items per page
+pub const MAX_PAGE: i64 = 1_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    Name,
+    Location,
+    Seats,
+    Id,
+}
+
+impl SortField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortField::Name => "name",
+            SortField::Location => "location",
+            SortField::Seats => "seats",
+            SortField::Id => "id",
+        }
+    }
+
+    // the kind of sort key a cursor carries, `id` sorts by the tie breaker alone
+    fn key_kind(self) -> Option<KeyKind> {
+        match self {
+            SortField::Name | SortField::Location => Some(KeyKind::Text),
+            SortField::Seats => Some(KeyKind::Int),
+            SortField::Id => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CursorKey {
+    Int(i64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Int,
+    Text,
+}
+
+impl CursorKey {
+    pub fn kind(&self) -> KeyKind {
+        match self {
+            CursorKey::Int(_) => KeyKind::Int,
+            CursorKey::Text(_) => KeyKind::Text,
+        }
+    }
+}
+
+// the sort key and the tie breaker of the last row a client has seen, plus
+// the sort it was issued for. it travels as base64url JSON and is opaque to
+// clients, the next page starts strictly after this row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<SortField>,
+    #[serde(rename = "o")]
+    pub order: SortOrder,
+    #[serde(rename = "k", default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<CursorKey>,
+    #[serde(rename = "t")]
+    pub tie: CursorKey,
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_vec(self).map_err(|_| fmt::Error)?;
+        f.write_str(&URL_SAFE_NO_PAD.encode(json))
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| "invalid cursor".to_string())
+    }
+}
+
+// implemented by every row type a listing can page through by cursor,
+// the keys must match the sort expressions the repository orders by
+pub trait SortKey {
+    // the kind of key `tie_key` returns
+    const TIE_KIND: KeyKind;
+
+    fn sort_key(&self, field: SortField) -> Option<CursorKey>;
+    fn tie_key(&self) -> CursorKey;
+}
+
+// sorting lives next to paging because serde can't flatten two structs
+// that both deny unknown fields into the same query.
+// `page`/`items_per_page` select offset mode, `cursor`/`limit` keyset mode
+#[serde_as]
+#[derive(Debug, Deserialize, Clone, Default, ToSchema, Validate)]
+#[validate(schema(function = "validate_pagination_mode"))]
+#[serde(deny_unknown_fields)]
+pub struct PaginationParams {
+    #[validate(range(min = 1, max = MAX_PAGE, code = "page_range"))]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub page: Option<i64>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[validate(range(min = 1, max = 100, code = "items_per_page_range"))]
+    pub items_per_page: Option<i64>,
+    pub sort_by: Option<SortField>,
+    pub order: Option<SortOrder>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[schema(value_type = Option<String>)]
+    pub cursor: Option<Cursor>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[validate(range(min = 1, max = 100, code = "limit_range"))]
+    pub limit: Option<i64>,
+}
+
+impl PaginationParams {
+    pub fn is_keyset(&self) -> bool {
+        self.cursor.is_some() || self.limit.is_some()
+    }
+
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(DEFAULT_PAGE).max(DEFAULT_PAGE)
+    }
+
+    pub fn items_per_page(&self) -> i64 {
+        self.limit
+            .or(self.items_per_page)
+            .unwrap_or(DEFAULT_ITEMS_PER_PAGE)
+            .max(1)
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1) * self.items_per_page()
+    }
+
+    // a cursor keeps the sort it was issued for
+    pub fn sort_by(&self) -> Option<SortField> {
+        match &self.cursor {
+            Some(cursor) => cursor.sort_by,
+            None => self.sort_by,
+        }
+    }
+
+    pub fn order(&self) -> SortOrder {
+        match &self.cursor {
+            Some(cursor) => cursor.order,
+            None => self.order.unwrap_or_default(),
+        }
+    }
+
+    pub fn ensure_sortable_by(&self, allowed: &[SortField]) -> Result<(), String> {
+        match self.sort_by() {
+            Some(field) if !allowed.contains(&field) => Err(format!(
+                "Cannot sort by `{}` here, expected one of: {}",
+                field.as_str(),
+                allowed
+                    .iter()
+                    .map(SortField::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // cursors are opaque, not tamper proof. a key of the wrong kind would be
+    // bound against a column postgres can't compare it with, so a cursor whose
+    // keys don't fit the listing of `T` is rejected before it gets there
+    pub fn ensure_cursor_fits<T: SortKey>(&self) -> Result<(), ValidationErrors> {
+        let Some(cursor) = &self.cursor else {
+            return Ok(());
+        };
+
+        let key_fits = match cursor.sort_by.and_then(SortField::key_kind) {
+            Some(kind) => cursor.key.as_ref().is_some_and(|key| key.kind() == kind),
+            None => true,
+        };
+
+        if key_fits && cursor.tie.kind() == T::TIE_KIND {
+            return Ok(());
+        }
+
+        let mut errors = ValidationErrors::new();
+        errors.add("cursor", ValidationError::new("cursor_key_mismatch"));
+        Err(errors)
+    }
+
+    // everything but the page number or cursor, which change from link to link
+    pub fn query_parts(&self) -> Vec<String> {
+        let mut parts = if self.is_keyset() {
+            vec![format!("limit={}", self.items_per_page())]
+        } else {
+            vec![format!("items_per_page={}", self.items_per_page())]
+        };
+
+        if let Some(sort_by) = self.sort_by() {
+            parts.push(format!("sort_by={}", sort_by.as_str()));
+        }
+        if self.order.is_some() || self.cursor.is_some() {
+            parts.push(format!("order={}", self.order().as_str()));
+        }
+
+        parts
+    }
+
+    fn cursor_after<T: SortKey>(&self, last: &T) -> Cursor {
+        let sort_by = self.sort_by();
+
+        Cursor {
+            sort_by,
+            order: self.order(),
+            key: sort_by.and_then(|field| last.sort_key(field)),
+            tie: last.tie_key(),
+        }
+    }
+}
+
+fn validate_pagination_mode(params: &PaginationParams) -> Result<(), ValidationError> {
+    let offset_mode = params.page.is_some() || params.items_per_page.is_some();
+
+    if offset_mode && params.is_keyset() {
+        return Err(ValidationError::new("mixed_pagination"));
+    }
+
+    if let Some(cursor) = &params.cursor {
+        let sort_differs = params.sort_by.is_some_and(|s| cursor.sort_by != Some(s))
+            || params.order.is_some_and(|o| cursor.order != o);
+
+        if sort_differs {
+            return Err(ValidationError::new("cursor_sort_mismatch"));
+        }
+    }
+
+    Ok(())
+}
+
+// one page of rows as a repository returns it, `links::build_collection`
+// turns it into the response
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    // only set in offset mode
+    pub page: Option<i64>,
+    pub items_per_page: i64,
+    // only set in keyset mode while there are rows left
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    // in keyset mode the repositories fetch one row past the limit
+    // to find out whether there is a next page
+    pub fn from_rows(mut rows: Vec<T>, total: i64, params: &PaginationParams) -> Self
+    where
+        T: SortKey,
+    {
+        if !params.is_keyset() {
+            return Self {
+                items: rows,
+                total,
+                page: Some(params.page()),
+                items_per_page: params.items_per_page(),
+                next_cursor: None,
+            };
+        }
+
+        let limit = params.items_per_page() as usize;
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(|last| params.cursor_after(last))
+        } else {
+            None
+        };
+
+        Self {
+            items: rows,
+            total,
+            page: None,
+            items_per_page: params.items_per_page(),
+            next_cursor,
+        }
+    }
+
+    // an empty collection still has one (empty) page
+    pub fn last_page(&self) -> i64 {
+        ((self.total + self.items_per_page - 1) / self.items_per_page).max(1)
+    }
+
+    pub fn map<U, F>(self, f: F) -> Page<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        self.map_items(|items| items.into_iter().map(f).collect())
+    }
+
+    pub fn map_items<U, F>(self, f: F) -> Page<U>
+    where
+        F: FnOnce(Vec<T>) -> Vec<U>,
+    {
+        Page {
+            items: f(self.items),
+            total: self.total,
+            page: self.page,
+            items_per_page: self.items_per_page,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
@@ -1,10 +1,12 @@
+use crate::shared::qr::QrFormat;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
 use utoipa::ToSchema;
 use validator::Validate;
 use validator::ValidationError;
 
-#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Ticket {
     pub cod: String,
 
@@ -15,18 +17,20 @@ pub struct Ticket {
     #[sqlx(rename = "evenimentid")]
     #[serde(rename = "evenimentid")]
     pub id_event: Option<i32>,
+
+    // only set for event tickets, packet tickets are checked in per event
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub checked_in_at: Option<OffsetDateTime>,
 }
 
 #[derive(Debug, Deserialize, FromRow, ToSchema, Validate)]
 #[validate(schema(function = "validate_create_ticket"))]
 #[serde(deny_unknown_fields)]
 pub struct CreateTicket {
-    #[validate(length(
-        min = 3,
-        max = 50,
-        message = "Code must be between 3 and 50 characters"
-    ))]
-    pub cod: String,
+    // left out to let the service generate an unguessable code
+    #[validate(length(min = 3, max = 50, code = "code_length"))]
+    #[schema(min_length = 3, max_length = 50)]
+    pub cod: Option<String>,
 
     #[sqlx(rename = "pachetid")]
     #[serde(rename = "pachetid")]
@@ -50,19 +54,32 @@ pub struct UpdateTicket {
     pub id_event: Option<i32>,
 }
 
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct BulkCreateTickets {
+    #[validate(range(min = 1, max = 100, code = "count_range"))]
+    #[schema(minimum = 1, maximum = 100)]
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QrQuery {
+    pub format: Option<QrFormat>,
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyTicketToken {
+    #[validate(length(min = 1, max = 1024, code = "token_length"))]
+    #[schema(min_length = 1, max_length = 1024)]
+    pub token: String,
+}
+
 fn validate_exclusive_ids(ticket: &impl ExclusiveTicketIds) -> Result<(), ValidationError> {
     match (ticket.get_pachet_id(), ticket.get_event_id()) {
-        (Some(_), Some(_)) => {
-            let mut err = ValidationError::new("exclusive_ids");
-            err.message =
-                Some("A ticket can belong to EITHER a packet OR an event, not both.".into());
-            Err(err)
-        }
-        (None, None) => {
-            let mut err = ValidationError::new("exclusive_ids");
-            err.message = Some("A ticket must belong to a packet OR an event.".into());
-            Err(err)
-        }
+        (Some(_), Some(_)) => Err(ValidationError::new("exclusive_ids")),
+        (None, None) => Err(ValidationError::new("missing_target")),
         _ => Ok(()),
     }
 }
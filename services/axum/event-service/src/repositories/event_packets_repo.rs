@@ -1,12 +1,41 @@
 use crate::models::event_packets::{
-    CreateEventPacket, EventPacketQuery, EventPackets, PaginationParams, UpdateEventPacket,
+    CreateEventPacket, EventPacketQuery, EventPackets, UpdateEventPacket,
 };
+use crate::models::pagination::{CursorKey, KeyKind, Page, SortField, SortKey};
+use crate::repositories::event_repo::event_seats_left;
+use crate::repositories::pagination::{push_keyset, push_limit, push_order_by};
+use crate::shared::auth::AuthUser;
 use crate::shared::error::*;
+use crate::shared::metrics::QueryTimer;
 use anyhow::Result;
-use sqlx::{Error, PgPool, Postgres, QueryBuilder};
+use async_trait::async_trait;
+use sqlx::{Error, PgConnection, PgPool, Postgres, QueryBuilder};
+use tracing::instrument;
 
-const DEFAULT_PAGE: i64 = 1;
-const DEFAULT_ITEMS_PER_PAGE: i64 = 10;
+#[async_trait]
+pub trait EventPacketRepository: Send + Sync {
+    async fn list_event_packets(
+        &self,
+        params: EventPacketQuery,
+    ) -> Result<Page<EventPackets>, EventPacketRepoError>;
+    async fn get_event_packet(&self, packet_id: i32) -> Result<EventPackets, EventPacketRepoError>;
+    async fn create_event_packet(
+        &self,
+        owner_id: i32,
+        payload: CreateEventPacket,
+    ) -> Result<EventPackets, EventPacketRepoError>;
+    async fn update_event_packet(
+        &self,
+        packet_id: i32,
+        payload: UpdateEventPacket,
+        caller: &AuthUser,
+    ) -> Result<EventPackets, EventPacketRepoError>;
+    async fn delete_event_packet(
+        &self,
+        packet_id: i32,
+        caller: &AuthUser,
+    ) -> Result<(), EventPacketRepoError>;
+}
 
 pub struct EventPacketRepo {
     pool: PgPool,
@@ -17,37 +46,68 @@ impl EventPacketRepo {
         Self { pool }
     }
 
-    pub async fn list_event_packets(
-        &self,
-        params: EventPacketQuery,
-    ) -> Result<Vec<EventPackets>, EventPacketRepoError> {
-        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-            "SELECT ID, ID_OWNER, nume, locatie, descriere, numarlocuri FROM PACHETE",
-        );
-
-        let mut has_condition = false;
-
-        let type_filter = params.descriere.filter(|s| !s.is_empty());
+    // same row lock as EventRepo::ensure_owner, but for PACHETE
+    async fn ensure_owner(
+        conn: &mut PgConnection,
+        packet_id: i32,
+        caller: &AuthUser,
+    ) -> Result<(), EventPacketRepoError> {
+        let owner_id: i32 =
+            sqlx::query_scalar("SELECT id_owner FROM PACHETE WHERE id = $1 FOR UPDATE")
+                .bind(packet_id)
+                .fetch_one(conn)
+                .await
+                .map_err(map_sqlx_packet_error)?;
 
-        if let Some(desc_filter) = type_filter {
-            query_builder.push(" WHERE unaccent(descriere) ILIKE unaccent(");
-            query_builder.push_bind(format!("%{}%", desc_filter));
-            query_builder.push(")");
-            has_condition = true;
+        if caller.is_admin() || owner_id == caller.id {
+            Ok(())
+        } else {
+            Err(EventPacketRepoError::Forbidden)
         }
+    }
+}
 
-        if let Some(min_tickets) = params.bilete {
-            if has_condition {
-                query_builder.push(" AND ");
-            } else {
-                query_builder.push(" WHERE ");
-            }
+#[async_trait]
+impl EventPacketRepository for EventPacketRepo {
+    #[instrument(name = "EventPacketRepo::list_event_packets", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn list_event_packets(
+        &self,
+        params: EventPacketQuery,
+    ) -> Result<Page<EventPackets>, EventPacketRepoError> {
+        let _timer = QueryTimer::start("EventPacketRepo", "list_event_packets");
+        let mut count_builder: QueryBuilder<Postgres> =
+            QueryBuilder::new("SELECT COUNT(*) FROM PACHETE");
+        push_packet_filters(&mut count_builder, &params);
 
-            query_builder.push(" PACHETE.numarlocuri >= ");
-            query_builder.push_bind(min_tickets);
-        }
+        let total: i64 = count_builder
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_sqlx_packet_error)?;
 
-        self.apply_pagination(&mut query_builder, params.paginare);
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+            r#"
+            SELECT ID, ID_OWNER, nume, locatie, descriere, numarlocuri,
+                {} AS locuri_disponibile
+            FROM PACHETE
+            "#,
+            packet_seats_left("numarlocuri", "PACHETE.ID"),
+        ));
+        let has_condition = push_packet_filters(&mut query_builder, &params);
+        push_keyset(
+            &mut query_builder,
+            has_condition,
+            &params.paginare,
+            packet_sort_column,
+            "ID",
+        );
+        push_order_by(
+            &mut query_builder,
+            &params.paginare,
+            packet_sort_column,
+            "ID",
+        );
+        push_limit(&mut query_builder, &params.paginare);
 
         let query = query_builder.build_query_as::<EventPackets>();
         let packets = query
@@ -55,40 +115,21 @@ impl EventPacketRepo {
             .await
             .map_err(map_sqlx_packet_error)?;
 
-        Ok(packets)
+        Ok(Page::from_rows(packets, total, &params.paginare))
     }
 
-    fn apply_pagination(
-        &self,
-        query_builder: &mut QueryBuilder<Postgres>,
-        pagination: PaginationParams,
-    ) {
-        let page = pagination.page.unwrap_or(DEFAULT_PAGE).max(DEFAULT_PAGE);
-
-        let items_per_page = pagination
-            .items_per_page
-            .unwrap_or(DEFAULT_ITEMS_PER_PAGE)
-            .max(1);
-
-        let offset = (page - 1) * items_per_page;
-
-        query_builder.push(" LIMIT ");
-        query_builder.push_bind(items_per_page);
-        query_builder.push(" OFFSET ");
-        query_builder.push_bind(offset);
-    }
-
-    pub async fn get_event_packet(
-        &self,
-        packet_id: i32,
-    ) -> Result<EventPackets, EventPacketRepoError> {
-        let result = sqlx::query_as::<_, EventPackets>(
+    #[instrument(name = "EventPacketRepo::get_event_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_event_packet(&self, packet_id: i32) -> Result<EventPackets, EventPacketRepoError> {
+        let _timer = QueryTimer::start("EventPacketRepo", "get_event_packet");
+        let result = sqlx::query_as::<_, EventPackets>(&format!(
             r#"
-            SELECT id, id_owner, nume, locatie, descriere, numarlocuri
+            SELECT id, id_owner, nume, locatie, descriere, numarlocuri,
+                {} AS locuri_disponibile
             FROM PACHETE
             WHERE id = $1
             "#,
-        )
+            packet_seats_left("numarlocuri", "PACHETE.id"),
+        ))
         .bind(packet_id)
         .fetch_one(&self.pool)
         .await;
@@ -100,18 +141,22 @@ impl EventPacketRepo {
         }
     }
 
-    pub async fn create_event_packet(
+    #[instrument(name = "EventPacketRepo::create_event_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_event_packet(
         &self,
+        owner_id: i32,
         payload: CreateEventPacket,
     ) -> Result<EventPackets, EventPacketRepoError> {
+        let _timer = QueryTimer::start("EventPacketRepo", "create_event_packet");
         let result = sqlx::query_as::<_, EventPackets>(
             r#"
             INSERT INTO PACHETE (id_owner, nume, locatie, descriere, numarlocuri)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, id_owner, nume, locatie, descriere, numarlocuri
+            RETURNING id, id_owner, nume, locatie, descriere, numarlocuri,
+                numarlocuri::BIGINT AS locuri_disponibile
             "#,
         )
-        .bind(payload.id_owner)
+        .bind(owner_id)
         .bind(&payload.nume)
         .bind(&payload.locatie)
         .bind(&payload.descriere)
@@ -122,47 +167,149 @@ impl EventPacketRepo {
         result.map_err(map_sqlx_packet_error)
     }
 
-    pub async fn update_event_packet(
+    #[instrument(name = "EventPacketRepo::update_event_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "UPDATE"))]
+    async fn update_event_packet(
         &self,
         packet_id: i32,
         payload: UpdateEventPacket,
+        caller: &AuthUser,
     ) -> Result<EventPackets, EventPacketRepoError> {
-        let result = sqlx::query_as::<_, EventPackets>(
+        let _timer = QueryTimer::start("EventPacketRepo", "update_event_packet");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_packet_error)?;
+
+        Self::ensure_owner(&mut tx, packet_id, caller).await?;
+
+        let packet = sqlx::query_as::<_, EventPackets>(&format!(
             r#"
             UPDATE PACHETE
             SET
-                id_owner = COALESCE($1, id_owner),
-                nume = COALESCE($2, nume),
-                locatie = COALESCE($3, locatie),
-                descriere = COALESCE($4, descriere),
-                numarlocuri = COALESCE($5, numarlocuri)
-            WHERE id = $6
-            RETURNING id, id_owner, nume, locatie, descriere, numarlocuri
+                nume = COALESCE($1, nume),
+                locatie = COALESCE($2, locatie),
+                descriere = COALESCE($3, descriere),
+                numarlocuri = COALESCE($4, numarlocuri)
+            WHERE id = $5
+            RETURNING id, id_owner, nume, locatie, descriere, numarlocuri,
+                {} AS locuri_disponibile
             "#,
-        )
-        .bind(payload.id_owner)
+            packet_seats_left("numarlocuri", "PACHETE.id"),
+        ))
         .bind(&payload.nume)
         .bind(&payload.locatie)
         .bind(&payload.descriere)
         .bind(payload.numarlocuri)
         .bind(packet_id)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_packet_error)?;
 
-        result.map_err(map_sqlx_packet_error)
+        tx.commit().await.map_err(map_sqlx_packet_error)?;
+
+        Ok(packet)
     }
 
-    pub async fn delete_event_packet(&self, packet_id: i32) -> Result<(), EventPacketRepoError> {
+    #[instrument(name = "EventPacketRepo::delete_event_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "DELETE"))]
+    async fn delete_event_packet(
+        &self,
+        packet_id: i32,
+        caller: &AuthUser,
+    ) -> Result<(), EventPacketRepoError> {
+        let _timer = QueryTimer::start("EventPacketRepo", "delete_event_packet");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_packet_error)?;
+
+        Self::ensure_owner(&mut tx, packet_id, caller).await?;
+
+        // SET NULL on BILETE trips `chk_bilet_exclusiv` while tickets are left
         let result = sqlx::query("DELETE FROM PACHETE WHERE id = $1")
             .bind(packet_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
-            .map_err(EventPacketRepoError::InternalError)?;
+            .map_err(map_sqlx_packet_error)?;
 
         if result.rows_affected() == 0 {
-            Err(EventPacketRepoError::NotFound)
+            return Err(EventPacketRepoError::NotFound);
+        }
+
+        tx.commit()
+            .await
+            .map_err(EventPacketRepoError::InternalError)?;
+
+        Ok(())
+    }
+}
+
+fn push_packet_filters(
+    query_builder: &mut QueryBuilder<Postgres>,
+    params: &EventPacketQuery,
+) -> bool {
+    let mut has_condition = false;
+
+    let type_filter = params.descriere.as_deref().filter(|s| !s.is_empty());
+
+    if let Some(desc_filter) = type_filter {
+        query_builder.push(" WHERE unaccent(descriere) ILIKE unaccent(");
+        query_builder.push_bind(format!("%{}%", desc_filter));
+        query_builder.push(")");
+        has_condition = true;
+    }
+
+    if let Some(min_tickets) = params.bilete {
+        if has_condition {
+            query_builder.push(" AND ");
         } else {
-            Ok(())
+            query_builder.push(" WHERE ");
+            has_condition = true;
+        }
+
+        // packets without a seat count in themselves or their events are never sold out
+        query_builder.push(format!(
+            "COALESCE({} >= ",
+            packet_seats_left("numarlocuri", "PACHETE.ID")
+        ));
+        query_builder.push_bind(min_tickets);
+        query_builder.push(", TRUE)");
+    }
+
+    has_condition
+}
+
+// the seats left at the packet with ID `id` and capacity `numarlocuri`. a
+// packet ticket also takes a seat at every event of the packet, so these are
+// the fewest left at the packet itself or at any of its events. LEAST and MIN
+// skip NULLs, the result is NULL only when all of them are unlimited
+pub(crate) fn packet_seats_left(numarlocuri: &str, id: &str) -> String {
+    format!(
+        "LEAST({numarlocuri} - (SELECT COUNT(*) FROM BILETE b WHERE b.pachetid = {id}), \
+         (SELECT MIN({}) FROM EVENIMENTE e JOIN JOIN_PE pe ON pe.evenimentid = e.ID \
+         WHERE pe.pachetid = {id}))",
+        event_seats_left("e.numarlocuri", "e.ID")
+    )
+}
+
+// same rules as for events, keep in sync with `SortKey for EventPackets`
+fn packet_sort_column(field: SortField) -> Option<&'static str> {
+    match field {
+        SortField::Name => Some("nume"),
+        SortField::Location => Some("COALESCE(locatie, '')"),
+        SortField::Seats => Some("COALESCE(numarlocuri, 2147483647)"),
+        SortField::Id => Some("ID"),
+    }
+}
+
+impl SortKey for EventPackets {
+    const TIE_KIND: KeyKind = KeyKind::Int;
+
+    fn sort_key(&self, field: SortField) -> Option<CursorKey> {
+        match field {
+            SortField::Name => Some(CursorKey::Text(self.nume.clone())),
+            SortField::Location => Some(CursorKey::Text(self.locatie.clone().unwrap_or_default())),
+            SortField::Seats => Some(CursorKey::Int(i64::from(
+                self.numarlocuri.unwrap_or(i32::MAX),
+            ))),
+            SortField::Id => None,
         }
     }
+
+    fn tie_key(&self) -> CursorKey {
+        CursorKey::Int(i64::from(self.id))
+    }
 }
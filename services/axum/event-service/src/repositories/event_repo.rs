@@ -1,7 +1,36 @@
+use crate::models::check_in::CheckInStats;
 use crate::models::event::{CreateEvent, Event, EventQuery, UpdateEvent};
+use crate::models::pagination::{CursorKey, KeyKind, Page, SortField, SortKey};
+use crate::repositories::pagination::{push_keyset, push_limit, push_order_by};
+use crate::shared::auth::AuthUser;
 use crate::shared::error::*;
+use crate::shared::metrics::QueryTimer;
 use anyhow::Result;
-use sqlx::{Error, PgPool, Postgres, QueryBuilder};
+use async_trait::async_trait;
+use sqlx::{Error, PgConnection, PgPool, Postgres, QueryBuilder};
+use tracing::instrument;
+
+// the handlers only see this trait, `EventRepo` backs it with postgres and
+// `InMemoryRepo` with plain collections for the handler tests
+#[async_trait]
+pub trait EventRepository: Send + Sync {
+    async fn check(&self) -> Result<(), Error>;
+    async fn list_events(&self, params: EventQuery) -> Result<Page<Event>, EventRepoError>;
+    async fn get_event(&self, event_id: i32) -> Result<Event, EventRepoError>;
+    async fn get_check_in_stats(&self, event_id: i32) -> Result<CheckInStats, EventRepoError>;
+    async fn create_event(
+        &self,
+        owner_id: i32,
+        payload: CreateEvent,
+    ) -> Result<Event, EventRepoError>;
+    async fn update_event(
+        &self,
+        event_id: i32,
+        payload: UpdateEvent,
+        caller: &AuthUser,
+    ) -> Result<Event, EventRepoError>;
+    async fn delete_event(&self, event_id: i32, caller: &AuthUser) -> Result<(), EventRepoError>;
+}
 
 pub struct EventRepo {
     pool: PgPool,
@@ -12,54 +41,95 @@ impl EventRepo {
         Self { pool }
     }
 
-    pub async fn check(&self) -> Result<(), Error> {
+    // locks the row for the rest of the transaction, so the owner
+    // can't change between this check and the mutation that follows
+    async fn ensure_owner(
+        conn: &mut PgConnection,
+        event_id: i32,
+        caller: &AuthUser,
+    ) -> Result<(), EventRepoError> {
+        let owner_id: i32 =
+            sqlx::query_scalar("SELECT ID_OWNER FROM EVENIMENTE WHERE ID = $1 FOR UPDATE")
+                .bind(event_id)
+                .fetch_one(conn)
+                .await
+                .map_err(map_sqlx_event_error)?;
+
+        if caller.is_admin() || owner_id == caller.id {
+            Ok(())
+        } else {
+            Err(EventRepoError::Forbidden)
+        }
+    }
+}
+
+#[async_trait]
+impl EventRepository for EventRepo {
+    #[instrument(name = "EventRepo::check", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn check(&self) -> Result<(), Error> {
+        let _timer = QueryTimer::start("EventRepo", "check");
         sqlx::query("select 1").execute(&self.pool).await?;
         Ok(())
     }
 
-    pub async fn list_events(&self, params: EventQuery) -> Result<Vec<Event>, EventRepoError> {
-        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-            "SELECT ID, ID_OWNER, nume, locatie, descriere, numarlocuri FROM EVENIMENTE",
-        );
-
-        let mut has_condition = false;
-
-        let location = params.locatie.filter(|s| !s.is_empty());
-        let name = params.nume.filter(|s| !s.is_empty());
+    #[instrument(name = "EventRepo::list_events", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn list_events(&self, params: EventQuery) -> Result<Page<Event>, EventRepoError> {
+        let _timer = QueryTimer::start("EventRepo", "list_events");
+        let mut count_builder: QueryBuilder<Postgres> =
+            QueryBuilder::new("SELECT COUNT(*) FROM EVENIMENTE");
+        push_event_filters(&mut count_builder, &params);
 
-        if let Some(location) = location {
-            query_builder.push(" WHERE unaccent(locatie) ILIKE unaccent(");
-            query_builder.push_bind(format!("{}%", location));
-            query_builder.push(")");
-            has_condition = true;
-        }
+        let total: i64 = count_builder
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(EventRepoError::InternalError)?;
 
-        if let Some(name) = name {
-            if has_condition {
-                query_builder.push(" AND unaccent(nume) ILIKE unaccent(");
-            } else {
-                query_builder.push(" WHERE unaccent(nume) ILIKE unaccent(");
-            }
-            query_builder.push_bind(format!("%{}%", name));
-            query_builder.push(")");
-        }
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+            r#"
+            SELECT ID, ID_OWNER, nume, locatie, descriere, numarlocuri, data_inceput, data_sfarsit, fus_orar,
+                {} AS locuri_disponibile
+            FROM EVENIMENTE
+            "#,
+            event_seats_left("numarlocuri", "EVENIMENTE.ID"),
+        ));
+        let has_condition = push_event_filters(&mut query_builder, &params);
+        push_keyset(
+            &mut query_builder,
+            has_condition,
+            &params.paginare,
+            event_sort_column,
+            "ID",
+        );
+        push_order_by(
+            &mut query_builder,
+            &params.paginare,
+            event_sort_column,
+            "ID",
+        );
+        push_limit(&mut query_builder, &params.paginare);
 
         let query = query_builder.build_query_as::<Event>();
         let events = query
             .fetch_all(&self.pool)
             .await
-            .map_err(|e| EventRepoError::InternalError(e))?;
-        Ok(events)
+            .map_err(EventRepoError::InternalError)?;
+
+        Ok(Page::from_rows(events, total, &params.paginare))
     }
 
-    pub async fn get_event(&self, event_id: i32) -> Result<Event, EventRepoError> {
-        let result = sqlx::query_as::<_, Event>(
+    #[instrument(name = "EventRepo::get_event", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_event(&self, event_id: i32) -> Result<Event, EventRepoError> {
+        let _timer = QueryTimer::start("EventRepo", "get_event");
+        let result = sqlx::query_as::<_, Event>(&format!(
             r#"
-            SELECT ID, ID_OWNER, nume, locatie, descriere, numarlocuri
+            SELECT ID, ID_OWNER, nume, locatie, descriere, numarlocuri, data_inceput, data_sfarsit, fus_orar,
+                {} AS locuri_disponibile
             FROM EVENIMENTE
             WHERE ID = $1
             "#,
-        )
+            event_seats_left("numarlocuri", "EVENIMENTE.ID"),
+        ))
         .bind(event_id)
         .fetch_one(&self.pool)
         .await;
@@ -71,70 +141,231 @@ impl EventRepo {
         }
     }
 
-    pub async fn create_event(&self, payload: CreateEvent) -> Result<Event, EventRepoError> {
+    // packet tickets count once for every event in their packet
+    #[instrument(name = "EventRepo::get_check_in_stats", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_check_in_stats(&self, event_id: i32) -> Result<CheckInStats, EventRepoError> {
+        let _timer = QueryTimer::start("EventRepo", "get_check_in_stats");
+        let result = sqlx::query_as::<_, CheckInStats>(
+            r#"
+            WITH stats AS (
+                SELECT
+                    e.ID AS evenimentid,
+                    (SELECT COUNT(*) FROM BILETE b WHERE b.evenimentid = e.ID)
+                        + (SELECT COUNT(*) FROM BILETE b
+                           JOIN JOIN_PE j ON b.pachetid = j.pachetid
+                           WHERE j.evenimentid = e.ID) AS bilete_emise,
+                    (SELECT COUNT(*) FROM BILETE b
+                     WHERE b.evenimentid = e.ID AND b.checked_in_at IS NOT NULL)
+                        + (SELECT COUNT(*) FROM INTRARI i WHERE i.evenimentid = e.ID) AS intrari
+                FROM EVENIMENTE e
+                WHERE e.ID = $1
+            )
+            SELECT evenimentid, bilete_emise, intrari, bilete_emise - intrari AS ramase
+            FROM stats
+            "#,
+        )
+        .bind(event_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map_err(map_sqlx_event_error)
+    }
+
+    #[instrument(name = "EventRepo::create_event", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_event(
+        &self,
+        owner_id: i32,
+        payload: CreateEvent,
+    ) -> Result<Event, EventRepoError> {
+        let _timer = QueryTimer::start("EventRepo", "create_event");
         let result = sqlx::query_as::<_, Event>(
             r#"
-            INSERT INTO EVENIMENTE (ID_OWNER, nume, locatie, descriere, numarlocuri)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING ID, ID_OWNER, nume, locatie, descriere, numarlocuri
+            INSERT INTO EVENIMENTE
+                (ID_OWNER, nume, locatie, descriere, numarlocuri, data_inceput, data_sfarsit, fus_orar)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING ID, ID_OWNER, nume, locatie, descriere, numarlocuri, data_inceput, data_sfarsit, fus_orar,
+                numarlocuri::BIGINT AS locuri_disponibile
             "#,
         )
-        .bind(payload.id_owner)
+        .bind(owner_id)
         .bind(&payload.nume)
         .bind(&payload.locatie)
         .bind(&payload.descriere)
         .bind(payload.locuri)
+        .bind(payload.data_inceput)
+        .bind(payload.data_sfarsit)
+        .bind(&payload.fus_orar)
         .fetch_one(&self.pool)
         .await;
 
         result.map_err(map_sqlx_event_error)
     }
 
-    pub async fn update_event(
+    #[instrument(name = "EventRepo::update_event", skip_all, fields(db.system = "postgresql", db.operation.name = "UPDATE"))]
+    async fn update_event(
         &self,
         event_id: i32,
         payload: UpdateEvent,
+        caller: &AuthUser,
     ) -> Result<Event, EventRepoError> {
-        let result = sqlx::query_as::<_, Event>(
+        let _timer = QueryTimer::start("EventRepo", "update_event");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_event_error)?;
+
+        Self::ensure_owner(&mut tx, event_id, caller).await?;
+
+        let event = sqlx::query_as::<_, Event>(&format!(
             r#"
         UPDATE EVENIMENTE
         SET
-            id_owner = COALESCE($1, id_owner),
-            nume = COALESCE($2, nume),
-            locatie = COALESCE($3, locatie),
-            descriere = COALESCE($4, descriere),
-            numarlocuri = COALESCE($5, numarlocuri)
-        WHERE ID = $6
-        RETURNING ID, ID_OWNER, nume, locatie, descriere, numarlocuri
+            nume = COALESCE($1, nume),
+            locatie = COALESCE($2, locatie),
+            descriere = COALESCE($3, descriere),
+            numarlocuri = COALESCE($4, numarlocuri),
+            data_inceput = COALESCE($5, data_inceput),
+            data_sfarsit = COALESCE($6, data_sfarsit),
+            fus_orar = COALESCE($7, fus_orar)
+        WHERE ID = $8
+        RETURNING ID, ID_OWNER, nume, locatie, descriere, numarlocuri, data_inceput, data_sfarsit, fus_orar,
+            {} AS locuri_disponibile
         "#,
-        )
-        .bind(payload.id_owner)
+            event_seats_left("numarlocuri", "EVENIMENTE.ID"),
+        ))
         .bind(&payload.nume)
         .bind(&payload.locatie)
         .bind(&payload.descriere)
         .bind(payload.locuri)
+        .bind(payload.data_inceput)
+        .bind(payload.data_sfarsit)
+        .bind(&payload.fus_orar)
         .bind(event_id)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_event_error)?;
 
-        match result {
-            Ok(event) => Ok(event),
-            Err(Error::RowNotFound) => Err(EventRepoError::NotFound),
-            Err(e) => Err(EventRepoError::InternalError(e)),
-        }
+        tx.commit().await.map_err(map_sqlx_event_error)?;
+
+        Ok(event)
     }
 
-    pub async fn delete_event(&self, event_id: i32) -> Result<(), EventRepoError> {
+    #[instrument(name = "EventRepo::delete_event", skip_all, fields(db.system = "postgresql", db.operation.name = "DELETE"))]
+    async fn delete_event(&self, event_id: i32, caller: &AuthUser) -> Result<(), EventRepoError> {
+        let _timer = QueryTimer::start("EventRepo", "delete_event");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_event_error)?;
+
+        Self::ensure_owner(&mut tx, event_id, caller).await?;
+
+        // SET NULL on BILETE trips `chk_bilet_exclusiv` while tickets are left
         let result = sqlx::query("DELETE FROM EVENIMENTE WHERE ID = $1")
             .bind(event_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
-            .map_err(EventRepoError::InternalError)?;
+            .map_err(map_sqlx_event_error)?;
 
         if result.rows_affected() == 0 {
-            Err(EventRepoError::NotFound)
-        } else {
-            Ok(())
+            return Err(EventRepoError::NotFound);
+        }
+
+        tx.commit().await.map_err(EventRepoError::InternalError)?;
+
+        Ok(())
+    }
+}
+
+fn push_event_filters(query_builder: &mut QueryBuilder<Postgres>, params: &EventQuery) -> bool {
+    let mut has_condition = false;
+
+    let location = params.locatie.as_deref().filter(|s| !s.is_empty());
+    let name = params.nume.as_deref().filter(|s| !s.is_empty());
+
+    if let Some(location) = location {
+        push_condition(query_builder, &mut has_condition);
+        query_builder.push("unaccent(locatie) ILIKE unaccent(");
+        query_builder.push_bind(format!("{}%", location));
+        query_builder.push(")");
+    }
+
+    if let Some(name) = name {
+        push_condition(query_builder, &mut has_condition);
+        query_builder.push("unaccent(nume) ILIKE unaccent(");
+        query_builder.push_bind(format!("%{}%", name));
+        query_builder.push(")");
+    }
+
+    if let Some(from) = params.from {
+        push_condition(query_builder, &mut has_condition);
+        query_builder.push("data_inceput >= ");
+        query_builder.push_bind(from);
+    }
+
+    if let Some(to) = params.to {
+        push_condition(query_builder, &mut has_condition);
+        query_builder.push("data_inceput <= ");
+        query_builder.push_bind(to);
+    }
+
+    // events without a seat count are never sold out
+    if let Some(min_tickets) = params.bilete {
+        push_condition(query_builder, &mut has_condition);
+        query_builder.push(format!(
+            "(numarlocuri IS NULL OR {} >= ",
+            event_seats_left("numarlocuri", "EVENIMENTE.ID")
+        ));
+        query_builder.push_bind(min_tickets);
+        query_builder.push(")");
+    }
+
+    // an event is still upcoming while it hasn't ended
+    if params.upcoming == Some(true) {
+        push_condition(query_builder, &mut has_condition);
+        query_builder.push("COALESCE(data_sfarsit, data_inceput) >= NOW()");
+    }
+
+    has_condition
+}
+
+// the seats left at the event with ID `id` and capacity `numarlocuri`. its own
+// tickets and those of the packets including it take one each, NULL when
+// the capacity is unlimited
+pub(crate) fn event_seats_left(numarlocuri: &str, id: &str) -> String {
+    format!(
+        "{numarlocuri} - (SELECT COUNT(*) FROM BILETE b WHERE b.evenimentid = {id} \
+         OR b.pachetid IN (SELECT j.pachetid FROM JOIN_PE j WHERE j.evenimentid = {id}))"
+    )
+}
+
+// a missing location sorts first and an unlimited capacity last,
+// keep these in sync with `SortKey for Event`
+fn event_sort_column(field: SortField) -> Option<&'static str> {
+    match field {
+        SortField::Name => Some("nume"),
+        SortField::Location => Some("COALESCE(locatie, '')"),
+        SortField::Seats => Some("COALESCE(numarlocuri, 2147483647)"),
+        SortField::Id => Some("ID"),
+    }
+}
+
+impl SortKey for Event {
+    const TIE_KIND: KeyKind = KeyKind::Int;
+
+    fn sort_key(&self, field: SortField) -> Option<CursorKey> {
+        match field {
+            SortField::Name => Some(CursorKey::Text(self.nume.clone())),
+            SortField::Location => Some(CursorKey::Text(self.locatie.clone().unwrap_or_default())),
+            SortField::Seats => Some(CursorKey::Int(i64::from(self.locuri.unwrap_or(i32::MAX)))),
+            SortField::Id => None,
         }
     }
+
+    fn tie_key(&self) -> CursorKey {
+        CursorKey::Int(i64::from(self.id))
+    }
+}
+
+fn push_condition(query_builder: &mut QueryBuilder<Postgres>, has_condition: &mut bool) {
+    if *has_condition {
+        query_builder.push(" AND ");
+    } else {
+        query_builder.push(" WHERE ");
+        *has_condition = true;
+    }
 }
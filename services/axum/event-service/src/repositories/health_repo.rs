@@ -0,0 +1,57 @@
+use crate::shared::db::MIGRATOR;
+use sqlx::{Error, PgPool};
+
+pub struct HealthRepo {
+    pool: PgPool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolUsage {
+    pub open: u32,
+    pub idle: usize,
+    pub max: u32,
+}
+
+impl PoolUsage {
+    // every connection is open and busy, the next request has to wait
+    pub fn is_saturated(&self) -> bool {
+        self.open >= self.max && self.idle == 0
+    }
+}
+
+impl HealthRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool_usage(&self) -> PoolUsage {
+        PoolUsage {
+            open: self.pool.size(),
+            idle: self.pool.num_idle(),
+            max: self.pool.options().get_max_connections(),
+        }
+    }
+
+    // versions embedded in the binary that the database hasn't applied yet
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>, Error> {
+        let tracked: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+
+        let applied: Vec<i64> = if tracked {
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect())
+    }
+}
@@ -0,0 +1,1252 @@
+use crate::models::check_in::{CheckIn, CheckInStats};
+use crate::models::event::{CreateEvent, Event, EventQuery, UpdateEvent};
+use crate::models::event_packets::{
+    CreateEventPacket, EventPacketQuery, EventPackets, UpdateEventPacket,
+};
+use crate::models::join_pe::{AddEventToPacket, AddPacketToEvent, EventPacketRelation};
+use crate::models::pagination::{Page, PaginationParams, SortKey, SortOrder};
+use crate::models::ticket::{CreateTicket, Ticket, UpdateTicket};
+use crate::repositories::event_packets_repo::EventPacketRepository;
+use crate::repositories::event_repo::EventRepository;
+use crate::repositories::join_pe_repo::JoinPeRepository;
+use crate::repositories::ticket_repo::{TicketRepository, TicketTarget};
+use crate::shared::auth::AuthUser;
+use crate::shared::error::*;
+use async_trait::async_trait;
+use sqlx::Error;
+use sqlx::error::{DatabaseError, ErrorKind};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+// all four repositories over plain collections, for tests that exercise the
+// handlers without postgres. it keeps the rules of the schema in migrations/:
+// unique names and ticket codes, foreign keys, the cascades and SET NULLs, and the CHECK constraints. a broken rule
+// raises the sqlstate postgres would, and goes through the same `map_sqlx_*`
+// function the postgres repository uses, so both answer with the same error.
+//
+// not modelled: the owner foreign key to UTILIZATORI (every owner exists),
+// text collation (names sort byte-wise) and concurrent transactions (each
+// call holds the lock from start to end)
+#[derive(Default)]
+pub struct InMemoryRepo {
+    tables: Mutex<Tables>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    events: BTreeMap<i32, Event>,
+    packets: BTreeMap<i32, EventPackets>,
+    // JOIN_PE as (pachetid, evenimentid)
+    joins: BTreeSet<(i32, i32)>,
+    tickets: BTreeMap<String, Ticket>,
+    // INTRARI as (cod, evenimentid), the check-ins of packet tickets
+    entries: BTreeMap<(String, i32), OffsetDateTime>,
+    last_event_id: i32,
+    last_packet_id: i32,
+}
+
+impl InMemoryRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Tables) -> T) -> T {
+        f(&self.tables.lock().unwrap())
+    }
+
+    // runs `f` on a copy of the tables that replaces them only when it
+    // succeeds, so a failing call leaves nothing behind, like a rolled back
+    // transaction would
+    fn transaction<T, E>(&self, f: impl FnOnce(&mut Tables) -> Result<T, E>) -> Result<T, E> {
+        let mut tables = self.tables.lock().unwrap();
+        let mut tx = tables.clone();
+        let result = f(&mut tx)?;
+        *tables = tx;
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Constraint {
+    Unique,
+    ForeignKey,
+    NotNull,
+    Check,
+}
+
+// what postgres reports when a statement breaks a constraint, enough of it
+// for the `map_sqlx_*` functions which only look at the sqlstate
+#[derive(Debug)]
+struct Violation {
+    constraint: Constraint,
+    name: &'static str,
+}
+
+fn violation(constraint: Constraint, name: &'static str) -> Error {
+    Error::Database(Box::new(Violation { constraint, name }))
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} violates constraint \"{}\"",
+            self.message(),
+            self.name
+        )
+    }
+}
+
+impl std::error::Error for Violation {}
+
+impl DatabaseError for Violation {
+    fn message(&self) -> &str {
+        match self.constraint {
+            Constraint::Unique => "duplicate key value",
+            Constraint::ForeignKey => "insert or update",
+            Constraint::NotNull => "null value",
+            Constraint::Check => "new row",
+        }
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        let code = match self.constraint {
+            Constraint::Unique => "23505",
+            Constraint::ForeignKey => "23503",
+            Constraint::NotNull => "23502",
+            Constraint::Check => "23514",
+        };
+        Some(Cow::Borrowed(code))
+    }
+
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn constraint(&self) -> Option<&str> {
+        Some(self.name)
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self.constraint {
+            Constraint::Unique => ErrorKind::UniqueViolation,
+            Constraint::ForeignKey => ErrorKind::ForeignKeyViolation,
+            Constraint::NotNull => ErrorKind::NotNullViolation,
+            Constraint::Check => ErrorKind::CheckViolation,
+        }
+    }
+}
+
+impl Tables {
+    fn event_sold(&self, event_id: i32, except: Option<&str>) -> i64 {
+        self.tickets
+            .values()
+            .filter(|t| t.id_event == Some(event_id) && Some(t.cod.as_str()) != except)
+            .count() as i64
+    }
+
+    fn packet_sold(&self, packet_id: i32, except: Option<&str>) -> i64 {
+        self.tickets
+            .values()
+            .filter(|t| t.id_pachet == Some(packet_id) && Some(t.cod.as_str()) != except)
+            .count() as i64
+    }
+
+    // the event's own tickets and those of the packets including it
+    fn event_seats_taken(&self, event_id: i32, except: Option<&str>) -> i64 {
+        let packet_tickets = self
+            .joins
+            .iter()
+            .filter(|(_, id)| *id == event_id)
+            .map(|(packet_id, _)| self.packet_sold(*packet_id, except))
+            .sum::<i64>();
+        self.event_sold(event_id, except) + packet_tickets
+    }
+
+    // `event_seats_left` of the event repository
+    fn event_seats_left(&self, event: &Event) -> Option<i64> {
+        event
+            .locuri
+            .map(|seats| i64::from(seats) - self.event_seats_taken(event.id, None))
+    }
+
+    // `packet_seats_left` of the packet repository, the fewest seats left at
+    // the packet or any of its events, skipping the unlimited ones like LEAST
+    fn packet_seats_left(&self, packet: &EventPackets) -> Option<i64> {
+        let own = packet
+            .numarlocuri
+            .map(|seats| i64::from(seats) - self.packet_sold(packet.id, None));
+        let events = self
+            .joins
+            .iter()
+            .filter(|(packet_id, _)| *packet_id == packet.id)
+            .filter_map(|(_, event_id)| self.event_seats_left(&self.events[event_id]))
+            .min();
+        own.into_iter().chain(events).min()
+    }
+
+    // a row as the SELECTs of the postgres repositories return it
+    fn event(&self, event_id: i32) -> Option<Event> {
+        self.events.get(&event_id).map(|event| Event {
+            locuri_disponibile: self.event_seats_left(event),
+            ..event.clone()
+        })
+    }
+
+    fn packet(&self, packet_id: i32) -> Option<EventPackets> {
+        self.packets.get(&packet_id).map(|packet| EventPackets {
+            locuri_disponibile: self.packet_seats_left(packet),
+            ..packet.clone()
+        })
+    }
+
+    fn check_event(&self, event: &Event) -> Result<(), Error> {
+        if let (Some(start), Some(end)) = (event.data_inceput, event.data_sfarsit)
+            && end <= start
+        {
+            return Err(violation(Constraint::Check, "chk_interval_eveniment"));
+        }
+        if self
+            .events
+            .values()
+            .any(|other| other.id != event.id && other.nume == event.nume)
+        {
+            return Err(violation(Constraint::Unique, "evenimente_nume_key"));
+        }
+        Ok(())
+    }
+
+    fn check_packet(&self, packet: &EventPackets) -> Result<(), Error> {
+        if self
+            .packets
+            .values()
+            .any(|other| other.id != packet.id && other.nume == packet.nume)
+        {
+            return Err(violation(Constraint::Unique, "pachete_nume_key"));
+        }
+        Ok(())
+    }
+
+    // the foreign keys of BILETE, postgres checks them after the row itself
+    fn check_ticket_references(&self, ticket: &Ticket) -> Result<(), Error> {
+        if ticket
+            .id_pachet
+            .is_some_and(|id| !self.packets.contains_key(&id))
+        {
+            return Err(violation(Constraint::ForeignKey, "bilete_pachetid_fkey"));
+        }
+        if ticket
+            .id_event
+            .is_some_and(|id| !self.events.contains_key(&id))
+        {
+            return Err(violation(Constraint::ForeignKey, "bilete_evenimentid_fkey"));
+        }
+        Ok(())
+    }
+
+    // the capacity check of `TicketRepo::reserve_seats`
+    fn reserve_seats(
+        &self,
+        target: TicketTarget,
+        seats: i64,
+        moved_ticket: Option<&str>,
+    ) -> Result<(), TicketRepoError> {
+        let check = |capacity: Option<i32>, sold: i64| match capacity {
+            Some(capacity) if sold + seats > i64::from(capacity) => Err(TicketRepoError::SoldOut),
+            _ => Ok(()),
+        };
+
+        match target {
+            TicketTarget::Event(id) => {
+                let event = self
+                    .events
+                    .get(&id)
+                    .ok_or(TicketRepoError::InvalidReference)?;
+                check(event.locuri, self.event_seats_taken(id, moved_ticket))
+            }
+            TicketTarget::Packet(id) => {
+                let packet = self
+                    .packets
+                    .get(&id)
+                    .ok_or(TicketRepoError::InvalidReference)?;
+                check(packet.numarlocuri, self.packet_sold(id, moved_ticket))?;
+
+                for (_, event_id) in self.joins.iter().filter(|(packet_id, _)| *packet_id == id) {
+                    let event = &self.events[event_id];
+                    check(
+                        event.locuri,
+                        self.event_seats_taken(*event_id, moved_ticket),
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn insert_ticket(
+        &mut self,
+        cod: Option<String>,
+        id_pachet: Option<i32>,
+        id_event: Option<i32>,
+    ) -> Result<Ticket, Error> {
+        let cod = cod.ok_or_else(|| violation(Constraint::NotNull, "cod"))?;
+        let ticket = Ticket {
+            cod,
+            id_pachet,
+            id_event,
+            checked_in_at: None,
+        };
+
+        check_exclusive(&ticket)?;
+        if self.tickets.contains_key(&ticket.cod) {
+            return Err(violation(Constraint::Unique, "bilete_pkey"));
+        }
+        self.check_ticket_references(&ticket)?;
+
+        self.tickets.insert(ticket.cod.clone(), ticket.clone());
+        Ok(ticket)
+    }
+
+    // UPDATE BILETE SET pachetid, evenimentid WHERE cod = .. AND `matches`
+    fn update_ticket(
+        &mut self,
+        cod: &str,
+        matches: impl Fn(&Ticket) -> bool,
+        id_pachet: Option<i32>,
+        id_event: Option<i32>,
+    ) -> Result<Ticket, Error> {
+        let current = self
+            .tickets
+            .get(cod)
+            .filter(|ticket| matches(ticket))
+            .ok_or(Error::RowNotFound)?;
+
+        let ticket = Ticket {
+            id_pachet,
+            id_event,
+            ..current.clone()
+        };
+        check_exclusive(&ticket)?;
+        self.check_ticket_references(&ticket)?;
+
+        self.tickets.insert(ticket.cod.clone(), ticket.clone());
+        Ok(ticket)
+    }
+
+    // DELETE FROM BILETE WHERE cod = .. AND `matches`, INTRARI cascades
+    fn delete_ticket(&mut self, cod: &str, matches: impl Fn(&Ticket) -> bool) -> bool {
+        if !self.tickets.get(cod).is_some_and(matches) {
+            return false;
+        }
+        self.tickets.remove(cod);
+        self.entries.retain(|(entry_cod, _), _| entry_cod != cod);
+        true
+    }
+
+    // JOIN_PE and INTRARI cascade, the tickets are SET NULL which
+    // `chk_bilet_exclusiv` rejects as long as any of them is left
+    fn delete_event(&mut self, event_id: i32) -> Result<bool, Error> {
+        if self.events.remove(&event_id).is_none() {
+            return Ok(false);
+        }
+        self.joins.retain(|(_, id)| *id != event_id);
+        self.entries.retain(|(_, id), _| *id != event_id);
+
+        if self.tickets.values().any(|t| t.id_event == Some(event_id)) {
+            return Err(violation(Constraint::Check, "chk_bilet_exclusiv"));
+        }
+        Ok(true)
+    }
+
+    fn delete_packet(&mut self, packet_id: i32) -> Result<bool, Error> {
+        if self.packets.remove(&packet_id).is_none() {
+            return Ok(false);
+        }
+        self.joins.retain(|(id, _)| *id != packet_id);
+
+        if self
+            .tickets
+            .values()
+            .any(|t| t.id_pachet == Some(packet_id))
+        {
+            return Err(violation(Constraint::Check, "chk_bilet_exclusiv"));
+        }
+        Ok(true)
+    }
+
+    // `JoinPeRepo::insert_join`, refused when the event can't seat the
+    // tickets already sold for the packet
+    fn link(
+        &mut self,
+        packet_id: i32,
+        event_id: i32,
+        caller: &AuthUser,
+    ) -> Result<EventPacketRelation, JoinPeRepoError> {
+        let owners = (
+            self.packets.get(&packet_id).map(|p| p.id_owner),
+            self.events.get(&event_id).map(|e| e.id_owner),
+        );
+        if let (Some(packet_owner), Some(event_owner)) = owners
+            && !caller.is_admin()
+            && (packet_owner != caller.id || event_owner != caller.id)
+        {
+            return Err(JoinPeRepoError::Forbidden);
+        }
+
+        let relation = self
+            .insert_join(packet_id, event_id)
+            .map_err(map_sqlx_join_pe_error)?;
+
+        if let Some(capacity) = self.events[&event_id].locuri
+            && self.event_seats_taken(event_id, None) > i64::from(capacity)
+        {
+            return Err(JoinPeRepoError::SoldOut);
+        }
+        Ok(relation)
+    }
+
+    fn insert_join(&mut self, packet_id: i32, event_id: i32) -> Result<EventPacketRelation, Error> {
+        if self.joins.contains(&(packet_id, event_id)) {
+            return Err(violation(Constraint::Unique, "join_pe_pkey"));
+        }
+        if !self.packets.contains_key(&packet_id) {
+            return Err(violation(Constraint::ForeignKey, "join_pe_pachetid_fkey"));
+        }
+        if !self.events.contains_key(&event_id) {
+            return Err(violation(
+                Constraint::ForeignKey,
+                "join_pe_evenimentid_fkey",
+            ));
+        }
+
+        self.joins.insert((packet_id, event_id));
+        Ok(EventPacketRelation {
+            id_pachet: packet_id,
+            id_event: event_id,
+        })
+    }
+
+    // like `TicketRepo::ensure_owner`, a missing target is left to the caller
+    fn ensure_target_owner(
+        &self,
+        target: TicketTarget,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError> {
+        let owner_id = match target {
+            TicketTarget::Event(id) => self.events.get(&id).map(|e| e.id_owner),
+            TicketTarget::Packet(id) => self.packets.get(&id).map(|p| p.id_owner),
+        };
+        match owner_id {
+            Some(owner_id) if !caller.is_admin() && owner_id != caller.id => {
+                Err(TicketRepoError::Forbidden)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn ensure_ticket_owner(&self, cod: &str, caller: &AuthUser) -> Result<(), TicketRepoError> {
+        let ticket = self.tickets.get(cod).ok_or(TicketRepoError::NotFound)?;
+        match TicketTarget::from_ids(ticket.id_pachet, ticket.id_event) {
+            Some(target) => self.ensure_target_owner(target, caller),
+            None => Ok(()),
+        }
+    }
+
+    fn tickets_where(&self, matches: impl Fn(&Ticket) -> bool) -> Vec<Ticket> {
+        self.tickets
+            .values()
+            .filter(|ticket| matches(ticket))
+            .cloned()
+            .collect()
+    }
+}
+
+fn check_exclusive(ticket: &Ticket) -> Result<(), Error> {
+    if ticket.id_pachet.is_some() == ticket.id_event.is_some() {
+        return Err(violation(Constraint::Check, "chk_bilet_exclusiv"));
+    }
+    Ok(())
+}
+
+// the owner check of the postgres repositories, a missing row is RowNotFound
+fn ensure_owner(owner_id: Option<i32>, caller: &AuthUser) -> Result<bool, Error> {
+    let owner_id = owner_id.ok_or(Error::RowNotFound)?;
+    Ok(caller.is_admin() || owner_id == caller.id)
+}
+
+// the rows, order and page that `push_keyset`, `push_order_by` and
+// `push_limit` ask postgres for. `total` counts the rows before the cursor
+fn paginate<T: SortKey>(mut rows: Vec<T>, params: &PaginationParams) -> Page<T> {
+    let total = rows.len() as i64;
+    let field = params.sort_by();
+    let descending = params.order() == SortOrder::Desc;
+
+    rows.sort_by(|a, b| {
+        let ordering = (field.and_then(|f| a.sort_key(f)), a.tie_key())
+            .cmp(&(field.and_then(|f| b.sort_key(f)), b.tie_key()));
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    if let Some(cursor) = &params.cursor {
+        rows.retain(|row| {
+            let ordering = match (field.and_then(|f| row.sort_key(f)), &cursor.key) {
+                (Some(key), Some(cursor_key)) => {
+                    (&key, &row.tie_key()).cmp(&(cursor_key, &cursor.tie))
+                }
+                _ => row.tie_key().cmp(&cursor.tie),
+            };
+            if descending {
+                ordering.is_lt()
+            } else {
+                ordering.is_gt()
+            }
+        });
+    }
+
+    let rows = if params.is_keyset() {
+        rows.into_iter()
+            .take(params.items_per_page() as usize + 1)
+            .collect()
+    } else {
+        rows.into_iter()
+            .skip(params.offset() as usize)
+            .take(params.items_per_page() as usize)
+            .collect()
+    };
+
+    Page::from_rows(rows, total, params)
+}
+
+// `unaccent(text) ILIKE unaccent(pattern)`, for the accents unaccent knows
+// from romanian and its neighbours
+fn ilike(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = fold(text).chars().collect();
+    let pattern = like_pattern(&fold(pattern));
+
+    // greedy wildcard matching that backtracks to the last `%`
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(LikeToken::Many) => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(LikeToken::One) => {
+                p += 1;
+                t += 1;
+            }
+            Some(LikeToken::Char(c)) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, from)) => {
+                    backtrack = Some((star, from + 1));
+                    p = star + 1;
+                    t = from + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|token| *token == LikeToken::Many)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LikeToken {
+    Many,
+    One,
+    Char(char),
+}
+
+// `%` and `_` are wildcards, a backslash escapes the character after it
+fn like_pattern(pattern: &str) -> Vec<LikeToken> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '%' => LikeToken::Many,
+            '_' => LikeToken::One,
+            '\\' => LikeToken::Char(chars.next().unwrap_or('\\')),
+            c => LikeToken::Char(c),
+        });
+    }
+    tokens
+}
+
+fn fold(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ă' | 'â' | 'á' | 'à' | 'ä' | 'ã' | 'å' => 'a',
+            'î' | 'í' | 'ì' | 'ï' => 'i',
+            'ș' | 'ş' | 'š' | 'ś' => 's',
+            'ț' | 'ţ' => 't',
+            'é' | 'è' | 'ê' | 'ë' | 'ě' => 'e',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ő' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' | 'ű' => 'u',
+            'ç' | 'č' | 'ć' => 'c',
+            'ž' | 'ź' | 'ż' => 'z',
+            'ñ' | 'ń' => 'n',
+            c => c,
+        })
+        .collect()
+}
+
+fn matches_event_filters(tables: &Tables, event: &Event, params: &EventQuery) -> bool {
+    let location = params.locatie.as_deref().filter(|s| !s.is_empty());
+    let name = params.nume.as_deref().filter(|s| !s.is_empty());
+    let now = OffsetDateTime::now_utc();
+
+    location.is_none_or(|location| {
+        event
+            .locatie
+            .as_deref()
+            .is_some_and(|l| ilike(l, &format!("{}%", location)))
+    }) && name.is_none_or(|name| ilike(&event.nume, &format!("%{}%", name)))
+        && params
+            .from
+            .is_none_or(|from| event.data_inceput.is_some_and(|start| start >= from))
+        && params
+            .to
+            .is_none_or(|to| event.data_inceput.is_some_and(|start| start <= to))
+        && params.bilete.is_none_or(|min_tickets| {
+            tables
+                .event_seats_left(event)
+                .is_none_or(|left| left >= i64::from(min_tickets))
+        })
+        && (params.upcoming != Some(true)
+            || event
+                .data_sfarsit
+                .or(event.data_inceput)
+                .is_some_and(|end| end >= now))
+}
+
+fn matches_packet_filters(
+    tables: &Tables,
+    packet: &EventPackets,
+    params: &EventPacketQuery,
+) -> bool {
+    let type_filter = params.descriere.as_deref().filter(|s| !s.is_empty());
+
+    type_filter.is_none_or(|desc_filter| {
+        packet
+            .descriere
+            .as_deref()
+            .is_some_and(|d| ilike(d, &format!("%{}%", desc_filter)))
+    }) && params.bilete.is_none_or(|min_tickets| {
+        tables
+            .packet_seats_left(packet)
+            .is_none_or(|left| left >= i64::from(min_tickets))
+    })
+}
+
+#[async_trait]
+impl EventRepository for InMemoryRepo {
+    async fn check(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn list_events(&self, params: EventQuery) -> Result<Page<Event>, EventRepoError> {
+        Ok(self.read(|tables| {
+            let events = tables
+                .events
+                .keys()
+                .filter_map(|id| tables.event(*id))
+                .filter(|event| matches_event_filters(tables, event, &params))
+                .collect();
+            paginate(events, &params.paginare)
+        }))
+    }
+
+    async fn get_event(&self, event_id: i32) -> Result<Event, EventRepoError> {
+        self.read(|tables| tables.event(event_id))
+            .ok_or(EventRepoError::NotFound)
+    }
+
+    async fn get_check_in_stats(&self, event_id: i32) -> Result<CheckInStats, EventRepoError> {
+        self.read(|tables| {
+            tables.events.get(&event_id).ok_or(Error::RowNotFound)?;
+
+            let packet_tickets = tables
+                .joins
+                .iter()
+                .filter(|(_, id)| *id == event_id)
+                .map(|(packet_id, _)| tables.packet_sold(*packet_id, None))
+                .sum::<i64>();
+            let checked_in = tables
+                .tickets
+                .values()
+                .filter(|t| t.id_event == Some(event_id) && t.checked_in_at.is_some())
+                .count() as i64;
+            let entries = tables
+                .entries
+                .keys()
+                .filter(|(_, id)| *id == event_id)
+                .count() as i64;
+
+            let bilete_emise = tables.event_sold(event_id, None) + packet_tickets;
+            let intrari = checked_in + entries;
+            Ok(CheckInStats {
+                id_event: event_id,
+                bilete_emise,
+                intrari,
+                ramase: bilete_emise - intrari,
+            })
+        })
+        .map_err(map_sqlx_event_error)
+    }
+
+    async fn create_event(
+        &self,
+        owner_id: i32,
+        payload: CreateEvent,
+    ) -> Result<Event, EventRepoError> {
+        self.transaction(|tables| {
+            tables.last_event_id += 1;
+            let event = Event {
+                id: tables.last_event_id,
+                id_owner: owner_id,
+                nume: payload.nume,
+                locatie: payload.locatie,
+                descriere: payload.descriere,
+                locuri: payload.locuri,
+                data_inceput: Some(payload.data_inceput),
+                data_sfarsit: payload.data_sfarsit,
+                fus_orar: payload.fus_orar,
+                locuri_disponibile: None,
+            };
+            tables.check_event(&event)?;
+
+            tables.events.insert(event.id, event);
+            tables.event(tables.last_event_id).ok_or(Error::RowNotFound)
+        })
+        .map_err(map_sqlx_event_error)
+    }
+
+    async fn update_event(
+        &self,
+        event_id: i32,
+        payload: UpdateEvent,
+        caller: &AuthUser,
+    ) -> Result<Event, EventRepoError> {
+        self.transaction(|tables| {
+            let owner = tables.events.get(&event_id).map(|e| e.id_owner);
+            if !ensure_owner(owner, caller).map_err(map_sqlx_event_error)? {
+                return Err(EventRepoError::Forbidden);
+            }
+
+            let current = tables.events[&event_id].clone();
+            let event = Event {
+                nume: payload.nume,
+                locatie: payload.locatie.or(current.locatie),
+                descriere: payload.descriere.or(current.descriere),
+                locuri: payload.locuri.or(current.locuri),
+                data_inceput: payload.data_inceput.or(current.data_inceput),
+                data_sfarsit: payload.data_sfarsit.or(current.data_sfarsit),
+                fus_orar: payload.fus_orar.or(current.fus_orar),
+                ..current
+            };
+            tables.check_event(&event).map_err(map_sqlx_event_error)?;
+
+            tables.events.insert(event_id, event);
+            tables.event(event_id).ok_or(EventRepoError::NotFound)
+        })
+    }
+
+    async fn delete_event(&self, event_id: i32, caller: &AuthUser) -> Result<(), EventRepoError> {
+        self.transaction(|tables| {
+            let owner = tables.events.get(&event_id).map(|e| e.id_owner);
+            if !ensure_owner(owner, caller).map_err(map_sqlx_event_error)? {
+                return Err(EventRepoError::Forbidden);
+            }
+
+            match tables.delete_event(event_id) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(EventRepoError::NotFound),
+                Err(e) => Err(map_sqlx_event_error(e)),
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl EventPacketRepository for InMemoryRepo {
+    async fn list_event_packets(
+        &self,
+        params: EventPacketQuery,
+    ) -> Result<Page<EventPackets>, EventPacketRepoError> {
+        Ok(self.read(|tables| {
+            let packets = tables
+                .packets
+                .keys()
+                .filter_map(|id| tables.packet(*id))
+                .filter(|packet| matches_packet_filters(tables, packet, &params))
+                .collect();
+            paginate(packets, &params.paginare)
+        }))
+    }
+
+    async fn get_event_packet(&self, packet_id: i32) -> Result<EventPackets, EventPacketRepoError> {
+        self.read(|tables| tables.packet(packet_id))
+            .ok_or(EventPacketRepoError::NotFound)
+    }
+
+    async fn create_event_packet(
+        &self,
+        owner_id: i32,
+        payload: CreateEventPacket,
+    ) -> Result<EventPackets, EventPacketRepoError> {
+        self.transaction(|tables| {
+            tables.last_packet_id += 1;
+            let packet = EventPackets {
+                id: tables.last_packet_id,
+                id_owner: owner_id,
+                nume: payload.nume,
+                locatie: payload.locatie,
+                descriere: payload.descriere,
+                numarlocuri: payload.numarlocuri,
+                locuri_disponibile: None,
+            };
+            tables.check_packet(&packet)?;
+
+            tables.packets.insert(packet.id, packet);
+            tables
+                .packet(tables.last_packet_id)
+                .ok_or(Error::RowNotFound)
+        })
+        .map_err(map_sqlx_packet_error)
+    }
+
+    async fn update_event_packet(
+        &self,
+        packet_id: i32,
+        payload: UpdateEventPacket,
+        caller: &AuthUser,
+    ) -> Result<EventPackets, EventPacketRepoError> {
+        self.transaction(|tables| {
+            let owner = tables.packets.get(&packet_id).map(|p| p.id_owner);
+            if !ensure_owner(owner, caller).map_err(map_sqlx_packet_error)? {
+                return Err(EventPacketRepoError::Forbidden);
+            }
+
+            let current = tables.packets[&packet_id].clone();
+            let packet = EventPackets {
+                nume: payload.nume,
+                locatie: payload.locatie.or(current.locatie),
+                descriere: payload.descriere.or(current.descriere),
+                numarlocuri: payload.numarlocuri.or(current.numarlocuri),
+                ..current
+            };
+            tables
+                .check_packet(&packet)
+                .map_err(map_sqlx_packet_error)?;
+
+            tables.packets.insert(packet_id, packet);
+            tables
+                .packet(packet_id)
+                .ok_or(EventPacketRepoError::NotFound)
+        })
+    }
+
+    async fn delete_event_packet(
+        &self,
+        packet_id: i32,
+        caller: &AuthUser,
+    ) -> Result<(), EventPacketRepoError> {
+        self.transaction(|tables| {
+            let owner = tables.packets.get(&packet_id).map(|p| p.id_owner);
+            if !ensure_owner(owner, caller).map_err(map_sqlx_packet_error)? {
+                return Err(EventPacketRepoError::Forbidden);
+            }
+
+            match tables.delete_packet(packet_id) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(EventPacketRepoError::NotFound),
+                Err(e) => Err(map_sqlx_packet_error(e)),
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl JoinPeRepository for InMemoryRepo {
+    async fn get_events_for_packet(
+        &self,
+        pachet_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Event>, JoinPeRepoError> {
+        Ok(self.read(|tables| {
+            let events = tables
+                .joins
+                .iter()
+                .filter(|(packet_id, _)| *packet_id == pachet_id)
+                .filter_map(|(_, event_id)| tables.event(*event_id))
+                .collect();
+            paginate(events, params)
+        }))
+    }
+
+    async fn get_packets_for_event(
+        &self,
+        eveniment_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<EventPackets>, JoinPeRepoError> {
+        Ok(self.read(|tables| {
+            let packets = tables
+                .joins
+                .iter()
+                .filter(|(_, event_id)| *event_id == eveniment_id)
+                .filter_map(|(packet_id, _)| tables.packet(*packet_id))
+                .collect();
+            paginate(packets, params)
+        }))
+    }
+
+    async fn add_event_to_packet(
+        &self,
+        eveniment_id: i32,
+        payload: AddEventToPacket,
+        caller: &AuthUser,
+    ) -> Result<EventPacketRelation, JoinPeRepoError> {
+        self.transaction(|tables| tables.link(payload.id_pachet, eveniment_id, caller))
+    }
+
+    async fn add_packet_to_event(
+        &self,
+        pachet_id: i32,
+        payload: AddPacketToEvent,
+        caller: &AuthUser,
+    ) -> Result<EventPacketRelation, JoinPeRepoError> {
+        self.transaction(|tables| tables.link(pachet_id, payload.id_event, caller))
+    }
+}
+
+#[async_trait]
+impl TicketRepository for InMemoryRepo {
+    async fn list_tickets(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        Ok(self.read(|tables| paginate(tables.tickets_where(|_| true), params)))
+    }
+
+    async fn get_ticket(&self, cod: &str) -> Result<Ticket, TicketRepoError> {
+        self.read(|tables| tables.tickets.get(cod).cloned())
+            .ok_or(TicketRepoError::NotFound)
+    }
+
+    async fn create_ticket(
+        &self,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.transaction(|tables| {
+            if let Some(target) = TicketTarget::from_ids(payload.id_pachet, payload.id_event) {
+                tables.ensure_target_owner(target, caller)?;
+                tables.reserve_seats(target, 1, None)?;
+            }
+            tables
+                .insert_ticket(payload.cod, payload.id_pachet, payload.id_event)
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn update_ticket(
+        &self,
+        cod: &str,
+        payload: UpdateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_ticket_owner(cod, caller)?;
+            if let Some(target) = TicketTarget::from_ids(payload.id_pachet, payload.id_event) {
+                tables.ensure_target_owner(target, caller)?;
+                tables.reserve_seats(target, 1, Some(cod))?;
+            }
+            tables
+                .update_ticket(cod, |_| true, payload.id_pachet, payload.id_event)
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn delete_ticket(&self, cod: &str, caller: &AuthUser) -> Result<(), TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_ticket_owner(cod, caller)?;
+            if tables.delete_ticket(cod, |_| true) {
+                Ok(())
+            } else {
+                Err(TicketRepoError::NotFound)
+            }
+        })
+    }
+
+    async fn list_tickets_for_event(
+        &self,
+        event_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        Ok(self.read(|tables| {
+            paginate(
+                tables.tickets_where(|t| t.id_event == Some(event_id)),
+                params,
+            )
+        }))
+    }
+
+    async fn get_ticket_for_event(
+        &self,
+        event_id: i32,
+        cod: &str,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.read(|tables| tables.tickets.get(cod).cloned())
+            .filter(|t| t.id_event == Some(event_id))
+            .ok_or(TicketRepoError::NotFound)
+    }
+
+    async fn create_ticket_for_event(
+        &self,
+        event_id: i32,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Event(event_id), caller)?;
+            tables.reserve_seats(TicketTarget::Event(event_id), 1, None)?;
+            tables
+                .insert_ticket(payload.cod, None, Some(event_id))
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn create_tickets_for_event(
+        &self,
+        event_id: i32,
+        codes: Vec<String>,
+        caller: &AuthUser,
+    ) -> Result<Vec<Ticket>, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Event(event_id), caller)?;
+            tables.reserve_seats(TicketTarget::Event(event_id), codes.len() as i64, None)?;
+            codes
+                .into_iter()
+                .map(|cod| tables.insert_ticket(Some(cod), None, Some(event_id)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn update_ticket_for_event(
+        &self,
+        event_id: i32,
+        cod: &str,
+        payload: UpdateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Event(event_id), caller)?;
+            if let Some(packet_id) = payload.id_pachet {
+                tables.ensure_target_owner(TicketTarget::Packet(packet_id), caller)?;
+                tables.reserve_seats(TicketTarget::Packet(packet_id), 1, Some(cod))?;
+            }
+            tables
+                .update_ticket(
+                    cod,
+                    |t| t.id_event == Some(event_id),
+                    payload.id_pachet,
+                    None,
+                )
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn delete_ticket_for_event(
+        &self,
+        event_id: i32,
+        cod: String,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Event(event_id), caller)?;
+            if tables.delete_ticket(&cod, |t| t.id_event == Some(event_id)) {
+                Ok(())
+            } else {
+                Err(TicketRepoError::NotFound)
+            }
+        })
+    }
+
+    async fn list_tickets_for_packet(
+        &self,
+        packet_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        Ok(self.read(|tables| {
+            paginate(
+                tables.tickets_where(|t| t.id_pachet == Some(packet_id)),
+                params,
+            )
+        }))
+    }
+
+    async fn get_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        cod: &str,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.read(|tables| tables.tickets.get(cod).cloned())
+            .filter(|t| t.id_pachet == Some(packet_id))
+            .ok_or(TicketRepoError::NotFound)
+    }
+
+    async fn create_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Packet(packet_id), caller)?;
+            tables.reserve_seats(TicketTarget::Packet(packet_id), 1, None)?;
+            tables
+                .insert_ticket(payload.cod, Some(packet_id), None)
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn create_tickets_for_packet(
+        &self,
+        packet_id: i32,
+        codes: Vec<String>,
+        caller: &AuthUser,
+    ) -> Result<Vec<Ticket>, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Packet(packet_id), caller)?;
+            tables.reserve_seats(TicketTarget::Packet(packet_id), codes.len() as i64, None)?;
+            codes
+                .into_iter()
+                .map(|cod| tables.insert_ticket(Some(cod), Some(packet_id), None))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn update_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        cod: &str,
+        payload: UpdateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Packet(packet_id), caller)?;
+            if let Some(event_id) = payload.id_event {
+                tables.ensure_target_owner(TicketTarget::Event(event_id), caller)?;
+                tables.reserve_seats(TicketTarget::Event(event_id), 1, Some(cod))?;
+            }
+            tables
+                .update_ticket(
+                    cod,
+                    |t| t.id_pachet == Some(packet_id),
+                    None,
+                    payload.id_event,
+                )
+                .map_err(map_sqlx_ticket_error)
+        })
+    }
+
+    async fn delete_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        cod: &str,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError> {
+        self.transaction(|tables| {
+            tables.ensure_target_owner(TicketTarget::Packet(packet_id), caller)?;
+            if tables.delete_ticket(cod, |t| t.id_pachet == Some(packet_id)) {
+                Ok(())
+            } else {
+                Err(TicketRepoError::NotFound)
+            }
+        })
+    }
+
+    async fn check_in(
+        &self,
+        cod: &str,
+        event_id: Option<i32>,
+        caller: &AuthUser,
+    ) -> Result<CheckIn, TicketRepoError> {
+        self.transaction(|tables| {
+            let ticket = tables
+                .tickets
+                .get(cod)
+                .cloned()
+                .ok_or(TicketRepoError::NotFound)?;
+
+            match (ticket.id_event, ticket.id_pachet) {
+                (Some(ticket_event), _) => {
+                    if event_id.is_some_and(|id| id != ticket_event) {
+                        return Err(TicketRepoError::NotValidForEvent);
+                    }
+                    tables.ensure_target_owner(TicketTarget::Event(ticket_event), caller)?;
+                    if ticket.checked_in_at.is_some() {
+                        return Err(TicketRepoError::AlreadyCheckedIn);
+                    }
+
+                    let checked_in_at = OffsetDateTime::now_utc();
+                    tables.tickets.insert(
+                        cod.to_string(),
+                        Ticket {
+                            checked_in_at: Some(checked_in_at),
+                            ..ticket
+                        },
+                    );
+                    Ok(CheckIn {
+                        cod: cod.to_string(),
+                        id_event: ticket_event,
+                        checked_in_at,
+                    })
+                }
+                (None, Some(packet_id)) => {
+                    let event_id = event_id.ok_or(TicketRepoError::EventRequired)?;
+                    tables.ensure_target_owner(TicketTarget::Event(event_id), caller)?;
+
+                    if !tables.joins.contains(&(packet_id, event_id)) {
+                        return Err(TicketRepoError::NotValidForEvent);
+                    }
+
+                    let key = (cod.to_string(), event_id);
+                    if tables.entries.contains_key(&key) {
+                        return Err(TicketRepoError::AlreadyCheckedIn);
+                    }
+
+                    let checked_in_at = OffsetDateTime::now_utc();
+                    tables.entries.insert(key, checked_in_at);
+                    Ok(CheckIn {
+                        cod: cod.to_string(),
+                        id_event: event_id,
+                        checked_in_at,
+                    })
+                }
+                (None, None) => Err(TicketRepoError::ConstraintViolation),
+            }
+        })
+    }
+}
@@ -1,9 +1,43 @@
 use crate::models::event::Event;
 use crate::models::event_packets::EventPackets;
 use crate::models::join_pe::{AddEventToPacket, AddPacketToEvent, EventPacketRelation};
+use crate::models::pagination::{Page, PaginationParams, SortField};
+use crate::repositories::event_packets_repo::packet_seats_left;
+use crate::repositories::event_repo::event_seats_left;
+use crate::repositories::pagination::{push_keyset, push_limit, push_order_by};
+use crate::shared::auth::AuthUser;
 use crate::shared::error::{JoinPeRepoError, map_sqlx_join_pe_error};
+use crate::shared::metrics::QueryTimer;
 use anyhow::Result;
-use sqlx::PgPool;
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use tracing::instrument;
+
+#[async_trait]
+pub trait JoinPeRepository: Send + Sync {
+    async fn get_events_for_packet(
+        &self,
+        pachet_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Event>, JoinPeRepoError>;
+    async fn get_packets_for_event(
+        &self,
+        eveniment_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<EventPackets>, JoinPeRepoError>;
+    async fn add_event_to_packet(
+        &self,
+        eveniment_id: i32,
+        payload: AddEventToPacket,
+        caller: &AuthUser,
+    ) -> Result<EventPacketRelation, JoinPeRepoError>;
+    async fn add_packet_to_event(
+        &self,
+        pachet_id: i32,
+        payload: AddPacketToEvent,
+        caller: &AuthUser,
+    ) -> Result<EventPacketRelation, JoinPeRepoError>;
+}
 
 pub struct JoinPeRepo {
     pool: PgPool,
@@ -14,77 +48,189 @@ impl JoinPeRepo {
         Self { pool }
     }
 
-    pub async fn get_events_for_packet(
+    // the tickets already sold for the packet take a seat at the event too, so
+    // the link is refused when the event can't hold them. the packet is locked
+    // before the event, the same order `TicketRepo` issues packet tickets in.
+    // the caller has to own both sides, admins own everything
+    async fn insert_join(
         &self,
         pachet_id: i32,
-    ) -> Result<Vec<Event>, JoinPeRepoError> {
-        sqlx::query_as::<_, Event>(
+        eveniment_id: i32,
+        caller: &AuthUser,
+    ) -> Result<EventPacketRelation, JoinPeRepoError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_join_pe_error)?;
+
+        let packet_owner: i32 =
+            sqlx::query_scalar("SELECT ID_OWNER FROM PACHETE WHERE ID = $1 FOR UPDATE")
+                .bind(pachet_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(map_sqlx_join_pe_error)?
+                .ok_or(JoinPeRepoError::InvalidReference)?;
+
+        let (event_owner, capacity): (i32, Option<i32>) =
+            sqlx::query_as("SELECT ID_OWNER, numarlocuri FROM EVENIMENTE WHERE ID = $1 FOR UPDATE")
+                .bind(eveniment_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(map_sqlx_join_pe_error)?
+                .ok_or(JoinPeRepoError::InvalidReference)?;
+
+        if !caller.is_admin() && (packet_owner != caller.id || event_owner != caller.id) {
+            return Err(JoinPeRepoError::Forbidden);
+        }
+
+        let relation = sqlx::query_as::<_, EventPacketRelation>(
             r#"
-            SELECT e.id, e.id_owner, e.nume, e.locatie, e.descriere, e.numarlocuri
-            FROM EVENIMENTE e
-            JOIN JOIN_PE j ON e.id = j.evenimentid
-            WHERE j.pachetid = $1
+            INSERT INTO JOIN_PE (pachetid, evenimentid)
+            VALUES ($1, $2)
+            RETURNING pachetid, evenimentid
             "#,
         )
         .bind(pachet_id)
-        .fetch_all(&self.pool)
+        .bind(eveniment_id)
+        .fetch_one(&mut *tx)
         .await
-        .map_err(map_sqlx_join_pe_error)
+        .map_err(map_sqlx_join_pe_error)?;
+
+        if let Some(capacity) = capacity {
+            let sold: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(*) FROM BILETE
+                WHERE evenimentid = $1
+                   OR pachetid IN (SELECT pachetid FROM JOIN_PE WHERE evenimentid = $1)
+                "#,
+            )
+            .bind(eveniment_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(map_sqlx_join_pe_error)?;
+
+            if sold > i64::from(capacity) {
+                return Err(JoinPeRepoError::SoldOut);
+            }
+        }
+
+        tx.commit().await.map_err(map_sqlx_join_pe_error)?;
+
+        Ok(relation)
+    }
+}
+
+#[async_trait]
+impl JoinPeRepository for JoinPeRepo {
+    #[instrument(name = "JoinPeRepo::get_events_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_events_for_packet(
+        &self,
+        pachet_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Event>, JoinPeRepoError> {
+        let _timer = QueryTimer::start("JoinPeRepo", "get_events_for_packet");
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM JOIN_PE WHERE pachetid = $1")
+            .bind(pachet_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_sqlx_join_pe_error)?;
+
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+            r#"
+            SELECT e.id, e.id_owner, e.nume, e.locatie, e.descriere, e.numarlocuri,
+                   e.data_inceput, e.data_sfarsit, e.fus_orar,
+                   {} AS locuri_disponibile
+            FROM EVENIMENTE e
+            JOIN JOIN_PE jp ON e.id = jp.evenimentid
+            WHERE jp.pachetid = "#,
+            event_seats_left("e.numarlocuri", "e.id"),
+        ));
+        query_builder.push_bind(pachet_id);
+        push_keyset(&mut query_builder, true, params, event_sort_column, "e.id");
+        push_order_by(&mut query_builder, params, event_sort_column, "e.id");
+        push_limit(&mut query_builder, params);
+
+        let events = query_builder
+            .build_query_as::<Event>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(map_sqlx_join_pe_error)?;
+
+        Ok(Page::from_rows(events, total, params))
     }
 
-    pub async fn get_packets_for_event(
+    #[instrument(name = "JoinPeRepo::get_packets_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_packets_for_event(
         &self,
         eveniment_id: i32,
-    ) -> Result<Vec<EventPackets>, JoinPeRepoError> {
-        sqlx::query_as::<_, EventPackets>(
+        params: &PaginationParams,
+    ) -> Result<Page<EventPackets>, JoinPeRepoError> {
+        let _timer = QueryTimer::start("JoinPeRepo", "get_packets_for_event");
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM JOIN_PE WHERE evenimentid = $1")
+            .bind(eveniment_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_sqlx_join_pe_error)?;
+
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(format!(
             r#"
-            SELECT p.id, p.id_owner, p.nume, p.locatie, p.descriere, p.numarlocuri
+            SELECT p.id, p.id_owner, p.nume, p.locatie, p.descriere, p.numarlocuri,
+                   {} AS locuri_disponibile
             FROM PACHETE p
-            JOIN JOIN_PE j ON p.id = j.pachetid
-            WHERE j.evenimentid = $1
-            "#,
-        )
-        .bind(eveniment_id)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(map_sqlx_join_pe_error)
+            JOIN JOIN_PE jp ON p.id = jp.pachetid
+            WHERE jp.evenimentid = "#,
+            packet_seats_left("p.numarlocuri", "p.id"),
+        ));
+        query_builder.push_bind(eveniment_id);
+        push_keyset(&mut query_builder, true, params, packet_sort_column, "p.id");
+        push_order_by(&mut query_builder, params, packet_sort_column, "p.id");
+        push_limit(&mut query_builder, params);
+
+        let packets = query_builder
+            .build_query_as::<EventPackets>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(map_sqlx_join_pe_error)?;
+
+        Ok(Page::from_rows(packets, total, params))
     }
 
-    pub async fn add_event_to_packet(
+    #[instrument(name = "JoinPeRepo::add_event_to_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn add_event_to_packet(
         &self,
         eveniment_id: i32,
         payload: AddEventToPacket,
+        caller: &AuthUser,
     ) -> Result<EventPacketRelation, JoinPeRepoError> {
-        sqlx::query_as::<_, EventPacketRelation>(
-            r#"
-            INSERT INTO JOIN_PE (pachetid, evenimentid)
-            VALUES ($1, $2)
-            RETURNING pachetid, evenimentid
-            "#,
-        )
-        .bind(payload.id_pachet)
-        .bind(eveniment_id)
-        .fetch_one(&self.pool)
-        .await
-        .map_err(map_sqlx_join_pe_error)
+        let _timer = QueryTimer::start("JoinPeRepo", "add_event_to_packet");
+        self.insert_join(payload.id_pachet, eveniment_id, caller)
+            .await
     }
 
-    pub async fn add_packet_to_event(
+    #[instrument(name = "JoinPeRepo::add_packet_to_event", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn add_packet_to_event(
         &self,
         pachet_id: i32,
         payload: AddPacketToEvent,
+        caller: &AuthUser,
     ) -> Result<EventPacketRelation, JoinPeRepoError> {
-        sqlx::query_as::<_, EventPacketRelation>(
-            r#"
-            INSERT INTO JOIN_PE (pachetid, evenimentid)
-            VALUES ($1, $2)
-            RETURNING pachetid, evenimentid
-            "#,
-        )
-        .bind(pachet_id)
-        .bind(payload.id_event)
-        .fetch_one(&self.pool)
-        .await
-        .map_err(map_sqlx_join_pe_error)
+        let _timer = QueryTimer::start("JoinPeRepo", "add_packet_to_event");
+        self.insert_join(pachet_id, payload.id_event, caller).await
+    }
+}
+
+// the listings of the event and packet repositories with table prefixes
+fn event_sort_column(field: SortField) -> Option<&'static str> {
+    match field {
+        SortField::Name => Some("e.nume"),
+        SortField::Location => Some("COALESCE(e.locatie, '')"),
+        SortField::Seats => Some("COALESCE(e.numarlocuri, 2147483647)"),
+        SortField::Id => Some("e.id"),
+    }
+}
+
+fn packet_sort_column(field: SortField) -> Option<&'static str> {
+    match field {
+        SortField::Name => Some("p.nume"),
+        SortField::Location => Some("COALESCE(p.locatie, '')"),
+        SortField::Seats => Some("COALESCE(p.numarlocuri, 2147483647)"),
+        SortField::Id => Some("p.id"),
     }
 }
@@ -1,4 +1,10 @@
 pub mod event_packets_repo;
 pub mod event_repo;
+pub mod health_repo;
+// only for tests, the integration tests turn it on through the `test-util`
+// feature of their dev-dependency on this crate
+#[cfg(any(test, feature = "test-util"))]
+pub mod in_memory;
 pub mod join_pe_repo;
+pub mod pagination;
 pub mod ticket_repo;
@@ -0,0 +1,95 @@
+use crate::models::pagination::{CursorKey, PaginationParams, SortField, SortOrder};
+use sqlx::{Postgres, QueryBuilder};
+
+// maps the public sort keys onto one listing's sort expressions. they must
+// never be NULL (wrap nullable columns in COALESCE) or the keyset comparison
+// in `push_keyset` would skip rows
+pub type SortColumns = fn(SortField) -> Option<&'static str>;
+
+fn direction(params: &PaginationParams) -> (&'static str, &'static str) {
+    match params.order() {
+        SortOrder::Asc => ("ASC", ">"),
+        SortOrder::Desc => ("DESC", "<"),
+    }
+}
+
+fn sort_column(
+    params: &PaginationParams,
+    columns: SortColumns,
+    tie_breaker: &'static str,
+) -> Option<&'static str> {
+    params
+        .sort_by()
+        .and_then(columns)
+        .filter(|column| *column != tie_breaker)
+}
+
+fn push_key(query_builder: &mut QueryBuilder<Postgres>, key: &CursorKey) {
+    match key {
+        CursorKey::Int(value) => query_builder.push_bind(*value),
+        CursorKey::Text(value) => query_builder.push_bind(value.clone()),
+    };
+}
+
+// in keyset mode, keeps only the rows after the cursor. `has_condition` tells
+// whether the query already has a WHERE clause
+pub fn push_keyset(
+    query_builder: &mut QueryBuilder<Postgres>,
+    has_condition: bool,
+    params: &PaginationParams,
+    columns: SortColumns,
+    tie_breaker: &'static str,
+) {
+    let Some(cursor) = &params.cursor else {
+        return;
+    };
+
+    let (_, comparison) = direction(params);
+    query_builder.push(if has_condition { " AND " } else { " WHERE " });
+
+    match (sort_column(params, columns, tie_breaker), &cursor.key) {
+        (Some(column), Some(key)) => {
+            query_builder.push(format!("({}, {}) {} (", column, tie_breaker, comparison));
+            push_key(query_builder, key);
+            query_builder.push(", ");
+            push_key(query_builder, &cursor.tie);
+            query_builder.push(")");
+        }
+        _ => {
+            query_builder.push(format!("{} {} ", tie_breaker, comparison));
+            push_key(query_builder, &cursor.tie);
+        }
+    }
+}
+
+// anything `columns` doesn't know sorts by `tie_breaker`. the tie breaker is
+// always appended so rows with equal keys keep the same order between pages
+pub fn push_order_by(
+    query_builder: &mut QueryBuilder<Postgres>,
+    params: &PaginationParams,
+    columns: SortColumns,
+    tie_breaker: &'static str,
+) {
+    let (direction, _) = direction(params);
+
+    query_builder.push(" ORDER BY ");
+
+    if let Some(column) = sort_column(params, columns, tie_breaker) {
+        query_builder.push(format!("{} {}, ", column, direction));
+    }
+
+    query_builder.push(format!("{} {}", tie_breaker, direction));
+}
+
+// keyset mode asks for one extra row, see `Page::from_rows`
+pub fn push_limit(query_builder: &mut QueryBuilder<Postgres>, params: &PaginationParams) {
+    if params.is_keyset() {
+        query_builder.push(" LIMIT ");
+        query_builder.push_bind(params.items_per_page() + 1);
+    } else {
+        query_builder.push(" LIMIT ");
+        query_builder.push_bind(params.items_per_page());
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(params.offset());
+    }
+}
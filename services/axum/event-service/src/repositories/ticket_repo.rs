@@ -1,43 +1,387 @@
+use crate::models::check_in::CheckIn;
+use crate::models::pagination::{CursorKey, KeyKind, Page, PaginationParams, SortField, SortKey};
 use crate::models::ticket::{CreateTicket, Ticket, UpdateTicket};
+use crate::repositories::pagination::{push_keyset, push_limit, push_order_by};
+use crate::shared::auth::AuthUser;
 use crate::shared::error::{TicketRepoError, map_sqlx_ticket_error};
+use crate::shared::metrics::QueryTimer;
 use anyhow::Result;
-use sqlx::PgPool;
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use tracing::instrument;
+
+#[async_trait]
+pub trait TicketRepository: Send + Sync {
+    async fn list_tickets(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError>;
+    async fn get_ticket(&self, cod: &str) -> Result<Ticket, TicketRepoError>;
+    async fn create_ticket(
+        &self,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn update_ticket(
+        &self,
+        cod: &str,
+        payload: UpdateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn delete_ticket(&self, cod: &str, caller: &AuthUser) -> Result<(), TicketRepoError>;
+
+    async fn list_tickets_for_event(
+        &self,
+        event_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError>;
+    async fn get_ticket_for_event(
+        &self,
+        event_id: i32,
+        cod: &str,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn create_ticket_for_event(
+        &self,
+        event_id: i32,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn create_tickets_for_event(
+        &self,
+        event_id: i32,
+        codes: Vec<String>,
+        caller: &AuthUser,
+    ) -> Result<Vec<Ticket>, TicketRepoError>;
+    async fn update_ticket_for_event(
+        &self,
+        event_id: i32,
+        cod: &str,
+        payload: UpdateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn delete_ticket_for_event(
+        &self,
+        event_id: i32,
+        cod: String,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError>;
+
+    async fn list_tickets_for_packet(
+        &self,
+        packet_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError>;
+    async fn get_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        cod: &str,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn create_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn create_tickets_for_packet(
+        &self,
+        packet_id: i32,
+        codes: Vec<String>,
+        caller: &AuthUser,
+    ) -> Result<Vec<Ticket>, TicketRepoError>;
+    async fn update_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        cod: &str,
+        payload: UpdateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError>;
+    async fn delete_ticket_for_packet(
+        &self,
+        packet_id: i32,
+        cod: &str,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError>;
+
+    async fn check_in(
+        &self,
+        cod: &str,
+        event_id: Option<i32>,
+        caller: &AuthUser,
+    ) -> Result<CheckIn, TicketRepoError>;
+}
 
 pub struct TicketRepo {
     pool: PgPool,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TicketTarget {
+    Event(i32),
+    Packet(i32),
+}
+
+impl TicketTarget {
+    pub(crate) fn from_ids(id_pachet: Option<i32>, id_event: Option<i32>) -> Option<Self> {
+        match (id_pachet, id_event) {
+            (Some(packet_id), None) => Some(TicketTarget::Packet(packet_id)),
+            (None, Some(event_id)) => Some(TicketTarget::Event(event_id)),
+            _ => None,
+        }
+    }
+}
+
+// tickets only have their code to sort by
+pub const TICKET_SORT_FIELDS: &[SortField] = &[SortField::Id];
+
+fn ticket_sort_column(field: SortField) -> Option<&'static str> {
+    match field {
+        SortField::Id => Some("cod"),
+        _ => None,
+    }
+}
+
+impl SortKey for Ticket {
+    const TIE_KIND: KeyKind = KeyKind::Text;
+
+    fn sort_key(&self, _field: SortField) -> Option<CursorKey> {
+        None
+    }
+
+    fn tie_key(&self) -> CursorKey {
+        CursorKey::Text(self.cod.clone())
+    }
+}
+
+fn push_target_filter(query_builder: &mut QueryBuilder<Postgres>, target: Option<TicketTarget>) {
+    match target {
+        Some(TicketTarget::Event(id)) => {
+            query_builder.push(" WHERE evenimentid = ");
+            query_builder.push_bind(id);
+        }
+        Some(TicketTarget::Packet(id)) => {
+            query_builder.push(" WHERE pachetid = ");
+            query_builder.push_bind(id);
+        }
+        None => {}
+    }
+}
+
 impl TicketRepo {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
 
-    pub async fn list_tickets_for_event(
-        &self,
+    // tickets belong to whoever owns their event or packet, admins own them
+    // all. the row is locked like in `EventRepo::ensure_owner`; a missing target
+    // is left to the statement that follows, which reports it as before
+    async fn ensure_owner(
+        conn: &mut PgConnection,
+        target: TicketTarget,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError> {
+        let (sql, id) = match target {
+            TicketTarget::Event(id) => (
+                "SELECT ID_OWNER FROM EVENIMENTE WHERE ID = $1 FOR UPDATE",
+                id,
+            ),
+            TicketTarget::Packet(id) => {
+                ("SELECT ID_OWNER FROM PACHETE WHERE ID = $1 FOR UPDATE", id)
+            }
+        };
+        let owner_id: Option<i32> = sqlx::query_scalar(sql)
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(map_sqlx_ticket_error)?;
+
+        match owner_id {
+            Some(owner_id) if !caller.is_admin() && owner_id != caller.id => {
+                Err(TicketRepoError::Forbidden)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // the same for a ticket found by its code, which is locked first
+    async fn ensure_ticket_owner(
+        conn: &mut PgConnection,
+        cod: &str,
+        caller: &AuthUser,
+    ) -> Result<(), TicketRepoError> {
+        let (id_pachet, id_event): (Option<i32>, Option<i32>) =
+            sqlx::query_as("SELECT pachetid, evenimentid FROM BILETE WHERE cod = $1 FOR UPDATE")
+                .bind(cod)
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(map_sqlx_ticket_error)?;
+
+        match TicketTarget::from_ids(id_pachet, id_event) {
+            Some(target) => Self::ensure_owner(conn, target, caller).await,
+            None => Ok(()),
+        }
+    }
+
+    // the event/packet row stays locked until the surrounding transaction ends,
+    // so concurrent purchases for the same target are serialized here. a packet
+    // ticket also takes a seat at every event of the packet, so those events are
+    // checked and locked too, after the packet and in ID order like
+    // `JoinPeRepo` does. `moved_ticket` is left out of the count when a ticket
+    // is re-assigned
+    async fn reserve_seats(
+        conn: &mut PgConnection,
+        target: TicketTarget,
+        seats: i64,
+        moved_ticket: Option<&str>,
+    ) -> Result<(), TicketRepoError> {
+        match target {
+            TicketTarget::Event(id) => {
+                Self::reserve_event_seats(conn, id, seats, moved_ticket).await
+            }
+            TicketTarget::Packet(id) => {
+                Self::check_capacity(
+                    conn,
+                    "SELECT numarlocuri FROM PACHETE WHERE ID = $1 FOR UPDATE",
+                    "SELECT COUNT(*) FROM BILETE WHERE pachetid = $1 AND cod IS DISTINCT FROM $2",
+                    id,
+                    seats,
+                    moved_ticket,
+                )
+                .await?;
+
+                let event_ids: Vec<i32> = sqlx::query_scalar(
+                    "SELECT evenimentid FROM JOIN_PE WHERE pachetid = $1 ORDER BY evenimentid",
+                )
+                .bind(id)
+                .fetch_all(&mut *conn)
+                .await
+                .map_err(map_sqlx_ticket_error)?;
+
+                for event_id in event_ids {
+                    Self::reserve_event_seats(conn, event_id, seats, moved_ticket).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // tickets of the packets including the event hold one of its seats each
+    async fn reserve_event_seats(
+        conn: &mut PgConnection,
         event_id: i32,
-    ) -> Result<Vec<Ticket>, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+        seats: i64,
+        moved_ticket: Option<&str>,
+    ) -> Result<(), TicketRepoError> {
+        Self::check_capacity(
+            conn,
+            "SELECT numarlocuri FROM EVENIMENTE WHERE ID = $1 FOR UPDATE",
             r#"
-            SELECT cod, pachetid, evenimentid
-            FROM BILETE
-            WHERE evenimentid = $1
+            SELECT COUNT(*) FROM BILETE
+            WHERE (evenimentid = $1
+                OR pachetid IN (SELECT pachetid FROM JOIN_PE WHERE evenimentid = $1))
+              AND cod IS DISTINCT FROM $2
             "#,
+            event_id,
+            seats,
+            moved_ticket,
         )
-        .bind(event_id)
-        .fetch_all(&self.pool)
-        .await;
+        .await
+    }
 
-        result.map_err(map_sqlx_ticket_error)
+    async fn check_capacity(
+        conn: &mut PgConnection,
+        capacity_sql: &str,
+        sold_sql: &str,
+        id: i32,
+        seats: i64,
+        moved_ticket: Option<&str>,
+    ) -> Result<(), TicketRepoError> {
+        let capacity: Option<i32> = sqlx::query_scalar(capacity_sql)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(map_sqlx_ticket_error)?
+            .ok_or(TicketRepoError::InvalidReference)?;
+
+        // no seat count means the capacity is unlimited
+        let Some(capacity) = capacity else {
+            return Ok(());
+        };
+
+        let sold: i64 = sqlx::query_scalar(sold_sql)
+            .bind(id)
+            .bind(moved_ticket)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(map_sqlx_ticket_error)?;
+
+        if sold + seats > i64::from(capacity) {
+            Err(TicketRepoError::SoldOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn list_page(
+        &self,
+        target: Option<TicketTarget>,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        let mut count_builder: QueryBuilder<Postgres> =
+            QueryBuilder::new("SELECT COUNT(*) FROM BILETE");
+        push_target_filter(&mut count_builder, target);
+
+        let total: i64 = count_builder
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_sqlx_ticket_error)?;
+
+        let mut query_builder: QueryBuilder<Postgres> =
+            QueryBuilder::new("SELECT cod, pachetid, evenimentid, checked_in_at FROM BILETE");
+        push_target_filter(&mut query_builder, target);
+        push_keyset(
+            &mut query_builder,
+            target.is_some(),
+            params,
+            ticket_sort_column,
+            "cod",
+        );
+        push_order_by(&mut query_builder, params, ticket_sort_column, "cod");
+        push_limit(&mut query_builder, params);
+
+        let tickets = query_builder
+            .build_query_as::<Ticket>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(map_sqlx_ticket_error)?;
+
+        Ok(Page::from_rows(tickets, total, params))
+    }
+}
+
+#[async_trait]
+impl TicketRepository for TicketRepo {
+    #[instrument(name = "TicketRepo::list_tickets_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn list_tickets_for_event(
+        &self,
+        event_id: i32,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "list_tickets_for_event");
+        self.list_page(Some(TicketTarget::Event(event_id)), params)
+            .await
     }
 
-    pub async fn get_ticket_for_event(
+    #[instrument(name = "TicketRepo::get_ticket_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_ticket_for_event(
         &self,
         event_id: i32,
         cod: &str,
     ) -> Result<Ticket, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "get_ticket_for_event");
         let result = sqlx::query_as::<_, Ticket>(
             r#"
-            SELECT cod, pachetid, evenimentid
+            SELECT cod, pachetid, evenimentid, checked_in_at
             FROM BILETE
             WHERE evenimentid = $1 AND cod = $2
             "#,
@@ -50,100 +394,193 @@ impl TicketRepo {
         result.map_err(map_sqlx_ticket_error)
     }
 
-    pub async fn create_ticket(&self, payload: CreateTicket) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+    #[instrument(name = "TicketRepo::create_ticket", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_ticket(
+        &self,
+        payload: CreateTicket,
+        caller: &AuthUser,
+    ) -> Result<Ticket, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "create_ticket");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        if let Some(target) = TicketTarget::from_ids(payload.id_pachet, payload.id_event) {
+            Self::ensure_owner(&mut tx, target, caller).await?;
+            Self::reserve_seats(&mut tx, target, 1, None).await?;
+        }
+
+        let ticket = sqlx::query_as::<_, Ticket>(
             r#"
             INSERT INTO BILETE (cod, pachetid, evenimentid)
             VALUES ($1, $2, $3)
-            RETURNING cod, pachetid, evenimentid
+            RETURNING cod, pachetid, evenimentid, checked_in_at
             "#,
         )
         .bind(payload.cod)
         .bind(payload.id_pachet)
         .bind(payload.id_event)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(ticket)
     }
 
-    pub async fn create_ticket_for_event(
+    #[instrument(name = "TicketRepo::create_ticket_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_ticket_for_event(
         &self,
         event_id: i32,
         payload: CreateTicket,
+        caller: &AuthUser,
     ) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+        let _timer = QueryTimer::start("TicketRepo", "create_ticket_for_event");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Event(event_id), caller).await?;
+
+        Self::reserve_seats(&mut tx, TicketTarget::Event(event_id), 1, None).await?;
+
+        let ticket = sqlx::query_as::<_, Ticket>(
             r#"
         INSERT INTO BILETE (cod, pachetid, evenimentid)
         VALUES ($1, NULL, $2)
-        RETURNING cod, pachetid, evenimentid
+        RETURNING cod, pachetid, evenimentid, checked_in_at
         "#,
         )
         .bind(payload.cod)
         .bind(event_id)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(ticket)
     }
 
-    pub async fn get_ticket(&self, cod: &str) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+    // all codes are inserted in one transaction, either every ticket is issued or none
+    #[instrument(name = "TicketRepo::create_tickets_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_tickets_for_event(
+        &self,
+        event_id: i32,
+        codes: Vec<String>,
+        caller: &AuthUser,
+    ) -> Result<Vec<Ticket>, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "create_tickets_for_event");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Event(event_id), caller).await?;
+
+        Self::reserve_seats(
+            &mut tx,
+            TicketTarget::Event(event_id),
+            codes.len() as i64,
+            None,
+        )
+        .await?;
+
+        let tickets = sqlx::query_as::<_, Ticket>(
             r#"
-            SELECT cod, pachetid, evenimentid
-            FROM BILETE
-            WHERE cod = $1
+            INSERT INTO BILETE (cod, pachetid, evenimentid)
+            SELECT cod, NULL, $2 FROM UNNEST($1::text[]) AS cod
+            RETURNING cod, pachetid, evenimentid, checked_in_at
             "#,
         )
-        .bind(cod)
-        .fetch_one(&self.pool)
-        .await;
+        .bind(codes)
+        .bind(event_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(tickets)
     }
 
-    pub async fn list_tickets(&self) -> Result<Vec<Ticket>, TicketRepoError> {
+    #[instrument(name = "TicketRepo::get_ticket", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_ticket(&self, cod: &str) -> Result<Ticket, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "get_ticket");
         let result = sqlx::query_as::<_, Ticket>(
             r#"
-            SELECT * FROM BILETE
+            SELECT cod, pachetid, evenimentid, checked_in_at
+            FROM BILETE
+            WHERE cod = $1
             "#,
         )
-        .fetch_all(&self.pool)
+        .bind(cod)
+        .fetch_one(&self.pool)
         .await;
 
         result.map_err(map_sqlx_ticket_error)
     }
-    pub async fn update_ticket(
+
+    #[instrument(name = "TicketRepo::list_tickets", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn list_tickets(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "list_tickets");
+        self.list_page(None, params).await
+    }
+
+    #[instrument(name = "TicketRepo::update_ticket", skip_all, fields(db.system = "postgresql", db.operation.name = "UPDATE"))]
+    async fn update_ticket(
         &self,
         cod: &str,
         payload: UpdateTicket,
+        caller: &AuthUser,
     ) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+        let _timer = QueryTimer::start("TicketRepo", "update_ticket");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        // moving a ticket takes both the target it leaves and the one it joins
+        Self::ensure_ticket_owner(&mut tx, cod, caller).await?;
+        if let Some(target) = TicketTarget::from_ids(payload.id_pachet, payload.id_event) {
+            Self::ensure_owner(&mut tx, target, caller).await?;
+            Self::reserve_seats(&mut tx, target, 1, Some(cod)).await?;
+        }
+
+        let ticket = sqlx::query_as::<_, Ticket>(
             r#"
             UPDATE BILETE
             SET
                 pachetid = $1,
                 evenimentid = $2
             WHERE COD = $3
-            RETURNING COD, pachetid, evenimentid
+            RETURNING COD, pachetid, evenimentid, checked_in_at
             "#,
         )
         .bind(payload.id_pachet)
         .bind(payload.id_event)
         .bind(cod)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(ticket)
     }
 
-    pub async fn update_ticket_for_event(
+    #[instrument(name = "TicketRepo::update_ticket_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "UPDATE"))]
+    async fn update_ticket_for_event(
         &self,
         event_id: i32,
         cod: &str,
         payload: UpdateTicket,
+        caller: &AuthUser,
     ) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+        let _timer = QueryTimer::start("TicketRepo", "update_ticket_for_event");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Event(event_id), caller).await?;
+        if let Some(packet_id) = payload.id_pachet {
+            Self::ensure_owner(&mut tx, TicketTarget::Packet(packet_id), caller).await?;
+            Self::reserve_seats(&mut tx, TicketTarget::Packet(packet_id), 1, Some(cod)).await?;
+        }
+
+        let ticket = sqlx::query_as::<_, Ticket>(
             r#"
             UPDATE BILETE
             SET
@@ -151,77 +588,88 @@ impl TicketRepo {
                 evenimentid = NULL
             WHERE
                 cod = $2 and evenimentid = $3
-            RETURNING cod, pachetid, evenimentid
+            RETURNING cod, pachetid, evenimentid, checked_in_at
             "#,
         )
         .bind(payload.id_pachet)
         .bind(cod)
         .bind(event_id)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(ticket)
     }
 
-    pub async fn delete_ticket(&self, cod: &str) -> Result<(), TicketRepoError> {
-        let result = sqlx::query("DELETE FROM BILETE WHERE cod = $1")
+    #[instrument(name = "TicketRepo::delete_ticket", skip_all, fields(db.system = "postgresql", db.operation.name = "DELETE"))]
+    async fn delete_ticket(&self, cod: &str, caller: &AuthUser) -> Result<(), TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "delete_ticket");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_ticket_owner(&mut tx, cod, caller).await?;
+
+        sqlx::query("DELETE FROM BILETE WHERE cod = $1")
             .bind(cod)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(TicketRepoError::InternalError)?;
 
-        if result.rows_affected() == 0 {
-            Err(TicketRepoError::NotFound)
-        } else {
-            Ok(())
-        }
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(())
     }
 
-    pub async fn delete_ticket_for_event(
+    #[instrument(name = "TicketRepo::delete_ticket_for_event", skip_all, fields(db.system = "postgresql", db.operation.name = "DELETE"))]
+    async fn delete_ticket_for_event(
         &self,
         event_id: i32,
         cod: String,
+        caller: &AuthUser,
     ) -> Result<(), TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "delete_ticket_for_event");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Event(event_id), caller).await?;
+
         let result = sqlx::query("DELETE FROM BILETE WHERE evenimentid = $1 AND cod = $2")
             .bind(event_id)
             .bind(cod)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(TicketRepoError::InternalError)?;
 
         if result.rows_affected() == 0 {
-            Err(TicketRepoError::NotFound)
-        } else {
-            Ok(())
+            return Err(TicketRepoError::NotFound);
         }
+
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(())
     }
 
-    pub async fn list_tickets_for_packet(
+    #[instrument(name = "TicketRepo::list_tickets_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn list_tickets_for_packet(
         &self,
         packet_id: i32,
-    ) -> Result<Vec<Ticket>, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
-            r#"
-            SELECT cod, pachetid, evenimentid
-            FROM BILETE
-            WHERE pachetid = $1
-            "#,
-        )
-        .bind(packet_id)
-        .fetch_all(&self.pool)
-        .await;
-
-        result.map_err(map_sqlx_ticket_error)
+        params: &PaginationParams,
+    ) -> Result<Page<Ticket>, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "list_tickets_for_packet");
+        self.list_page(Some(TicketTarget::Packet(packet_id)), params)
+            .await
     }
 
-    pub async fn get_ticket_for_packet(
+    #[instrument(name = "TicketRepo::get_ticket_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "SELECT"))]
+    async fn get_ticket_for_packet(
         &self,
         packet_id: i32,
         cod: &str,
     ) -> Result<Ticket, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "get_ticket_for_packet");
         let result = sqlx::query_as::<_, Ticket>(
             r#"
-            SELECT cod, pachetid, evenimentid
+            SELECT cod, pachetid, evenimentid, checked_in_at
             FROM BILETE
             WHERE pachetid = $1 AND cod = $2
             "#,
@@ -234,33 +682,94 @@ impl TicketRepo {
         result.map_err(map_sqlx_ticket_error)
     }
 
-    pub async fn create_ticket_for_packet(
+    #[instrument(name = "TicketRepo::create_ticket_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_ticket_for_packet(
         &self,
         packet_id: i32,
         payload: CreateTicket,
+        caller: &AuthUser,
     ) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+        let _timer = QueryTimer::start("TicketRepo", "create_ticket_for_packet");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Packet(packet_id), caller).await?;
+
+        Self::reserve_seats(&mut tx, TicketTarget::Packet(packet_id), 1, None).await?;
+
+        let ticket = sqlx::query_as::<_, Ticket>(
             r#"
             INSERT INTO BILETE (cod, pachetid, evenimentid)
             VALUES ($1, $2, NULL)
-            RETURNING cod, pachetid, evenimentid
+            RETURNING cod, pachetid, evenimentid, checked_in_at
             "#,
         )
         .bind(payload.cod)
         .bind(packet_id)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(ticket)
+    }
+
+    #[instrument(name = "TicketRepo::create_tickets_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "INSERT"))]
+    async fn create_tickets_for_packet(
+        &self,
+        packet_id: i32,
+        codes: Vec<String>,
+        caller: &AuthUser,
+    ) -> Result<Vec<Ticket>, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "create_tickets_for_packet");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Packet(packet_id), caller).await?;
+
+        Self::reserve_seats(
+            &mut tx,
+            TicketTarget::Packet(packet_id),
+            codes.len() as i64,
+            None,
+        )
+        .await?;
+
+        let tickets = sqlx::query_as::<_, Ticket>(
+            r#"
+            INSERT INTO BILETE (cod, pachetid, evenimentid)
+            SELECT cod, $2, NULL FROM UNNEST($1::text[]) AS cod
+            RETURNING cod, pachetid, evenimentid, checked_in_at
+            "#,
+        )
+        .bind(codes)
+        .bind(packet_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
+
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(tickets)
     }
 
-    pub async fn update_ticket_for_packet(
+    #[instrument(name = "TicketRepo::update_ticket_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "UPDATE"))]
+    async fn update_ticket_for_packet(
         &self,
         packet_id: i32,
         cod: &str,
         payload: UpdateTicket,
+        caller: &AuthUser,
     ) -> Result<Ticket, TicketRepoError> {
-        let result = sqlx::query_as::<_, Ticket>(
+        let _timer = QueryTimer::start("TicketRepo", "update_ticket_for_packet");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Packet(packet_id), caller).await?;
+        if let Some(event_id) = payload.id_event {
+            Self::ensure_owner(&mut tx, TicketTarget::Event(event_id), caller).await?;
+            Self::reserve_seats(&mut tx, TicketTarget::Event(event_id), 1, Some(cod)).await?;
+        }
+
+        let ticket = sqlx::query_as::<_, Ticket>(
             r#"
             UPDATE BILETE
             SET
@@ -268,34 +777,135 @@ impl TicketRepo {
                 evenimentid = $1
             WHERE
                 cod = $2 AND pachetid = $3
-            RETURNING cod, pachetid, evenimentid
+            RETURNING cod, pachetid, evenimentid, checked_in_at
             "#,
         )
         .bind(payload.id_event)
         .bind(cod)
         .bind(packet_id)
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
 
-        result.map_err(map_sqlx_ticket_error)
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(ticket)
     }
 
-    pub async fn delete_ticket_for_packet(
+    #[instrument(name = "TicketRepo::delete_ticket_for_packet", skip_all, fields(db.system = "postgresql", db.operation.name = "DELETE"))]
+    async fn delete_ticket_for_packet(
         &self,
         packet_id: i32,
         cod: &str,
+        caller: &AuthUser,
     ) -> Result<(), TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "delete_ticket_for_packet");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        Self::ensure_owner(&mut tx, TicketTarget::Packet(packet_id), caller).await?;
+
         let result = sqlx::query("DELETE FROM BILETE WHERE pachetid = $1 AND cod = $2")
             .bind(packet_id)
             .bind(cod)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(TicketRepoError::InternalError)?;
 
         if result.rows_affected() == 0 {
-            Err(TicketRepoError::NotFound)
-        } else {
-            Ok(())
+            return Err(TicketRepoError::NotFound);
         }
+
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(())
+    }
+
+    // the ticket row is locked first, so two scanners reading the same code at
+    // the same time can't both get in: the second one sees AlreadyCheckedIn
+    #[instrument(name = "TicketRepo::check_in", skip_all, fields(db.system = "postgresql", db.operation.name = "UPDATE"))]
+    async fn check_in(
+        &self,
+        cod: &str,
+        event_id: Option<i32>,
+        caller: &AuthUser,
+    ) -> Result<CheckIn, TicketRepoError> {
+        let _timer = QueryTimer::start("TicketRepo", "check_in");
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_ticket_error)?;
+
+        let ticket = sqlx::query_as::<_, Ticket>(
+            r#"
+            SELECT cod, pachetid, evenimentid, checked_in_at
+            FROM BILETE
+            WHERE cod = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(cod)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_ticket_error)?;
+
+        let check_in = match (ticket.id_event, ticket.id_pachet) {
+            (Some(ticket_event), _) => {
+                if event_id.is_some_and(|id| id != ticket_event) {
+                    return Err(TicketRepoError::NotValidForEvent);
+                }
+                Self::ensure_owner(&mut tx, TicketTarget::Event(ticket_event), caller).await?;
+                if ticket.checked_in_at.is_some() {
+                    return Err(TicketRepoError::AlreadyCheckedIn);
+                }
+
+                sqlx::query_as::<_, CheckIn>(
+                    r#"
+                    UPDATE BILETE
+                    SET checked_in_at = NOW()
+                    WHERE cod = $1
+                    RETURNING cod, evenimentid, checked_in_at
+                    "#,
+                )
+                .bind(cod)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(map_sqlx_ticket_error)?
+            }
+            (None, Some(packet_id)) => {
+                let event_id = event_id.ok_or(TicketRepoError::EventRequired)?;
+                // a packet ticket is checked in by the owner of the event it enters
+                Self::ensure_owner(&mut tx, TicketTarget::Event(event_id), caller).await?;
+
+                let in_packet: bool = sqlx::query_scalar(
+                    "SELECT EXISTS (SELECT 1 FROM JOIN_PE WHERE pachetid = $1 AND evenimentid = $2)",
+                )
+                .bind(packet_id)
+                .bind(event_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(map_sqlx_ticket_error)?;
+
+                if !in_packet {
+                    return Err(TicketRepoError::NotValidForEvent);
+                }
+
+                sqlx::query_as::<_, CheckIn>(
+                    r#"
+                    INSERT INTO INTRARI (cod, evenimentid)
+                    VALUES ($1, $2)
+                    ON CONFLICT (cod, evenimentid) DO NOTHING
+                    RETURNING cod, evenimentid, checked_in_at
+                    "#,
+                )
+                .bind(cod)
+                .bind(event_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(map_sqlx_ticket_error)?
+                .ok_or(TicketRepoError::AlreadyCheckedIn)?
+            }
+            (None, None) => return Err(TicketRepoError::ConstraintViolation),
+        };
+
+        tx.commit().await.map_err(map_sqlx_ticket_error)?;
+
+        Ok(check_in)
     }
 }
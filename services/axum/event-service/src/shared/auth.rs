@@ -0,0 +1,155 @@
+use crate::AppState;
+use crate::shared::error::ApiError;
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::http::{Method, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Admin,
+    OwnerEvent,
+    Client,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+// mirrors the claims issued by auth-service/src/shared/jwt.rs
+#[derive(Debug, Clone, Deserialize)]
+pub struct Claims {
+    pub sub: i32,
+    pub rol: Role,
+    pub typ: TokenKind,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Mutate,
+}
+
+impl From<&Method> for Access {
+    fn from(method: &Method) -> Self {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => Access::Read,
+            _ => Access::Mutate,
+        }
+    }
+}
+
+// clients may only read, owner-event users may mutate, admins may do everything
+const POLICY: &[(Access, &[Role])] = &[
+    (Access::Read, &[Role::Admin, Role::OwnerEvent, Role::Client]),
+    (Access::Mutate, &[Role::Admin, Role::OwnerEvent]),
+];
+
+fn is_allowed(role: Role, access: Access) -> bool {
+    POLICY
+        .iter()
+        .any(|(action, roles)| *action == access && roles.contains(&role))
+}
+
+/// The authenticated caller, inserted into the request extensions by [`require_auth`].
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub id: i32,
+    pub role: Role,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .copied()
+            .ok_or_else(|| ApiError::Unauthorized("missing-authentication"))
+    }
+}
+
+pub struct JwtVerifier {
+    decoding: DecodingKey,
+    validation: Validation,
+}
+
+impl JwtVerifier {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            decoding: DecodingKey::from_secret(secret),
+            validation: Validation::new(Algorithm::HS256),
+        }
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, ApiError> {
+        let claims = decode::<Claims>(token, &self.decoding, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => ApiError::Unauthorized("token-expired"),
+                _ => ApiError::Unauthorized("token-invalid"),
+            })?;
+
+        if claims.typ != TokenKind::Access {
+            return Err(ApiError::Unauthorized("access-token-required"));
+        }
+
+        Ok(claims)
+    }
+}
+
+fn bearer_token(req: &Request) -> Result<&str, ApiError> {
+    let header = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .ok_or_else(|| ApiError::Unauthorized("missing-authorization-header"))?;
+
+    header
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ApiError::Unauthorized("bearer-token-expected"))
+}
+
+pub async fn require_auth(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let claims = state.jwt.verify(bearer_token(&req)?)?;
+
+    if !is_allowed(claims.rol, Access::from(req.method())) {
+        return Err(ApiError::Forbidden("role-not-allowed"));
+    }
+
+    req.extensions_mut().insert(AuthUser {
+        id: claims.sub,
+        role: claims.rol,
+    });
+
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,315 @@
+use crate::AppState;
+use axum::extract::{ConnectInfo, FromRequestParts, OriginalUri};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, header};
+use ipnet::IpNet;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+// the base every HATEOAS link of the current request starts with, e.g.
+// `https://tickets.example.com/api/event-manager`
+#[derive(Debug, Clone)]
+pub struct BaseUrl(pub String);
+
+impl FromRequestParts<Arc<AppState>> for BaseUrl {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(BaseUrl(state.base_url.resolve(parts)))
+    }
+}
+
+// either always the configured base url, or one rebuilt from each request.
+// forwarded headers are only believed when the peer is a trusted proxy,
+// anyone else could point our links wherever they like
+#[derive(Debug, Clone)]
+pub struct BaseUrlResolver {
+    fixed: String,
+    from_request: bool,
+    trusted_proxies: Vec<IpNet>,
+}
+
+// what the client used to reach us, before our own mount path is added
+struct Origin {
+    proto: String,
+    host: String,
+    prefix: String,
+}
+
+impl BaseUrlResolver {
+    pub fn fixed(base_url: impl Into<String>) -> Self {
+        Self {
+            fixed: base_url.into(),
+            from_request: false,
+            trusted_proxies: Vec::new(),
+        }
+    }
+
+    // proxies are single addresses (`10.0.0.7`) or networks (`10.0.0.0/8`)
+    pub fn from_request(
+        fallback: impl Into<String>,
+        trusted_proxies: &[String],
+    ) -> Result<Self, String> {
+        let trusted_proxies = trusted_proxies
+            .iter()
+            .map(|proxy| parse_proxy(proxy))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            fixed: fallback.into(),
+            from_request: true,
+            trusted_proxies,
+        })
+    }
+
+    pub fn resolve(&self, parts: &Parts) -> String {
+        if !self.from_request {
+            return self.fixed.clone();
+        }
+
+        let origin = if self.is_trusted(parts) {
+            forwarded(&parts.headers).or_else(|| x_forwarded(&parts.headers))
+        } else {
+            host(&parts.headers)
+        };
+
+        match origin {
+            Some(origin) => format!(
+                "{}://{}{}{}",
+                origin.proto,
+                origin.host,
+                origin.prefix,
+                mount_path(parts)
+            ),
+            None => self.fixed.clone(),
+        }
+    }
+
+    fn is_trusted(&self, parts: &Parts) -> bool {
+        // without connect info (tests, some embedders) nobody is trusted
+        let Some(ConnectInfo(peer)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() else {
+            return false;
+        };
+        let ip = peer.ip().to_canonical();
+
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+}
+
+fn parse_proxy(proxy: &str) -> Result<IpNet, String> {
+    proxy
+        .parse::<IpNet>()
+        .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("`{}` is not an IP address or CIDR network", proxy))
+}
+
+// the part of the path the router is nested under, `/api/event-manager`
+fn mount_path(parts: &Parts) -> String {
+    let Some(OriginalUri(original)) = parts.extensions.get::<OriginalUri>() else {
+        return String::new();
+    };
+
+    original
+        .path()
+        .strip_suffix(parts.uri.path())
+        .unwrap_or_default()
+        .to_string()
+}
+
+// RFC 7239. every proxy appends its element, so only the last one was written
+// by the trusted proxy in front of us, the ones before it came from the client
+fn forwarded(headers: &HeaderMap) -> Option<Origin> {
+    let last = last_value(headers, header::FORWARDED.as_str())?;
+
+    let mut proto = None;
+    let mut host = None;
+    for pair in last.split(';') {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_ascii_lowercase().as_str() {
+            "proto" => proto = Some(value),
+            "host" => host = Some(value),
+            _ => {}
+        }
+    }
+
+    Some(Origin {
+        proto: valid_proto(proto.unwrap_or("http"))?,
+        host: valid_host(host?)?,
+        prefix: forwarded_prefix(headers),
+    })
+}
+
+// each header falls back on its own, a proxy may only set the proto
+fn x_forwarded(headers: &HeaderMap) -> Option<Origin> {
+    let host = last_value(headers, "x-forwarded-host")
+        .or_else(|| headers.get(header::HOST)?.to_str().ok())?;
+    let proto = last_value(headers, "x-forwarded-proto").unwrap_or("http");
+
+    Some(Origin {
+        proto: valid_proto(proto)?,
+        host: valid_host(host)?,
+        prefix: forwarded_prefix(headers),
+    })
+}
+
+fn host(headers: &HeaderMap) -> Option<Origin> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+
+    Some(Origin {
+        proto: "http".to_string(),
+        host: valid_host(host)?,
+        prefix: String::new(),
+    })
+}
+
+// proxies chain their values, `X-Forwarded-Host: outer, inner`, in one line
+// or in repeated ones. the last value is the trusted proxy's own
+fn last_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(name)
+        .iter()
+        .next_back()?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn forwarded_prefix(headers: &HeaderMap) -> String {
+    last_value(headers, "x-forwarded-prefix")
+        .map(|prefix| prefix.trim_end_matches('/'))
+        .filter(|prefix| {
+            prefix.starts_with('/')
+                && prefix
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "/-._~".contains(c))
+        })
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn valid_proto(proto: &str) -> Option<String> {
+    let proto = proto.to_ascii_lowercase();
+    matches!(proto.as_str(), "http" | "https").then_some(proto)
+}
+
+// a hostname or address with an optional port, nothing that could break out
+// of the authority part of a link
+fn valid_host(host: &str) -> Option<String> {
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-.:[]".contains(c));
+
+    valid.then(|| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    const FALLBACK: &str = "http://localhost:8001/api/event-manager";
+
+    fn resolver() -> BaseUrlResolver {
+        BaseUrlResolver::from_request(FALLBACK, &["10.0.0.0/8".to_string()]).unwrap()
+    }
+
+    fn parts(peer: &str, headers: &[(&str, &str)]) -> Parts {
+        let mut request = Request::builder().uri("/events");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        parts
+    }
+
+    #[test]
+    fn the_last_forwarded_element_is_used() {
+        let parts = parts(
+            "10.0.0.7:40000",
+            &[(
+                "forwarded",
+                "host=evil.example;proto=http, for=1.2.3.4;host=tickets.example.com;proto=https",
+            )],
+        );
+
+        assert_eq!(resolver().resolve(&parts), "https://tickets.example.com");
+    }
+
+    #[test]
+    fn the_last_x_forwarded_values_are_used() {
+        let parts = parts(
+            "10.0.0.7:40000",
+            &[
+                ("host", "event-service:8080"),
+                ("x-forwarded-host", "evil.example, tickets.example.com"),
+                ("x-forwarded-proto", "http"),
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-prefix", "/evil, /api/"),
+            ],
+        );
+
+        assert_eq!(
+            resolver().resolve(&parts),
+            "https://tickets.example.com/api"
+        );
+    }
+
+    #[test]
+    fn a_missing_x_forwarded_host_falls_back_on_the_host() {
+        let parts = parts(
+            "10.0.0.7:40000",
+            &[
+                ("host", "event-service:8080"),
+                ("x-forwarded-proto", "https"),
+            ],
+        );
+
+        assert_eq!(resolver().resolve(&parts), "https://event-service:8080");
+    }
+
+    #[test]
+    fn untrusted_peers_only_choose_the_host() {
+        let parts = parts(
+            "192.168.1.5:40000",
+            &[
+                ("host", "event-service:8080"),
+                ("forwarded", "host=evil.example;proto=https"),
+                ("x-forwarded-host", "evil.example"),
+            ],
+        );
+
+        assert_eq!(resolver().resolve(&parts), "http://event-service:8080");
+    }
+
+    #[test]
+    fn invalid_values_fall_back_on_the_configured_url() {
+        let parts = parts(
+            "10.0.0.7:40000",
+            &[("forwarded", "host=\"evil.example/x?\";proto=https")],
+        );
+
+        assert_eq!(resolver().resolve(&parts), FALLBACK);
+    }
+
+    #[test]
+    fn proxies_are_addresses_or_networks() {
+        assert!(parse_proxy("10.0.0.7").is_ok());
+        assert!(parse_proxy("10.0.0.0/8").is_ok());
+        assert!(parse_proxy("::1").is_ok());
+        assert!(parse_proxy("proxy.local").is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use sqlx::PgPool;
+use sqlx::migrate::{MigrateError, Migrator};
+
+// embedded at compile time, `cargo build` picks up new files in `migrations/`
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// demo data for local development, it truncates every table first
+const SEED: &str = include_str!("../../seeds/populate.sql");
+
+// applies the pending migrations. sqlx holds an advisory lock while it runs,
+// so replicas starting together won't race each other
+pub async fn migrate(pool: &PgPool) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await
+}
+
+pub async fn seed(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::raw_sql(SEED).execute(pool).await?;
+    Ok(())
+}
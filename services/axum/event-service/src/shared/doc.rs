@@ -1,6 +1,17 @@
-use crate::handlers::{event::*, event_packets::*, join_pe::*, ticket::*};
-use crate::models::{event::Event, event_packets::EventPackets, ticket::Ticket};
-use utoipa::OpenApi;
+use crate::handlers::{event::*, event_packets::*, health::*, join_pe::*, ticket::*};
+use crate::models::{
+    check_in::{CheckIn, CheckInStats},
+    event::Event,
+    event_packets::EventPackets,
+    health::{HealthCheck, HealthReport, HealthStatus},
+    ticket::{Ticket, VerifyTicketToken},
+};
+use crate::shared::error::{FieldError, Problem};
+use crate::shared::hypermedia::{Property, Template};
+use crate::shared::links::{CollectionResponse, Link, Links, PageInfo};
+use crate::shared::ticket_token::TicketTokenClaims;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 
 #[derive(OpenApi)]
 #[openapi(
@@ -11,6 +22,7 @@ use utoipa::OpenApi;
         update_event,
         delete_event,
         list_events,
+        get_check_in_stats,
 
         // EventPackets
         create_event_packet,
@@ -26,31 +38,81 @@ use utoipa::OpenApi;
         delete_ticket,
         list_tickets,
         create_ticket_for_event,
+        create_tickets_for_event,
         get_ticket_for_event,
         update_ticket_for_event,
         delete_ticket_for_event,
         list_tickets_for_packet,
         create_ticket_for_packet,
+        create_tickets_for_packet,
         get_ticket_for_packet,
         update_ticket_for_packet,
         delete_ticket_for_packet,
+        check_in_ticket,
+        get_ticket_qr,
+        verify_ticket_token,
 
         // Join PE
         add_event_to_packet,
         add_packet_to_event,
         list_events_for_packet,
-        list_packets_for_event
+        list_packets_for_event,
+
+        // Health
+        live,
+        ready
     ),
-    components(schemas(Event, EventPackets, Ticket)),
+    components(schemas(
+        Event,
+        EventPackets,
+        Ticket,
+        CheckIn,
+        CheckInStats,
+        VerifyTicketToken,
+        TicketTokenClaims,
+        Link,
+        Links,
+        PageInfo,
+        Template,
+        Property,
+        Problem,
+        FieldError,
+        HealthReport,
+        HealthCheck,
+        HealthStatus,
+        CollectionResponse<Event>,
+        CollectionResponse<EventPackets>,
+        CollectionResponse<Ticket>
+    )),
+    modifiers(&BearerAuth),
+    security(("bearer_auth" = [])),
     tags(
         (name = "events", description = "Event management endpoints"),
         (name = "event_packets", description = "Event packet management"),
         (name = "tickets", description = "Ticket management"),
-        (name = "joins", description = "Link events with packets")
+        (name = "joins", description = "Link events with packets"),
+        (name = "health", description = "Liveness and readiness probes")
     )
 )]
 pub struct ApiDoc;
 
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
 pub fn openapi() -> utoipa::openapi::OpenApi {
     ApiDoc::openapi()
 }
@@ -1,41 +1,132 @@
+use crate::shared::i18n::{self, Locale};
+use crate::shared::request_id::RequestId;
+use crate::shared::ticket_token::TicketTokenError;
 use axum::Json;
-use axum::extract::rejection::JsonRejection;
-use axum::http::StatusCode;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{OriginalUri, Request};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 use serde::Serialize;
 use sqlx::Error;
+use tracing::error;
+use utoipa::ToSchema;
 use validator::{ValidationErrors, ValidationErrorsKind};
 
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+// `type` is built from the code, e.g. `urn:event-service:problem:event-not-found`
+const PROBLEM_TYPE_PREFIX: &str = "urn:event-service:problem:";
+
+tokio::task_local! {
+    static REQUEST_PATH: String;
+}
+
 #[derive(Debug)]
 pub enum ApiError {
     Validation(ValidationErrors),
     Event(EventRepoError),
+    // an ID path parameter below zero
+    NegativeId,
+    // a ticket code whose check character doesn't match
+    MistypedTicketCode,
     BadRequest(String),
+    // the reason is a message code from `i18n`
+    Unauthorized(&'static str),
+    Forbidden(&'static str),
     Json(JsonRejection),
+    Query(QueryRejection),
+    Path(PathRejection),
     Packet(EventPacketRepoError),
     Ticket(TicketRepoError),
     Join(JoinPeRepoError),
+    TicketToken(TicketTokenError),
+    Internal(String),
+}
+
+// an RFC 9457 problem document. `code` is the stable identifier clients
+// branch on, `title` and `detail` are meant for humans and may change
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub code: String,
+    // the `X-Request-Id` of the failed request, quote it when reporting a bug
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+// one failed validation rule, `field` is missing for rules spanning the whole body
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FieldError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub code: String,
+    pub message: String,
 }
 
-#[derive(Serialize)]
-struct ApiErrorResponse {
-    error: String,
-    details: Vec<String>,
+// the catalog covers every code used here, the code itself is only
+// shown if one was forgotten
+fn text(code: &str) -> String {
+    i18n::message(code).unwrap_or(code).to_string()
+}
+
+impl Problem {
+    // `title` is a key under `title.` in the catalog
+    fn new(status: StatusCode, code: &str, title: &str, detail: String) -> Self {
+        Self {
+            problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, code),
+            title: text(&format!("title.{}", title)),
+            status: status.as_u16(),
+            detail,
+            instance: REQUEST_PATH.try_with(Clone::clone).ok(),
+            code: code.to_string(),
+            request_id: RequestId::current(),
+            errors: Vec::new(),
+        }
+    }
+
+    // a problem whose detail is the catalog message for its code
+    fn catalogued(status: StatusCode, code: &str, title: &str) -> Self {
+        Problem::new(status, code, title, text(code))
+    }
+
+    // the cause stays in the logs, clients only learn that something broke
+    fn internal(cause: &dyn std::fmt::Display) -> Self {
+        error!(error = %cause, "internal error");
+        Problem::catalogued(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal-error",
+            "internal-error",
+        )
+    }
 }
 
 #[derive(Debug)]
 pub enum EventRepoError {
     NotFound,
+    Forbidden,
     InvalidReference,
     DuplicateEntry,
+    InvalidSchedule,
+    HasTickets,
     InternalError(Error),
 }
 
 #[derive(Debug)]
 pub enum EventPacketRepoError {
     NotFound,
+    Forbidden,
     DuplicateName,
     InvalidEventId,
+    HasTickets,
     InternalError(Error),
 }
 
@@ -45,6 +136,11 @@ pub enum TicketRepoError {
     DuplicateEntry,
     InvalidReference,
     ConstraintViolation,
+    SoldOut,
+    AlreadyCheckedIn,
+    EventRequired,
+    NotValidForEvent,
+    Forbidden,
     InternalError(Error),
 }
 
@@ -52,12 +148,72 @@ pub enum TicketRepoError {
 pub enum JoinPeRepoError {
     DuplicateEntry,
     InvalidReference,
+    SoldOut,
+    Forbidden,
     InternalError(Error),
 }
 
+// the codes below are part of the API, clients match on them. rename a
+// variant freely, never its code
+impl EventRepoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            EventRepoError::NotFound => "event-not-found",
+            EventRepoError::Forbidden => "event-forbidden",
+            EventRepoError::InvalidReference => "event-invalid-reference",
+            EventRepoError::DuplicateEntry => "event-duplicate",
+            EventRepoError::InvalidSchedule => "event-invalid-schedule",
+            EventRepoError::HasTickets => "event-has-tickets",
+            EventRepoError::InternalError(_) => "internal-error",
+        }
+    }
+}
+
+impl EventPacketRepoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            EventPacketRepoError::NotFound => "packet-not-found",
+            EventPacketRepoError::Forbidden => "packet-forbidden",
+            EventPacketRepoError::DuplicateName => "packet-duplicate",
+            EventPacketRepoError::InvalidEventId => "packet-invalid-reference",
+            EventPacketRepoError::HasTickets => "packet-has-tickets",
+            EventPacketRepoError::InternalError(_) => "internal-error",
+        }
+    }
+}
+
+impl TicketRepoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TicketRepoError::NotFound => "ticket-not-found",
+            TicketRepoError::DuplicateEntry => "ticket-duplicate",
+            TicketRepoError::InvalidReference => "ticket-invalid-reference",
+            TicketRepoError::ConstraintViolation => "ticket-target-conflict",
+            TicketRepoError::SoldOut => "ticket-sold-out",
+            TicketRepoError::AlreadyCheckedIn => "ticket-already-checked-in",
+            TicketRepoError::EventRequired => "ticket-event-required",
+            TicketRepoError::NotValidForEvent => "ticket-not-valid-for-event",
+            TicketRepoError::Forbidden => "ticket-forbidden",
+            TicketRepoError::InternalError(_) => "internal-error",
+        }
+    }
+}
+
+impl JoinPeRepoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            JoinPeRepoError::DuplicateEntry => "join-duplicate",
+            JoinPeRepoError::InvalidReference => "join-invalid-reference",
+            JoinPeRepoError::SoldOut => "join-sold-out",
+            JoinPeRepoError::Forbidden => "join-forbidden",
+            JoinPeRepoError::InternalError(_) => "internal-error",
+        }
+    }
+}
+
 impl From<String> for ApiError {
     fn from(value: String) -> Self {
-        ApiError::BadRequest(value.into())
+        ApiError::BadRequest(value)
     }
 }
 
@@ -67,6 +223,18 @@ impl From<JsonRejection> for ApiError {
     }
 }
 
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::Query(rejection)
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        ApiError::Path(rejection)
+    }
+}
+
 impl From<ValidationErrors> for ApiError {
     fn from(errors: ValidationErrors) -> Self {
         ApiError::Validation(errors)
@@ -91,241 +259,335 @@ impl From<TicketRepoError> for ApiError {
     }
 }
 
+impl From<TicketTokenError> for ApiError {
+    fn from(error: TicketTokenError) -> Self {
+        ApiError::TicketToken(error)
+    }
+}
+
 impl From<JoinPeRepoError> for ApiError {
     fn from(error: JoinPeRepoError) -> Self {
         ApiError::Join(error)
     }
 }
 
+// keeps the path of the current request around so problems can fill in
+// `instance` without every handler passing it down
+pub async fn problem_instance(req: Request, next: Next) -> Response {
+    let path = req
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+
+    REQUEST_PATH.scope(path, next.run(req)).await
+}
+
 // this is mainly for EventPacketQuery (I have a nested struct inside it)
-// pretty much I try to get the errors from PaginationParams
-// so I have a cleaner response when validating errors
-fn flatten_validation_errors(errors: &ValidationErrors) -> Vec<String> {
-    let mut messages = Vec::new();
+// pretty much I try to get the errors from PaginationParams too. the nested
+// structs are all `#[serde(flatten)]`ed, so their fields are reported without
+// a parent. schema level errors come back under `__all__` and have no field
+fn flatten_validation_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut field_errors = Vec::new();
 
-    for (_, kind) in errors.errors() {
+    for (field, kind) in errors.errors() {
         match kind {
             ValidationErrorsKind::Struct(nested_errors) => {
-                messages.extend(flatten_validation_errors(nested_errors));
+                field_errors.extend(flatten_validation_errors(nested_errors));
             }
             ValidationErrorsKind::List(list_errors) => {
-                for (_, nested_errors) in list_errors {
-                    messages.extend(flatten_validation_errors(nested_errors));
+                for nested_errors in list_errors.values() {
+                    field_errors.extend(flatten_validation_errors(nested_errors));
                 }
             }
-            ValidationErrorsKind::Field(field_errors) => {
-                for error in field_errors {
-                    if let Some(message) = &error.message {
-                        messages.push(message.to_string());
-                    }
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    let message = i18n::message(&error.code)
+                        .map(str::to_string)
+                        .or_else(|| error.message.as_ref().map(|m| m.to_string()))
+                        .unwrap_or_else(|| text("invalid_value"));
+
+                    field_errors.push(FieldError {
+                        field: (field != "__all__").then(|| field.to_string()),
+                        code: error.code.to_string(),
+                        message,
+                    });
                 }
             }
         }
     }
 
-    messages
+    field_errors
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, body) = match self {
-            ApiError::Validation(errors) => {
-                let all_messages = flatten_validation_errors(&errors);
-                (
+// the inner serde error, without axum's english prefix
+fn serde_message(rejection: &dyn std::error::Error) -> String {
+    rejection
+        .source()
+        .map(|source| source.to_string())
+        .unwrap_or_else(|| rejection.to_string())
+}
+
+impl ApiError {
+    fn problem(self) -> Problem {
+        match self {
+            ApiError::Validation(errors) => Problem {
+                errors: flatten_validation_errors(&errors),
+                ..Problem::catalogued(
                     StatusCode::UNPROCESSABLE_ENTITY,
-                    ApiErrorResponse {
-                        error: "Validation Failed".to_string(),
-                        details: all_messages,
-                    },
+                    "validation-failed",
+                    "validation-failed",
                 )
+            },
+
+            ApiError::NegativeId => {
+                Problem::catalogued(StatusCode::BAD_REQUEST, "negative-id", "bad-request")
             }
 
-            ApiError::BadRequest(message) => (
+            ApiError::MistypedTicketCode => Problem::catalogued(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "ticket-code-mistyped",
+                "mistyped-ticket-code",
+            ),
+
+            ApiError::BadRequest(message) => Problem::new(
                 StatusCode::BAD_REQUEST,
-                ApiErrorResponse {
-                    error: "Bad Request".to_string(),
-                    details: vec![message],
-                },
+                "bad-request",
+                "bad-request",
+                message,
+            ),
+
+            ApiError::Unauthorized(reason) => Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "unauthorized",
+                text(reason),
             ),
 
-            ApiError::Json(rejection) => {
-                let (status, title, detail) = match rejection {
-                    JsonRejection::JsonDataError(err) => {
-                        let msg = err.to_string();
-                        let final_msg = if msg.contains("unknown field") {
-                            msg.split('`')
-                                .nth(1)
-                                .map(|field_name| format!("Unknown field `{}`", field_name))
-                                .unwrap_or("Invalid data format.".to_string())
-                        } else {
-                            msg
-                        };
-                        (
+            ApiError::Forbidden(reason) => Problem::new(
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                "forbidden",
+                text(reason),
+            ),
+
+            // serde's own message is kept after the localized one, it names
+            // the field and position that broke
+            ApiError::Json(rejection) => match rejection {
+                JsonRejection::JsonDataError(err) => {
+                    let msg = serde_message(&err);
+                    match msg.contains("unknown field").then(|| msg.split('`').nth(1)) {
+                        Some(Some(field_name)) => {
+                            let message = text("unknown-field").replace("{field}", field_name);
+                            Problem {
+                                errors: vec![FieldError {
+                                    field: Some(field_name.to_string()),
+                                    code: "unknown_field".to_string(),
+                                    message: message.clone(),
+                                }],
+                                ..Problem::new(
+                                    StatusCode::UNPROCESSABLE_ENTITY,
+                                    "invalid-json-data",
+                                    "invalid-json-data",
+                                    message,
+                                )
+                            }
+                        }
+                        _ => Problem::new(
                             StatusCode::UNPROCESSABLE_ENTITY,
-                            "Invalid JSON Data".to_string(),
-                            vec![final_msg],
-                        )
+                            "invalid-json-data",
+                            "invalid-json-data",
+                            format!("{} {}", text("invalid-json-data"), msg),
+                        ),
                     }
-                    JsonRejection::JsonSyntaxError(err) => (
-                        StatusCode::BAD_REQUEST,
-                        "Invalid JSON Syntax".to_string(),
-                        vec![err.to_string()],
-                    ),
-                    JsonRejection::MissingJsonContentType(_) => (
-                        StatusCode::UNSUPPORTED_MEDIA_TYPE,
-                        "Missing Content-Type".to_string(),
-                        vec!["Expected 'application/json'.".to_string()],
-                    ),
-                    _ => (
-                        StatusCode::BAD_REQUEST,
-                        "Bad JSON Request".to_string(),
-                        vec![rejection.to_string()],
-                    ),
-                };
-                (status, ApiErrorResponse { error: title, details: detail })
-            }
-
-            ApiError::Event(e) => match e {
-                EventRepoError::NotFound => (
-                    StatusCode::NOT_FOUND,
-                    ApiErrorResponse {
-                        error: "Resource Not Found".to_string(),
-                        details: vec!["The requested event was not found.".to_string()],
-                    },
-                ),
-                EventRepoError::InvalidReference => (
+                }
+                JsonRejection::JsonSyntaxError(err) => Problem::new(
                     StatusCode::BAD_REQUEST,
-                    ApiErrorResponse {
-                        error: "Invalid Reference".to_string(),
-                        details: vec!["A provided reference, such as an owner ID, is invalid."
-                            .to_string()],
-                    },
+                    "invalid-json-syntax",
+                    "invalid-json-syntax",
+                    format!("{} {}", text("invalid-json-syntax"), serde_message(&err)),
                 ),
-                EventRepoError::DuplicateEntry => (
-                    StatusCode::CONFLICT,
-                    ApiErrorResponse {
-                        error: "Duplicate Entry".to_string(),
-                        details: vec!["An event with this name already exists.".to_string()],
-                    },
+                JsonRejection::MissingJsonContentType(_) => Problem::catalogued(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "missing-content-type",
+                    "missing-content-type",
                 ),
-                EventRepoError::InternalError(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiErrorResponse {
-                        error: "Internal Server Error".to_string(),
-                        details: vec!["An internal server error occurred.".to_string()],
-                    },
+                _ => Problem::catalogued(
+                    StatusCode::BAD_REQUEST,
+                    "bad-json-request",
+                    "bad-json-request",
                 ),
             },
 
-            ApiError::Packet(e) => match e {
-                EventPacketRepoError::NotFound => (
-                    StatusCode::NOT_FOUND,
-                    ApiErrorResponse {
-                        error: "Resource Not Found".to_string(),
-                        details: vec!["The requested event packet was not found.".to_string()],
-                    },
-                ),
-                EventPacketRepoError::DuplicateName => (
-                    StatusCode::CONFLICT,
-                    ApiErrorResponse {
-                        error: "Duplicate Entry".to_string(),
-                        details: vec!["An event packet with this name already exists."
-                            .to_string()],
-                    },
-                ),
-                EventPacketRepoError::InvalidEventId => (
+            // an unknown parameter is reported like an unknown JSON field,
+            // anything else with serde's message, e.g. for `page=abc`
+            ApiError::Query(rejection) => {
+                let msg = serde_message(&rejection);
+                match msg.contains("unknown field").then(|| msg.split('`').nth(1)) {
+                    Some(Some(name)) => {
+                        let message = text("unknown-query-parameter").replace("{field}", name);
+                        Problem {
+                            errors: vec![FieldError {
+                                field: Some(name.to_string()),
+                                code: "unknown_field".to_string(),
+                                message: message.clone(),
+                            }],
+                            ..Problem::new(
+                                StatusCode::BAD_REQUEST,
+                                "invalid-query",
+                                "invalid-query",
+                                message,
+                            )
+                        }
+                    }
+                    _ => Problem::new(
+                        StatusCode::BAD_REQUEST,
+                        "invalid-query",
+                        "invalid-query",
+                        format!("{} {}", text("invalid-query"), msg),
+                    ),
+                }
+            }
+
+            ApiError::Path(rejection) => match rejection {
+                PathRejection::FailedToDeserializePathParams(err) => Problem::new(
                     StatusCode::BAD_REQUEST,
-                    ApiErrorResponse {
-                        error: "Invalid Reference".to_string(),
-                        details: vec!["A provided event ID is invalid.".to_string()],
-                    },
-                ),
-                EventPacketRepoError::InternalError(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiErrorResponse {
-                        error: "Internal Server Error".to_string(),
-                        details: vec!["An internal server error occurred.".to_string()],
-                    },
+                    "invalid-path-parameter",
+                    "invalid-path-parameter",
+                    format!("{} {}", text("invalid-path-parameter"), err.body_text()),
                 ),
+                // the route and the extractor disagree, a bug on our side
+                rejection => Problem::internal(&rejection),
             },
 
-            ApiError::Ticket(e) => match e {
-                TicketRepoError::NotFound => (
-                    StatusCode::NOT_FOUND,
-                    ApiErrorResponse {
-                        error: "Resource Not Found".to_string(),
-                        details: vec!["The requested ticket was not found.".to_string()],
-                    },
-                ),
-                TicketRepoError::DuplicateEntry => (
-                    StatusCode::CONFLICT,
-                    ApiErrorResponse {
-                        error: "Duplicate Entry".to_string(),
-                        details: vec!["A ticket with this code already exists.".to_string()],
-                    },
-                ),
-                TicketRepoError::InvalidReference => (
-                    StatusCode::BAD_REQUEST,
-                    ApiErrorResponse {
-                        error: "Invalid Reference".to_string(),
-                        details: vec!["Invalid packet or event ID provided.".to_string()],
-                    },
-                ),
-                TicketRepoError::ConstraintViolation => (
+            ApiError::Event(e) => {
+                let (status, title) = match e {
+                    EventRepoError::NotFound => (StatusCode::NOT_FOUND, "not-found"),
+                    EventRepoError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+                    EventRepoError::InvalidReference => {
+                        (StatusCode::BAD_REQUEST, "invalid-reference")
+                    }
+                    EventRepoError::DuplicateEntry => (StatusCode::CONFLICT, "duplicate-entry"),
+                    EventRepoError::InvalidSchedule => {
+                        (StatusCode::UNPROCESSABLE_ENTITY, "invalid-schedule")
+                    }
+                    EventRepoError::HasTickets => (StatusCode::CONFLICT, "has-tickets"),
+                    EventRepoError::InternalError(cause) => return Problem::internal(&cause),
+                };
+                Problem::catalogued(status, e.code(), title)
+            }
+
+            ApiError::Packet(e) => {
+                let (status, title) = match e {
+                    EventPacketRepoError::NotFound => (StatusCode::NOT_FOUND, "not-found"),
+                    EventPacketRepoError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+                    EventPacketRepoError::DuplicateName => {
+                        (StatusCode::CONFLICT, "duplicate-entry")
+                    }
+                    EventPacketRepoError::InvalidEventId => {
+                        (StatusCode::BAD_REQUEST, "invalid-reference")
+                    }
+                    EventPacketRepoError::HasTickets => (StatusCode::CONFLICT, "has-tickets"),
+                    EventPacketRepoError::InternalError(cause) => return Problem::internal(&cause),
+                };
+                Problem::catalogued(status, e.code(), title)
+            }
+
+            ApiError::Ticket(e) => {
+                let (status, title) = match e {
+                    TicketRepoError::NotFound => (StatusCode::NOT_FOUND, "not-found"),
+                    TicketRepoError::DuplicateEntry => (StatusCode::CONFLICT, "duplicate-entry"),
+                    TicketRepoError::InvalidReference => {
+                        (StatusCode::BAD_REQUEST, "invalid-reference")
+                    }
+                    TicketRepoError::ConstraintViolation => {
+                        (StatusCode::UNPROCESSABLE_ENTITY, "constraint-violation")
+                    }
+                    TicketRepoError::SoldOut => (StatusCode::CONFLICT, "sold-out"),
+                    TicketRepoError::AlreadyCheckedIn => {
+                        (StatusCode::CONFLICT, "already-checked-in")
+                    }
+                    TicketRepoError::EventRequired => {
+                        (StatusCode::UNPROCESSABLE_ENTITY, "event-required")
+                    }
+                    TicketRepoError::NotValidForEvent => {
+                        (StatusCode::UNPROCESSABLE_ENTITY, "not-valid-for-event")
+                    }
+                    TicketRepoError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+                    TicketRepoError::InternalError(cause) => return Problem::internal(&cause),
+                };
+                Problem::catalogued(status, e.code(), title)
+            }
+
+            ApiError::Join(e) => {
+                let (status, title) = match e {
+                    JoinPeRepoError::DuplicateEntry => (StatusCode::CONFLICT, "duplicate-entry"),
+                    JoinPeRepoError::InvalidReference => {
+                        (StatusCode::BAD_REQUEST, "invalid-reference")
+                    }
+                    JoinPeRepoError::SoldOut => (StatusCode::CONFLICT, "sold-out"),
+                    JoinPeRepoError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+                    JoinPeRepoError::InternalError(cause) => return Problem::internal(&cause),
+                };
+                Problem::catalogued(status, e.code(), title)
+            }
+
+            ApiError::TicketToken(e) => {
+                let code = match e {
+                    TicketTokenError::Malformed => "ticket-token-malformed",
+                    TicketTokenError::BadSignature => "ticket-token-bad-signature",
+                    TicketTokenError::Expired => "ticket-token-expired",
+                };
+                Problem::catalogued(
                     StatusCode::UNPROCESSABLE_ENTITY,
-                    ApiErrorResponse {
-                        error: "Constraint Violation".to_string(),
-                        details: vec![
-                            "A ticket must belong to EITHER a packet OR an event, not both or neither."
-                                .to_string(),
-                        ],
-                    },
-                ),
-                TicketRepoError::InternalError(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiErrorResponse {
-                        error: "Internal Server Error".to_string(),
-                        details: vec!["An internal server error occurred.".to_string()],
-                    },
-                ),
-            },
+                    code,
+                    "invalid-ticket-token",
+                )
+            }
 
-            ApiError::Join(e) => match e {
-                JoinPeRepoError::DuplicateEntry => (
-                    StatusCode::CONFLICT,
-                    ApiErrorResponse {
-                        error: "Duplicate Entry".to_string(),
-                        details: vec!["This event is already in this packet.".to_string()],
-                    },
-                ),
-                JoinPeRepoError::InvalidReference => (
-                    StatusCode::BAD_REQUEST,
-                    ApiErrorResponse {
-                        error: "Invalid Reference".to_string(),
-                        details: vec!["Invalid packet or event ID provided.".to_string()],
-                    },
-                ),
-                JoinPeRepoError::InternalError(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ApiErrorResponse {
-                        error: "Internal Server Error".to_string(),
-                        details: vec!["An internal server error occurred.".to_string()],
-                    },
-                ),
-            },
-        };
+            ApiError::Internal(message) => Problem::internal(&message),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let problem = self.problem();
+        let status =
+            StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let mut response = (status, Json(problem)).into_response();
+
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        headers.insert(
+            header::CONTENT_LANGUAGE,
+            HeaderValue::from_static(Locale::current().as_str()),
+        );
+
+        if status == StatusCode::UNAUTHORIZED {
+            headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
 
-        (status, Json(body)).into_response()
+        response
     }
 }
 pub fn map_sqlx_event_error(err: Error) -> EventRepoError {
-    if let Some(db_err) = err.as_database_error() {
-        if let Some(code) = db_err.code() {
-            match code.as_ref() {
-                "23503" => return EventRepoError::InvalidReference,
-                "23505" => return EventRepoError::DuplicateEntry,
-                _ => {}
+    if let Some(db_err) = err.as_database_error()
+        && let Some(code) = db_err.code()
+    {
+        match code.as_ref() {
+            "23503" => return EventRepoError::InvalidReference,
+            "23505" => return EventRepoError::DuplicateEntry,
+            // other CHECK constraints are not the caller's schedule
+            "23514" if db_err.constraint() == Some("chk_interval_eveniment") => {
+                return EventRepoError::InvalidSchedule;
+            }
+            // deleting would leave its tickets without a target
+            "23514" if db_err.constraint() == Some("chk_bilet_exclusiv") => {
+                return EventRepoError::HasTickets;
             }
+            _ => {}
         }
     }
     match err {
@@ -335,13 +597,16 @@ pub fn map_sqlx_event_error(err: Error) -> EventRepoError {
 }
 
 pub fn map_sqlx_packet_error(err: Error) -> EventPacketRepoError {
-    if let Some(db_err) = err.as_database_error() {
-        if let Some(code) = db_err.code() {
-            match code.as_ref() {
-                "23503" => return EventPacketRepoError::InvalidEventId,
-                "23505" => return EventPacketRepoError::DuplicateName,
-                _ => {}
+    if let Some(db_err) = err.as_database_error()
+        && let Some(code) = db_err.code()
+    {
+        match code.as_ref() {
+            "23503" => return EventPacketRepoError::InvalidEventId,
+            "23505" => return EventPacketRepoError::DuplicateName,
+            "23514" if db_err.constraint() == Some("chk_bilet_exclusiv") => {
+                return EventPacketRepoError::HasTickets;
             }
+            _ => {}
         }
     }
     match err {
@@ -351,14 +616,14 @@ pub fn map_sqlx_packet_error(err: Error) -> EventPacketRepoError {
 }
 
 pub fn map_sqlx_ticket_error(err: Error) -> TicketRepoError {
-    if let Some(db_err) = err.as_database_error() {
-        if let Some(code) = db_err.code() {
-            match code.as_ref() {
-                "23503" => return TicketRepoError::InvalidReference,
-                "23505" => return TicketRepoError::DuplicateEntry,
-                "23514" => return TicketRepoError::ConstraintViolation,
-                _ => {}
-            }
+    if let Some(db_err) = err.as_database_error()
+        && let Some(code) = db_err.code()
+    {
+        match code.as_ref() {
+            "23503" => return TicketRepoError::InvalidReference,
+            "23505" => return TicketRepoError::DuplicateEntry,
+            "23514" => return TicketRepoError::ConstraintViolation,
+            _ => {}
         }
     }
     match err {
@@ -368,13 +633,13 @@ pub fn map_sqlx_ticket_error(err: Error) -> TicketRepoError {
 }
 
 pub fn map_sqlx_join_pe_error(err: Error) -> JoinPeRepoError {
-    if let Some(db_err) = err.as_database_error() {
-        if let Some(code) = db_err.code() {
-            match code.as_ref() {
-                "23503" => return JoinPeRepoError::InvalidReference,
-                "23505" => return JoinPeRepoError::DuplicateEntry,
-                _ => {}
-            }
+    if let Some(db_err) = err.as_database_error()
+        && let Some(code) = db_err.code()
+    {
+        match code.as_ref() {
+            "23503" => return JoinPeRepoError::InvalidReference,
+            "23505" => return JoinPeRepoError::DuplicateEntry,
+            _ => {}
         }
     }
     match err {
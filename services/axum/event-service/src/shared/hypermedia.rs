@@ -0,0 +1,209 @@
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue, Method, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+use utoipa::openapi::schema::{Object, SchemaType, Type};
+use utoipa::openapi::{RefOr, Schema};
+use utoipa::{Number, ToSchema};
+
+pub const JSON: &str = "application/json";
+pub const HAL_JSON: &str = "application/hal+json";
+pub const HAL_FORMS_JSON: &str = "application/prs.hal-forms+json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Json,
+    Hal,
+    HalForms,
+}
+
+tokio::task_local! {
+    static MEDIA_TYPE: MediaType;
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Json => JSON,
+            MediaType::Hal => HAL_JSON,
+            MediaType::HalForms => HAL_FORMS_JSON,
+        }
+    }
+
+    // the representation the current request asked for, HAL outside of a request
+    pub fn current() -> Self {
+        MEDIA_TYPE
+            .try_with(|media_type| *media_type)
+            .unwrap_or(MediaType::Hal)
+    }
+
+    // runs `f` with this media type in scope, as the middleware does per request
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        MEDIA_TYPE.scope(self, f).await
+    }
+
+    // picks the supported type with the highest `q`, earlier entries win ties.
+    // wildcards and anything we don't serve fall back to HAL instead of a 406,
+    // the QR endpoint answers with images no matter what was asked for
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return MediaType::Hal;
+        };
+
+        let mut best: Option<(MediaType, f32)> = None;
+
+        for range in accept.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+                Some(HAL_FORMS_JSON) => MediaType::HalForms,
+                Some(HAL_JSON) => MediaType::Hal,
+                Some(JSON) => MediaType::Json,
+                _ => continue,
+            };
+
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((media_type, quality));
+            }
+        }
+
+        best.map(|(media_type, _)| media_type)
+            .unwrap_or(MediaType::Hal)
+    }
+}
+
+// runs the request with the negotiated media type in scope, so response
+// builders know whether to render `_templates`, and labels successful JSON
+// bodies with it. errors keep their own content type
+pub async fn negotiate_media_type(req: Request, next: Next) -> Response {
+    let media_type = MediaType::negotiate(req.headers());
+
+    let mut response = media_type.scope(next.run(req)).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(JSON.as_bytes()));
+
+    if response.status().is_success() && is_json {
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(media_type.as_str()),
+        );
+    }
+
+    response.headers_mut().insert(
+        header::VARY,
+        HeaderValue::from_static(header::ACCEPT.as_str()),
+    );
+
+    response
+}
+
+// a HAL-FORMS template, see https://rwcbook.github.io/hal-forms/
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Template {
+    pub method: String,
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Property {
+    pub name: String,
+    pub required: bool,
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+}
+
+impl Template {
+    pub fn new(method: Method, target: impl Into<String>) -> Self {
+        Self {
+            method: method.to_string(),
+            target: target.into(),
+            content_type: None,
+            properties: Vec::new(),
+        }
+    }
+
+    // the fields come from the OpenAPI schema of the request body, so a
+    // form always matches what the handler deserializes
+    pub fn with_body<T: ToSchema>(mut self) -> Self {
+        self.content_type = Some(JSON.to_string());
+        self.properties = properties_of::<T>();
+        self
+    }
+}
+
+fn properties_of<T: ToSchema>() -> Vec<Property> {
+    let RefOr::T(Schema::Object(object)) = T::schema() else {
+        return Vec::new();
+    };
+
+    object
+        .properties
+        .iter()
+        .map(|(name, schema)| {
+            let field = match schema {
+                RefOr::T(Schema::Object(field)) => Some(field),
+                _ => None,
+            };
+
+            Property {
+                name: name.clone(),
+                required: object.required.contains(name),
+                r#type: field.map_or("text", input_type).to_string(),
+                min_length: field.and_then(|f| f.min_length),
+                max_length: field.and_then(|f| f.max_length),
+                min: field.and_then(|f| f.minimum.as_ref()).map(to_f64),
+                max: field.and_then(|f| f.maximum.as_ref()).map(to_f64),
+                regex: field.and_then(|f| f.pattern.clone()),
+            }
+        })
+        .collect()
+}
+
+fn to_f64(number: &Number) -> f64 {
+    match number {
+        Number::Int(value) => *value as f64,
+        Number::UInt(value) => *value as f64,
+        Number::Float(value) => *value,
+    }
+}
+
+// HAL-FORMS reuses the HTML input types. timestamps stay `text`, the API wants
+// an RFC 3339 offset which `datetime-local` can't express
+fn input_type(field: &Object) -> &'static str {
+    let types: &[Type] = match &field.schema_type {
+        SchemaType::Type(kind) => std::slice::from_ref(kind),
+        SchemaType::Array(kinds) => kinds,
+        SchemaType::AnyValue => &[],
+    };
+
+    match types.iter().find(|kind| **kind != Type::Null) {
+        Some(Type::Integer | Type::Number) => "number",
+        Some(Type::Boolean) => "checkbox",
+        _ => "text",
+    }
+}
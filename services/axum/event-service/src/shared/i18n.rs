@@ -0,0 +1,553 @@
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Ro,
+}
+
+tokio::task_local! {
+    static LOCALE: Locale;
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ro => "ro",
+        }
+    }
+
+    // the locale the current request asked for, English outside of a request
+    pub fn current() -> Self {
+        LOCALE.try_with(|locale| *locale).unwrap_or_default()
+    }
+
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        LOCALE.scope(self, f).await
+    }
+
+    // only the primary subtag matters, `ro-RO` and `ro-MD` are both `ro`.
+    // the supported language with the highest `q` wins, anything else is English
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Locale::En;
+        };
+
+        let mut best: Option<(Locale, f32)> = None;
+
+        for range in accept.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let language = parts
+                .next()
+                .and_then(|tag| tag.split('-').next())
+                .map(str::to_ascii_lowercase);
+
+            let locale = match language.as_deref() {
+                Some("ro") => Locale::Ro,
+                Some("en") => Locale::En,
+                _ => continue,
+            };
+
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((locale, quality));
+            }
+        }
+
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+}
+
+// runs the request with the negotiated locale in scope, error responses
+// read it when they render their messages
+pub async fn negotiate_locale(req: Request, next: Next) -> Response {
+    let locale = Locale::negotiate(req.headers());
+
+    let mut response = locale.scope(next.run(req)).await;
+
+    response.headers_mut().append(
+        header::VARY,
+        HeaderValue::from_static(header::ACCEPT_LANGUAGE.as_str()),
+    );
+
+    response
+}
+
+// the message for `code` in the current locale, `None` for unknown codes
+pub fn message(code: &str) -> Option<&'static str> {
+    let locale = Locale::current();
+
+    CATALOG
+        .iter()
+        .find(|(key, _, _)| *key == code)
+        .map(|(_, en, ro)| match locale {
+            Locale::En => *en,
+            Locale::Ro => *ro,
+        })
+}
+
+// every code the API can answer with, (code, english, romanian).
+// problem titles are prefixed with `title.`, validation codes use underscores
+// like the ones validator reports for its own rules
+const CATALOG: &[(&str, &str, &str)] = &[
+    // problem titles
+    (
+        "title.validation-failed",
+        "Validation Failed",
+        "Validare eșuată",
+    ),
+    ("title.bad-request", "Bad Request", "Cerere invalidă"),
+    ("title.unauthorized", "Unauthorized", "Neautorizat"),
+    ("title.forbidden", "Forbidden", "Acces interzis"),
+    (
+        "title.invalid-json-data",
+        "Invalid JSON Data",
+        "Date JSON invalide",
+    ),
+    (
+        "title.invalid-json-syntax",
+        "Invalid JSON Syntax",
+        "Sintaxă JSON invalidă",
+    ),
+    (
+        "title.invalid-query",
+        "Invalid Query Parameters",
+        "Parametri de interogare invalizi",
+    ),
+    (
+        "title.invalid-path-parameter",
+        "Invalid Path Parameter",
+        "Parametru de cale invalid",
+    ),
+    (
+        "title.missing-content-type",
+        "Missing Content-Type",
+        "Content-Type lipsă",
+    ),
+    (
+        "title.bad-json-request",
+        "Bad JSON Request",
+        "Cerere JSON invalidă",
+    ),
+    (
+        "title.not-found",
+        "Resource Not Found",
+        "Resursa nu a fost găsită",
+    ),
+    (
+        "title.invalid-reference",
+        "Invalid Reference",
+        "Referință invalidă",
+    ),
+    (
+        "title.duplicate-entry",
+        "Duplicate Entry",
+        "Înregistrare duplicată",
+    ),
+    (
+        "title.invalid-schedule",
+        "Invalid Schedule",
+        "Program invalid",
+    ),
+    (
+        "title.constraint-violation",
+        "Constraint Violation",
+        "Constrângere încălcată",
+    ),
+    ("title.sold-out", "Sold Out", "Locuri epuizate"),
+    ("title.has-tickets", "Tickets Issued", "Bilete emise"),
+    (
+        "title.already-checked-in",
+        "Already Checked In",
+        "Intrare deja înregistrată",
+    ),
+    (
+        "title.event-required",
+        "Event Required",
+        "Eveniment obligatoriu",
+    ),
+    (
+        "title.not-valid-for-event",
+        "Not Valid For Event",
+        "Bilet nevalabil pentru eveniment",
+    ),
+    (
+        "title.mistyped-ticket-code",
+        "Mistyped Ticket Code",
+        "Cod de bilet greșit",
+    ),
+    (
+        "title.invalid-ticket-token",
+        "Invalid Ticket Token",
+        "Token de bilet invalid",
+    ),
+    (
+        "title.internal-error",
+        "Internal Server Error",
+        "Eroare internă a serverului",
+    ),
+    // request errors
+    (
+        "validation-failed",
+        "One or more fields failed validation.",
+        "Unul sau mai multe câmpuri nu au trecut validarea.",
+    ),
+    (
+        "negative-id",
+        "ID cannot be negative",
+        "ID-ul nu poate fi negativ",
+    ),
+    (
+        "invalid-json-data",
+        "The JSON body doesn't match the expected format.",
+        "Corpul JSON nu respectă formatul așteptat.",
+    ),
+    (
+        "unknown-field",
+        "Unknown field `{field}`",
+        "Câmp necunoscut `{field}`",
+    ),
+    (
+        "invalid-json-syntax",
+        "The request body is not valid JSON.",
+        "Corpul cererii nu este un JSON valid.",
+    ),
+    (
+        "invalid-query",
+        "The query parameters don't match the expected format.",
+        "Parametrii de interogare nu respectă formatul așteptat.",
+    ),
+    (
+        "unknown-query-parameter",
+        "Unknown query parameter `{field}`",
+        "Parametru de interogare necunoscut `{field}`",
+    ),
+    (
+        "invalid-path-parameter",
+        "A path parameter doesn't have the expected type.",
+        "Un parametru din cale nu are tipul așteptat.",
+    ),
+    (
+        "missing-content-type",
+        "Expected 'application/json'.",
+        "Se așteaptă 'application/json'.",
+    ),
+    (
+        "bad-json-request",
+        "The JSON body could not be read.",
+        "Corpul JSON nu a putut fi citit.",
+    ),
+    // authentication
+    (
+        "missing-authentication",
+        "Missing authentication.",
+        "Autentificarea lipsește.",
+    ),
+    (
+        "missing-authorization-header",
+        "Missing Authorization header.",
+        "Antetul Authorization lipsește.",
+    ),
+    (
+        "bearer-token-expected",
+        "Expected a Bearer token.",
+        "Se așteaptă un token de tip Bearer.",
+    ),
+    (
+        "token-expired",
+        "The provided token has expired.",
+        "Token-ul furnizat a expirat.",
+    ),
+    (
+        "token-invalid",
+        "The provided token is invalid.",
+        "Token-ul furnizat este invalid.",
+    ),
+    (
+        "access-token-required",
+        "An access token is required.",
+        "Este necesar un token de acces.",
+    ),
+    (
+        "role-not-allowed",
+        "Your role is not allowed to perform this action.",
+        "Rolul tău nu are permisiunea de a efectua această acțiune.",
+    ),
+    // events
+    (
+        "event-not-found",
+        "The requested event was not found.",
+        "Evenimentul cerut nu a fost găsit.",
+    ),
+    (
+        "event-forbidden",
+        "You can only modify events you own.",
+        "Poți modifica doar evenimentele pe care le deții.",
+    ),
+    (
+        "event-invalid-reference",
+        "A provided reference, such as an owner ID, is invalid.",
+        "O referință furnizată, precum ID-ul proprietarului, este invalidă.",
+    ),
+    (
+        "event-duplicate",
+        "An event with this name already exists.",
+        "Există deja un eveniment cu acest nume.",
+    ),
+    (
+        "event-invalid-schedule",
+        "The end of an event must be after its start.",
+        "Sfârșitul unui eveniment trebuie să fie după începutul său.",
+    ),
+    (
+        "event-has-tickets",
+        "The event still has tickets, delete them first.",
+        "Evenimentul are încă bilete, ștergeți-le mai întâi.",
+    ),
+    // packets
+    (
+        "packet-not-found",
+        "The requested event packet was not found.",
+        "Pachetul de evenimente cerut nu a fost găsit.",
+    ),
+    (
+        "packet-forbidden",
+        "You can only modify event packets you own.",
+        "Poți modifica doar pachetele de evenimente pe care le deții.",
+    ),
+    (
+        "packet-duplicate",
+        "An event packet with this name already exists.",
+        "Există deja un pachet de evenimente cu acest nume.",
+    ),
+    (
+        "packet-invalid-reference",
+        "A provided event ID is invalid.",
+        "Un ID de eveniment furnizat este invalid.",
+    ),
+    (
+        "packet-has-tickets",
+        "The packet still has tickets, delete them first.",
+        "Pachetul are încă bilete, ștergeți-le mai întâi.",
+    ),
+    // tickets
+    (
+        "ticket-not-found",
+        "The requested ticket was not found.",
+        "Biletul cerut nu a fost găsit.",
+    ),
+    (
+        "ticket-duplicate",
+        "A ticket with this code already exists.",
+        "Există deja un bilet cu acest cod.",
+    ),
+    (
+        "ticket-invalid-reference",
+        "Invalid packet or event ID provided.",
+        "ID-ul de pachet sau de eveniment furnizat este invalid.",
+    ),
+    (
+        "ticket-target-conflict",
+        "A ticket must belong to EITHER a packet OR an event, not both or neither.",
+        "Un bilet trebuie să aparțină FIE unui pachet, FIE unui eveniment, nu ambelor sau niciunuia.",
+    ),
+    (
+        "ticket-sold-out",
+        "There are no seats left for this event or packet.",
+        "Nu mai sunt locuri disponibile pentru acest eveniment sau pachet.",
+    ),
+    (
+        "ticket-already-checked-in",
+        "This ticket has already been used for this event.",
+        "Acest bilet a fost deja folosit pentru acest eveniment.",
+    ),
+    (
+        "ticket-event-required",
+        "Packet tickets must be checked in for a specific event.",
+        "Biletele de pachet trebuie validate pentru un eveniment anume.",
+    ),
+    (
+        "ticket-not-valid-for-event",
+        "This ticket doesn't grant entry to this event.",
+        "Acest bilet nu oferă acces la acest eveniment.",
+    ),
+    (
+        "ticket-forbidden",
+        "You can only manage tickets of events and packets you own.",
+        "Poți gestiona doar biletele evenimentelor și pachetelor pe care le deții.",
+    ),
+    (
+        "ticket-code-mistyped",
+        "The ticket code has a typo, its check character doesn't match.",
+        "Codul biletului are o greșeală, caracterul de control nu se potrivește.",
+    ),
+    (
+        "ticket-token-malformed",
+        "The ticket token is malformed.",
+        "Token-ul biletului este malformat.",
+    ),
+    (
+        "ticket-token-bad-signature",
+        "The ticket token signature doesn't match.",
+        "Semnătura token-ului biletului nu corespunde.",
+    ),
+    (
+        "ticket-token-expired",
+        "The ticket token has expired.",
+        "Token-ul biletului a expirat.",
+    ),
+    // joins
+    (
+        "join-duplicate",
+        "This event is already in this packet.",
+        "Acest eveniment face deja parte din acest pachet.",
+    ),
+    (
+        "join-invalid-reference",
+        "Invalid packet or event ID provided.",
+        "ID-ul de pachet sau de eveniment furnizat este invalid.",
+    ),
+    (
+        "join-sold-out",
+        "The event doesn't have enough seats left for the tickets already sold for this packet.",
+        "Evenimentul nu mai are destule locuri pentru biletele deja vândute pentru acest pachet.",
+    ),
+    (
+        "join-forbidden",
+        "You can only join events and packets you both own.",
+        "Poți asocia doar evenimente și pachete pe care le deții pe amândouă.",
+    ),
+    (
+        "internal-error",
+        "An internal server error occurred.",
+        "A apărut o eroare internă a serverului.",
+    ),
+    // validation rules
+    (
+        "invalid_value",
+        "The value is invalid.",
+        "Valoarea este invalidă.",
+    ),
+    (
+        "name_length",
+        "Name must be between 3 and 100 characters",
+        "Numele trebuie să aibă între 3 și 100 de caractere",
+    ),
+    (
+        "location_length",
+        "Location must be less than 255 characters",
+        "Locația trebuie să aibă mai puțin de 255 de caractere",
+    ),
+    (
+        "description_length",
+        "Description must be between 10 and 500 characters",
+        "Descrierea trebuie să aibă între 10 și 500 de caractere",
+    ),
+    (
+        "seats_range",
+        "Seats must be between 1 and 50,000",
+        "Numărul de locuri trebuie să fie între 1 și 50.000",
+    ),
+    (
+        "location_filter_length",
+        "Location filter must be less than 50 characters",
+        "Filtrul de locație trebuie să aibă mai puțin de 50 de caractere",
+    ),
+    (
+        "name_filter_length",
+        "Name filter must be less than 50 characters",
+        "Filtrul de nume trebuie să aibă mai puțin de 50 de caractere",
+    ),
+    (
+        "description_filter_length",
+        "Description filter must be less than 50 characters",
+        "Filtrul de descriere trebuie să aibă mai puțin de 50 de caractere",
+    ),
+    (
+        "available_tickets_min",
+        "Available tickets must be at least 1",
+        "Numărul de bilete disponibile trebuie să fie cel puțin 1",
+    ),
+    (
+        "invalid_interval",
+        "The end of an event must be after its start.",
+        "Sfârșitul unui eveniment trebuie să fie după începutul său.",
+    ),
+    (
+        "invalid_range",
+        "The `to` filter must not be before `from`.",
+        "Filtrul `to` nu poate fi înainte de `from`.",
+    ),
+    (
+        "invalid_timezone",
+        "Timezone must be an IANA name such as `Europe/Bucharest`.",
+        "Fusul orar trebuie să fie un nume IANA, de exemplu `Europe/Bucharest`.",
+    ),
+    (
+        "page_range",
+        "Page must be between 1 and 1000000",
+        "Pagina trebuie să fie între 1 și 1000000",
+    ),
+    (
+        "items_per_page_range",
+        "Items per page must be between 1 and 100",
+        "Numărul de elemente pe pagină trebuie să fie între 1 și 100",
+    ),
+    (
+        "limit_range",
+        "Limit must be between 1 and 100",
+        "Limita trebuie să fie între 1 și 100",
+    ),
+    (
+        "mixed_pagination",
+        "Use either `page`/`items_per_page` or `cursor`/`limit`, not both.",
+        "Folosește fie `page`/`items_per_page`, fie `cursor`/`limit`, nu pe amândouă.",
+    ),
+    (
+        "cursor_sort_mismatch",
+        "The cursor was issued for a different sort order.",
+        "Cursorul a fost emis pentru o altă ordine de sortare.",
+    ),
+    (
+        "cursor_key_mismatch",
+        "The cursor doesn't belong to this listing.",
+        "Cursorul nu aparține acestei liste.",
+    ),
+    (
+        "code_length",
+        "Code must be between 3 and 50 characters",
+        "Codul trebuie să aibă între 3 și 50 de caractere",
+    ),
+    (
+        "count_range",
+        "Count must be between 1 and 100",
+        "Numărul trebuie să fie între 1 și 100",
+    ),
+    (
+        "token_length",
+        "Token must not be empty",
+        "Token-ul nu poate fi gol",
+    ),
+    (
+        "exclusive_ids",
+        "A ticket can belong to EITHER a packet OR an event, not both.",
+        "Un bilet poate aparține FIE unui pachet, FIE unui eveniment, nu ambelor.",
+    ),
+    (
+        "missing_target",
+        "A ticket must belong to a packet OR an event.",
+        "Un bilet trebuie să aparțină unui pachet SAU unui eveniment.",
+    ),
+];
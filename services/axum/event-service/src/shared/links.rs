@@ -1,15 +1,22 @@
-use crate::models::event::{Event, EventQuery};
-use crate::models::event_packets::{EventPacketQuery, EventPackets};
-use crate::models::ticket::Ticket;
+use crate::models::check_in::{CheckIn, CheckInStats, CheckInTicket};
+use crate::models::event::{Event, EventQuery, UpdateEvent};
+use crate::models::event_packets::{EventPacketQuery, EventPackets, UpdateEventPacket};
+use crate::models::join_pe::{AddEventToPacket, AddPacketToEvent};
+use crate::models::pagination::{Cursor, Page, PaginationParams};
+use crate::models::ticket::{BulkCreateTickets, CreateTicket, Ticket, UpdateTicket};
+use crate::shared::hypermedia::{MediaType, Template};
+use crate::shared::routes::Route;
+use axum::http::Method;
 use serde::Serialize;
-use std::collections::HashMap;
+use serde_with::{DisplayFromStr, serde_as};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use time::format_description::well_known::Rfc3339;
 use utoipa::ToSchema;
 
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct Link {
     pub href: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub r#type: Option<String>,
 }
 
 #[derive(Serialize, Debug, Clone, ToSchema)]
@@ -24,22 +31,12 @@ pub struct Links {
 
 impl Link {
     pub fn new(href: impl Into<String>) -> Self {
-        Self {
-            href: href.into(),
-            r#type: None,
-        }
-    }
-
-    pub fn with_type(mut self, r#type: impl Into<String>) -> Self {
-        self.r#type = Some(r#type.into());
-        self
-    }
-    pub fn with_types(mut self, types: &[&str]) -> Self {
-        self.r#type = Some(types.join(", "));
-        self
+        Self { href: href.into() }
     }
 }
 
+// `_templates` is only rendered for `application/prs.hal-forms+json`,
+// plain HAL clients get the links alone
 #[derive(Serialize, Debug, ToSchema)]
 pub struct Response<T>
 where
@@ -49,6 +46,38 @@ where
     pub data: T,
     #[serde(rename = "_links")]
     pub links: Links,
+    #[serde(rename = "_templates", skip_serializing_if = "Option::is_none")]
+    pub templates: Option<BTreeMap<String, Template>>,
+}
+
+#[serde_as]
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PageInfo {
+    pub total: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,
+    pub items_per_page: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[schema(value_type = Option<String>)]
+    pub next_cursor: Option<Cursor>,
+}
+
+// the items sit under `_embedded.<rel>`, e.g. `_embedded.events`
+#[derive(Serialize, Debug, ToSchema)]
+pub struct CollectionResponse<T>
+where
+    T: ToSchema + Serialize,
+{
+    #[serde(rename = "_embedded")]
+    pub embedded: HashMap<String, Vec<Response<T>>>,
+    #[serde(rename = "_links")]
+    pub links: Links,
+    #[serde(rename = "_templates", skip_serializing_if = "Option::is_none")]
+    pub templates: Option<BTreeMap<String, Template>>,
+    pub count: usize,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub paging: Option<PageInfo>,
 }
 
 pub struct ResponseBuilder<T> {
@@ -56,6 +85,7 @@ pub struct ResponseBuilder<T> {
     self_link: Link,
     parent_link: Option<Link>,
     other_links: HashMap<String, Link>,
+    templates: Option<BTreeMap<String, Template>>,
 }
 
 impl<T> ResponseBuilder<T>
@@ -63,78 +93,61 @@ where
     T: ToSchema + Serialize,
 {
     pub fn new(data: T, self_href: impl Into<String>) -> Self {
+        // templates are skipped up front when nobody asked for them,
+        // building the forms walks the request schemas
+        let templates = (MediaType::current() == MediaType::HalForms).then(BTreeMap::new);
+
         Self {
             data,
             self_link: Link::new(self_href),
             parent_link: None,
             other_links: HashMap::new(),
+            templates,
         }
     }
 
-    pub fn self_type(mut self, r#type: impl Into<String>) -> Self {
-        self.self_link.r#type = Some(r#type.into());
-        self
-    }
-
-    pub fn self_types(mut self, types: &[&str]) -> Self {
-        self.self_link.r#type = Some(types.join(", "));
-        self
-    }
-
     pub fn parent(mut self, href: impl Into<String>) -> Self {
         self.parent_link = Some(Link::new(href));
         self
     }
 
-    pub fn parent_with_type(mut self, href: impl Into<String>, r#type: impl Into<String>) -> Self {
-        self.parent_link = Some(Link::new(href).with_type(r#type));
-        self
-    }
-
-    pub fn parent_with_types(mut self, href: impl Into<String>, types: &[&str]) -> Self {
-        self.parent_link = Some(Link::new(href).with_types(types));
-        self
-    }
-
     pub fn link(mut self, name: impl Into<String>, href: impl Into<String>) -> Self {
         self.other_links.insert(name.into(), Link::new(href));
         self
     }
 
-    pub fn link_with_type(
+    // an affordance without a request body, e.g. DELETE
+    pub fn action(
         mut self,
         name: impl Into<String>,
-        href: impl Into<String>,
-        r#type: impl Into<String>,
+        method: Method,
+        target: impl Into<String>,
     ) -> Self {
-        self.other_links
-            .insert(name.into(), Link::new(href).with_type(r#type));
+        if let Some(templates) = self.templates.as_mut() {
+            templates.insert(name.into(), Template::new(method, target));
+        }
         self
     }
 
-    pub fn link_with_types(
+    // an affordance whose fields are described by the request body `B`
+    pub fn form<B: ToSchema>(
         mut self,
         name: impl Into<String>,
-        href: impl Into<String>,
-        types: &[&str],
+        method: Method,
+        target: impl Into<String>,
     ) -> Self {
-        self.other_links
-            .insert(name.into(), Link::new(href).with_types(types));
+        if let Some(templates) = self.templates.as_mut() {
+            templates.insert(name.into(), Template::new(method, target).with_body::<B>());
+        }
         self
     }
 
     pub fn build(self) -> Response<T> {
+        let (data, links, templates) = self.into_parts();
         Response {
-            data: self.data,
-            links: Links {
-                link: self.self_link,
-                parent: self.parent_link,
-                others: if self.other_links.is_empty() {
-                    None
-                } else {
-                    Some(self.other_links)
-                },
-            },
+            data,
+            links,
+            templates,
         }
     }
     pub fn builder(entity: T, self_href: impl Into<String>) -> ResponseBuilder<T> {
@@ -142,30 +155,162 @@ where
     }
 }
 
+type Parts<T> = (T, Links, Option<BTreeMap<String, Template>>);
+
+impl<T> ResponseBuilder<T> {
+    fn into_parts(self) -> Parts<T> {
+        let links = Links {
+            link: self.self_link,
+            parent: self.parent_link,
+            others: if self.other_links.is_empty() {
+                None
+            } else {
+                Some(self.other_links)
+            },
+        };
+        (self.data, links, self.templates)
+    }
+}
+
+impl<T> ResponseBuilder<Vec<Response<T>>>
+where
+    T: ToSchema + Serialize,
+{
+    pub fn build_collection(
+        self,
+        rel: impl Into<String>,
+        paging: Option<PageInfo>,
+    ) -> CollectionResponse<T> {
+        let (items, links, templates) = self.into_parts();
+        CollectionResponse {
+            count: items.len(),
+            embedded: HashMap::from([(rel.into(), items)]),
+            links,
+            templates,
+            paging,
+        }
+    }
+}
+
 pub fn build_simple_ticket(ticket: Ticket, base_url: &str) -> Response<Ticket> {
     let code = ticket.cod.clone();
-    ResponseBuilder::new(ticket, format!("{}/tickets/{}", base_url, code))
-        .self_types(&["[GET, PUT, POST, DELETE]"])
-        .parent_with_types(format!("{}/tickets", base_url), &["[GET, POST]"])
+    let self_href = Route::Ticket.href(base_url, &[&code]);
+
+    ResponseBuilder::new(ticket, self_href.clone())
+        .parent(Route::Tickets.href(base_url, &[]))
+        .link("qr", Route::TicketQr.href(base_url, &[&code]))
+        .form::<UpdateTicket>("default", Method::PUT, self_href.clone())
+        .action("delete", Method::DELETE, self_href)
+        .form::<CheckInTicket>(
+            "check-in",
+            Method::POST,
+            Route::TicketCheckIn.href(base_url, &[&code]),
+        )
         .build()
 }
 
-pub fn build_simple_event(event: Event, base_url: &str) -> Response<Event> {
-    let id = event.id;
-    ResponseBuilder::new(event, format!("{}/events/{}", base_url, id))
-        .self_types(&["[GET, PUT, POST, DELETE]"])
-        .parent_with_types(format!("{}/events", base_url), &["[GET, POST]"])
-        .link_with_types(
-            "event-packets",
-            format!("{}/events/{}/event-packets", base_url, id),
-            &["[GET, POST]"],
+// the bulk endpoint only takes POSTs, so the batch points back at the
+// collection the tickets were added to
+pub fn build_ticket_batch(
+    tickets: Vec<Response<Ticket>>,
+    collection_href: String,
+    bulk_href: String,
+) -> CollectionResponse<Ticket> {
+    ResponseBuilder::new(tickets, collection_href)
+        .form::<BulkCreateTickets>("default", Method::POST, bulk_href)
+        .build_collection("tickets", None)
+}
+
+// what can be done with an event, wherever it's listed
+fn event_affordances<T>(builder: ResponseBuilder<T>, id: i32, base_url: &str) -> ResponseBuilder<T>
+where
+    T: ToSchema + Serialize,
+{
+    let event_href = Route::Event.href(base_url, &[&id]);
+
+    builder
+        .form::<UpdateEvent>("default", Method::PUT, event_href.clone())
+        .action("delete", Method::DELETE, event_href)
+        .form::<CreateTicket>(
+            "create-ticket",
+            Method::POST,
+            Route::EventTickets.href(base_url, &[&id]),
         )
-        .link_with_types(
-            "tickets",
-            format!("{}/events/{}/tickets", base_url, id),
-            &["[GET, POST]"],
+        .form::<AddPacketToEvent>(
+            "add-packet",
+            Method::POST,
+            Route::EventPackets.href(base_url, &[&id]),
         )
-        .build()
+}
+
+fn packet_affordances<T>(builder: ResponseBuilder<T>, id: i32, base_url: &str) -> ResponseBuilder<T>
+where
+    T: ToSchema + Serialize,
+{
+    let packet_href = Route::Packet.href(base_url, &[&id]);
+
+    builder
+        .form::<UpdateEventPacket>("default", Method::PUT, packet_href.clone())
+        .action("delete", Method::DELETE, packet_href)
+        .form::<CreateTicket>(
+            "create-ticket",
+            Method::POST,
+            Route::PacketTickets.href(base_url, &[&id]),
+        )
+        .form::<AddEventToPacket>(
+            "add-event",
+            Method::POST,
+            Route::PacketEvents.href(base_url, &[&id]),
+        )
+}
+
+pub fn build_simple_event(event: Event, base_url: &str) -> Response<Event> {
+    let id = event.id;
+    let builder = ResponseBuilder::new(event, Route::Event.href(base_url, &[&id]))
+        .parent(Route::Events.href(base_url, &[]))
+        .link("event-packets", Route::EventPackets.href(base_url, &[&id]))
+        .link("tickets", Route::EventTickets.href(base_url, &[&id]))
+        .link(
+            "check-in-stats",
+            Route::EventCheckInStats.href(base_url, &[&id]),
+        );
+
+    event_affordances(builder, id, base_url).build()
+}
+
+// `name=value` with the value form-encoded, so spaces, `&`, `+` (e.g. in a
+// timestamp offset) or diacritics survive the round trip through the link
+fn query_part(name: &str, value: impl Display) -> String {
+    let value: String = form_urlencoded::byte_serialize(value.to_string().as_bytes()).collect();
+    format!("{}={}", name, value)
+}
+
+pub fn event_filter_parts(params: &EventQuery) -> Vec<String> {
+    let mut query_parts = vec![];
+
+    if let Some(loc) = &params.locatie {
+        query_parts.push(query_part("location", loc));
+    }
+    if let Some(name) = &params.nume {
+        query_parts.push(query_part("name", name));
+    }
+    if let Some(tickets) = params.bilete {
+        query_parts.push(query_part("available_tickets", tickets));
+    }
+    if let Some(from) = params.from {
+        query_parts.push(query_part(
+            "from",
+            from.format(&Rfc3339).unwrap_or_default(),
+        ));
+    }
+    if let Some(to) = params.to {
+        query_parts.push(query_part("to", to.format(&Rfc3339).unwrap_or_default()));
+    }
+    if let Some(upcoming) = params.upcoming {
+        query_parts.push(query_part("upcoming", upcoming));
+    }
+
+    query_parts
 }
 
 pub fn build_filtered_event(
@@ -176,24 +321,17 @@ pub fn build_filtered_event(
     let mut responses = Vec::with_capacity(events.len());
 
     for event in events {
-        let mut self_href = format!("{}/events", base_url);
-        let mut query_parts = vec![];
-
-        if let Some(loc) = &params.locatie {
-            query_parts.push(format!("location={}", loc));
-        }
-        if let Some(name) = &params.nume {
-            query_parts.push(format!("name={}", name));
-        }
+        let id = event.id;
+        let mut self_href = Route::Events.href(base_url, &[]);
+        let query_parts = event_filter_parts(params);
 
         if !query_parts.is_empty() {
             self_href = format!("{}?{}", self_href, query_parts.join("&"));
         }
 
-        let response = ResponseBuilder::new(event, self_href)
-            .self_types(&["GET"])
-            .parent_with_types(format!("{}/events", base_url), &["[GET", "POST]"])
-            .build();
+        let builder =
+            ResponseBuilder::new(event, self_href).parent(Route::Events.href(base_url, &[]));
+        let response = event_affordances(builder, id, base_url).build();
 
         responses.push(response);
     }
@@ -206,56 +344,46 @@ pub fn build_packet_over_event(
     id: i32,
     base_url: &str,
 ) -> Response<EventPackets> {
-    let self_url = format!("{}/events/{}/event-packets", base_url, id);
+    let self_url = Route::EventPackets.href(base_url, &[&id]);
 
-    ResponseBuilder::new(event, self_url)
-        .self_types(&["[GET", "POST]"])
-        .parent_with_types(
-            format!("{}/events/{}", base_url, id),
-            &["[GET, PUT, POST, DELETE]"],
-        )
+    ResponseBuilder::new(event, self_url.clone())
+        .parent(Route::Event.href(base_url, &[&id]))
+        .form::<AddPacketToEvent>("default", Method::POST, self_url)
         .build()
 }
 
 pub fn build_ticket_over_event(ticket: Ticket, event_id: i32, base_url: &str) -> Response<Ticket> {
     let code = ticket.cod.clone();
-    let self_url = format!("{}/events/{}/tickets/{}", base_url, event_id, code);
-    let parent_url = format!("{}/events/{}/tickets", base_url, event_id);
+    let self_url = Route::EventTicket.href(base_url, &[&event_id, &code]);
+    let parent_url = Route::EventTickets.href(base_url, &[&event_id]);
 
-    ResponseBuilder::new(ticket, self_url)
-        .self_types(&["[GET", "PUT", "POST", "DELETE]"])
-        .parent_with_types(parent_url, &["[GET, POST]"])
+    ResponseBuilder::new(ticket, self_url.clone())
+        .parent(parent_url)
+        .form::<UpdateTicket>("default", Method::PUT, self_url.clone())
+        .action("delete", Method::DELETE, self_url)
         .build()
 }
 
 pub fn build_simple_event_packet(packet: EventPackets, base_url: &str) -> Response<EventPackets> {
     let packet_id = packet.id;
 
-    ResponseBuilder::new(packet, format!("{}/event-packets/{}", base_url, packet_id))
-        .self_types(&["[GET", "PUT", "POST", "DELETE]"])
-        .parent_with_types(format!("{}/event-packets", base_url), &["[GET", "POST]"])
-        .link_with_types(
-            "events",
-            format!("{}/event-packets/{}/events", base_url, packet_id),
-            &["[GET", "POST]"],
-        )
-        .link_with_types(
+    let builder = ResponseBuilder::new(packet, Route::Packet.href(base_url, &[&packet_id]))
+        .parent(Route::Packets.href(base_url, &[]))
+        .link("events", Route::PacketEvents.href(base_url, &[&packet_id]))
+        .link(
             "tickets",
-            format!("{}/event-packets/{}/tickets", base_url, packet_id),
-            &["[GET", "POST]"],
-        )
-        .build()
+            Route::PacketTickets.href(base_url, &[&packet_id]),
+        );
+
+    packet_affordances(builder, packet_id, base_url).build()
 }
 
 pub fn build_event_over_packet(event: Event, packet_id: i32, base_url: &str) -> Response<Event> {
-    let self_url = format!("{}/event-packets/{}/events", base_url, packet_id);
+    let self_url = Route::PacketEvents.href(base_url, &[&packet_id]);
 
-    ResponseBuilder::new(event, self_url)
-        .self_types(&["[GET", "POST]"])
-        .parent_with_types(
-            format!("{}/event-packets/{}", base_url, packet_id),
-            &["[GET", "PUT", "POST", "DELETE]"],
-        )
+    ResponseBuilder::new(event, self_url.clone())
+        .parent(Route::Packet.href(base_url, &[&packet_id]))
+        .form::<AddEventToPacket>("default", Method::POST, self_url)
         .build()
 }
 
@@ -265,18 +393,29 @@ pub fn build_ticket_over_packet(
     base_url: &str,
 ) -> Response<Ticket> {
     let ticket_cod = ticket.cod.clone();
-    let self_url = format!(
-        "{}/event-packets/{}/tickets/{}",
-        base_url, packet_id, ticket_cod
-    );
-    let parent_url = format!("{}/event-packets/{}/tickets", base_url, packet_id);
-
-    ResponseBuilder::new(ticket, self_url)
-        .self_types(&["[GET", "PUT", "POST", "DELETE]"])
-        .parent_with_types(parent_url, &["[GET", "POST]"])
+    let self_url = Route::PacketTicket.href(base_url, &[&packet_id, &ticket_cod]);
+    let parent_url = Route::PacketTickets.href(base_url, &[&packet_id]);
+
+    ResponseBuilder::new(ticket, self_url.clone())
+        .parent(parent_url)
+        .form::<UpdateTicket>("default", Method::PUT, self_url.clone())
+        .action("delete", Method::DELETE, self_url)
         .build()
 }
 
+pub fn packet_filter_parts(params: &EventPacketQuery) -> Vec<String> {
+    let mut query_parts = vec![];
+
+    if let Some(tickets) = params.bilete {
+        query_parts.push(query_part("available_tickets", tickets));
+    }
+    if let Some(desc) = &params.descriere {
+        query_parts.push(query_part("type", desc));
+    }
+
+    query_parts
+}
+
 pub fn build_filtered_event_packets(
     packets: Vec<EventPackets>,
     params: &EventPacketQuery,
@@ -285,7 +424,8 @@ pub fn build_filtered_event_packets(
     let mut responses = Vec::with_capacity(packets.len());
 
     for packet in packets {
-        let mut self_href = format!("{}/event-packets", base_url);
+        let id = packet.id;
+        let mut self_href = Route::Packets.href(base_url, &[]);
         let mut query_parts = vec![];
 
         if let Some(page) = params.paginare.page {
@@ -294,24 +434,113 @@ pub fn build_filtered_event_packets(
         if let Some(items) = params.paginare.items_per_page {
             query_parts.push(format!("items_per_page={}", items));
         }
-        if let Some(tickets) = params.bilete {
-            query_parts.push(format!("available_tickets={}", tickets));
-        }
-        if let Some(desc) = &params.descriere {
-            query_parts.push(format!("type={}", desc));
-        }
+        query_parts.extend(packet_filter_parts(params));
 
         if !query_parts.is_empty() {
             self_href = format!("{}?{}", self_href, query_parts.join("&"));
         }
 
-        let response = ResponseBuilder::new(packet, self_href)
-            .self_types(&["GET"])
-            .parent_with_types(format!("{}/event-packets", base_url), &["[GET", "POST]"])
-            .build();
+        let builder =
+            ResponseBuilder::new(packet, self_href).parent(Route::Packets.href(base_url, &[]));
+        let response = packet_affordances(builder, id, base_url).build();
 
         responses.push(response);
     }
 
     responses
 }
+
+pub fn build_check_in(check_in: CheckIn, base_url: &str) -> Response<CheckIn> {
+    let code = check_in.cod.clone();
+    let event_id = check_in.id_event;
+
+    // a check-in can only be POSTed, the ticket is where it can be read back
+    ResponseBuilder::new(check_in, Route::Ticket.href(base_url, &[&code]))
+        .parent(Route::Tickets.href(base_url, &[]))
+        .link(
+            "check-in-stats",
+            Route::EventCheckInStats.href(base_url, &[&event_id]),
+        )
+        .form::<CheckInTicket>(
+            "default",
+            Method::POST,
+            Route::TicketCheckIn.href(base_url, &[&code]),
+        )
+        .build()
+}
+
+pub fn build_check_in_stats(stats: CheckInStats, base_url: &str) -> Response<CheckInStats> {
+    let event_id = stats.id_event;
+
+    ResponseBuilder::new(stats, Route::EventCheckInStats.href(base_url, &[&event_id]))
+        .parent(Route::Event.href(base_url, &[&event_id]))
+        .build()
+}
+
+// `filters` are the listing's own query parts, paging and sorting are added
+// here. offset pages link to first/prev/next/last, keyset pages only know the
+// way forward so they link to first and next. `B` is the body a POST to the
+// collection takes
+pub fn build_collection<B, T>(
+    page: Page<Response<T>>,
+    rel: &str,
+    collection_href: &str,
+    filters: Vec<String>,
+    params: &PaginationParams,
+) -> CollectionResponse<T>
+where
+    B: ToSchema,
+    T: ToSchema + Serialize,
+{
+    let mut query_parts = filters;
+    query_parts.extend(params.query_parts());
+    let query = query_parts.join("&");
+
+    let href = |position: Option<String>| match position {
+        Some(position) => format!("{}?{}&{}", collection_href, position, query),
+        None => format!("{}?{}", collection_href, query),
+    };
+
+    let last = page.last_page();
+    let paging = PageInfo {
+        total: page.total,
+        page: page.page,
+        items_per_page: page.items_per_page,
+        next_cursor: page.next_cursor,
+    };
+
+    if params.is_keyset() {
+        let self_href = href(params.cursor.as_ref().map(|c| format!("cursor={}", c)));
+        let next_href = paging
+            .next_cursor
+            .as_ref()
+            .map(|c| href(Some(format!("cursor={}", c))));
+
+        let mut builder = ResponseBuilder::new(page.items, self_href)
+            .link("first", href(None))
+            .form::<B>("default", Method::POST, collection_href);
+
+        if let Some(next_href) = next_href {
+            builder = builder.link("next", next_href);
+        }
+
+        return builder.build_collection(rel, Some(paging));
+    }
+
+    let page_href = |number: i64| href(Some(format!("page={}", number)));
+    let current = paging.page.unwrap_or(1);
+
+    let mut builder = ResponseBuilder::new(page.items, page_href(current))
+        .link("first", page_href(1))
+        .link("last", page_href(last))
+        .form::<B>("default", Method::POST, collection_href);
+
+    if current > 1 {
+        builder = builder.link("prev", page_href((current - 1).min(last)));
+    }
+    if current < last {
+        builder = builder.link("next", page_href(current + 1));
+    }
+
+    builder.build_collection(rel, Some(paging))
+}
@@ -0,0 +1,78 @@
+use crate::repositories::health_repo::PoolUsage;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::Instant;
+
+// seconds, from a cache hit to a request that's about to time out
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// the recorder is process wide, install it once before the router is built
+pub fn install_recorder() -> Result<PrometheusHandle, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Suffix("_duration_seconds".to_string()),
+            LATENCY_BUCKETS,
+        )?
+        .install_recorder()
+}
+
+// labelled by the route template, `/api/event-manager/events/{id}`, so ids
+// and ticket codes don't turn into label values
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(req).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+
+    response
+}
+
+// records how long a repository method took once it goes out of scope,
+// `let _timer = QueryTimer::start("EventRepo", "get_event");`
+pub struct QueryTimer {
+    repo: &'static str,
+    query: &'static str,
+    started: Instant,
+}
+
+impl QueryTimer {
+    pub fn start(repo: &'static str, query: &'static str) -> Self {
+        Self {
+            repo,
+            query,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        histogram!("db_query_duration_seconds", "repo" => self.repo, "query" => self.query)
+            .record(self.started.elapsed().as_secs_f64());
+    }
+}
+
+// gauges are sampled on scrape, the pool doesn't report changes itself
+pub fn record_pool(usage: PoolUsage) {
+    gauge!("db_pool_connections", "state" => "open").set(usage.open as f64);
+    gauge!("db_pool_connections", "state" => "idle").set(usage.idle as f64);
+    gauge!("db_pool_max_connections").set(usage.max as f64);
+}
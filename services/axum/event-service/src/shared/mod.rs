@@ -1,3 +1,17 @@
+pub mod auth;
+pub mod base_url;
+pub mod db;
 pub mod doc;
 pub mod error;
+pub mod hypermedia;
+pub mod i18n;
 pub mod links;
+pub mod metrics;
+pub mod qr;
+pub mod request_id;
+pub mod routes;
+pub mod settings;
+pub mod shutdown;
+pub mod telemetry;
+pub mod ticket_code;
+pub mod ticket_token;
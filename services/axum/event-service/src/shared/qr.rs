@@ -0,0 +1,99 @@
+use image::{ImageFormat, Luma};
+use qrcode::QrCode;
+use qrcode::render::svg;
+use serde::Deserialize;
+use std::io::Cursor;
+use utoipa::ToSchema;
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+pub fn render(data: &str, format: QrFormat) -> Result<Vec<u8>, String> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| e.to_string())?;
+
+    match format {
+        QrFormat::Png => {
+            let image = code.render::<Luma<u8>>().min_dimensions(256, 256).build();
+            let mut bytes = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+            Ok(bytes)
+        }
+        QrFormat::Svg => Ok(code
+            .render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .build()
+            .into_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_is_at_least_256_pixels_wide() {
+        let bytes = render("TKT-A", QrFormat::Png).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let image = image::load_from_memory_with_format(&bytes, ImageFormat::Png).unwrap();
+        assert!(image.width() >= 256 && image.height() >= 256);
+        assert_eq!(image.width(), image.height());
+    }
+
+    #[test]
+    fn svg_is_an_svg_document_of_at_least_256_pixels() {
+        let bytes = render("TKT-A", QrFormat::Svg).unwrap();
+        let svg = String::from_utf8(bytes).unwrap();
+        assert!(svg.starts_with("<?xml"), "{}", svg);
+        assert!(svg.contains("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        let width: u32 = svg
+            .split_once(r#"width=""#)
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .and_then(|(width, _)| width.parse().ok())
+            .unwrap();
+        assert!(width >= 256, "{}", width);
+    }
+
+    #[test]
+    fn the_same_data_renders_the_same_image() {
+        assert_eq!(
+            render("TKT-A", QrFormat::Png).unwrap(),
+            render("TKT-A", QrFormat::Png).unwrap()
+        );
+        assert_ne!(
+            render("TKT-A", QrFormat::Svg).unwrap(),
+            render("TKT-B", QrFormat::Svg).unwrap()
+        );
+    }
+
+    #[test]
+    fn data_past_the_largest_version_is_an_error() {
+        assert!(render(&"A".repeat(8000), QrFormat::Png).is_err());
+        assert!(render(&"A".repeat(8000), QrFormat::Svg).is_err());
+    }
+
+    #[test]
+    fn content_types_match_the_format() {
+        assert_eq!(QrFormat::Png.content_type(), "image/png");
+        assert_eq!(QrFormat::Svg.content_type(), "image/svg+xml");
+        assert!(matches!(QrFormat::default(), QrFormat::Png));
+    }
+}
@@ -0,0 +1,110 @@
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use tracing::{Span, info_span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const TRACEPARENT: &str = "traceparent";
+
+// longer ids are replaced, they would only bloat logs and error bodies
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: RequestId;
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    // the id of the request being handled, `None` outside of one
+    pub fn current() -> Option<String> {
+        REQUEST_ID.try_with(|id| id.0.clone()).ok()
+    }
+
+    // the caller's `X-Request-Id`, else the trace id of its `traceparent` so
+    // both name the same thing, else a fresh one
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let given = headers
+            .get(X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid(id));
+
+        let traced = || {
+            headers
+                .get(TRACEPARENT)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split('-').nth(1))
+                .filter(|trace_id| trace_id.len() == 32 && is_valid(trace_id))
+        };
+
+        match given.or_else(traced) {
+            Some(id) => RequestId(id.to_string()),
+            None => RequestId(format!("{:032x}", rand::random::<u128>())),
+        }
+    }
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+}
+
+// outermost layer, so the id exists for the trace span and for every
+// problem document rendered further in
+pub async fn propagate_request_id(mut req: Request, next: Next) -> Response {
+    let id = RequestId::from_headers(req.headers());
+    req.extensions_mut().insert(id.clone());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+
+    // only ids that passed `is_valid` get here
+    if let Ok(value) = HeaderValue::from_str(&id.0) {
+        response.headers_mut().insert(X_REQUEST_ID, value);
+    }
+    response
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+// the span `TraceLayer` opens per request. it carries the request id, so
+// every span below it (handlers, repositories) is tied to it, and continues
+// the caller's trace when a `traceparent` came in
+pub fn request_span<B>(req: &axum::http::Request<B>) -> Span {
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.as_str())
+        .unwrap_or_default();
+
+    let span = info_span!(
+        "request",
+        method = %req.method(),
+        uri = %req.uri(),
+        request_id = %request_id,
+    );
+
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req.headers()))
+    });
+    let _ = span.set_parent(parent);
+
+    span
+}
@@ -0,0 +1,127 @@
+use axum::http::Method;
+use std::fmt::Display;
+
+// every path the service serves. the routers register `path()` and the link
+// builders fill it in through `href()`, so a link can't drift from its route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Events,
+    Event,
+    EventCheckInStats,
+    EventPackets,
+    EventTickets,
+    EventTicketsBulk,
+    EventTicket,
+    Packets,
+    Packet,
+    PacketEvents,
+    PacketTickets,
+    PacketTicketsBulk,
+    PacketTicket,
+    Tickets,
+    Ticket,
+    TicketTokenVerify,
+    TicketQr,
+    TicketCheckIn,
+}
+
+impl Route {
+    pub const ALL: &'static [Route] = &[
+        Route::Events,
+        Route::Event,
+        Route::EventCheckInStats,
+        Route::EventPackets,
+        Route::EventTickets,
+        Route::EventTicketsBulk,
+        Route::EventTicket,
+        Route::Packets,
+        Route::Packet,
+        Route::PacketEvents,
+        Route::PacketTickets,
+        Route::PacketTicketsBulk,
+        Route::PacketTicket,
+        Route::Tickets,
+        Route::Ticket,
+        Route::TicketTokenVerify,
+        Route::TicketQr,
+        Route::TicketCheckIn,
+    ];
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Route::Events => "/events",
+            Route::Event => "/events/{id}",
+            Route::EventCheckInStats => "/events/{id}/check-in-stats",
+            Route::EventPackets => "/events/{id}/event-packets",
+            Route::EventTickets => "/events/{id}/tickets",
+            Route::EventTicketsBulk => "/events/{id}/tickets/bulk",
+            Route::EventTicket => "/events/{id}/tickets/{cod}",
+            Route::Packets => "/event-packets",
+            Route::Packet => "/event-packets/{id}",
+            Route::PacketEvents => "/event-packets/{id}/events",
+            Route::PacketTickets => "/event-packets/{id}/tickets",
+            Route::PacketTicketsBulk => "/event-packets/{id}/tickets/bulk",
+            Route::PacketTicket => "/event-packets/{id}/tickets/{ticket_cod}",
+            Route::Tickets => "/tickets",
+            Route::Ticket => "/tickets/{cod}",
+            Route::TicketTokenVerify => "/ticket-tokens/verify",
+            Route::TicketQr => "/tickets/{cod}/qr",
+            Route::TicketCheckIn => "/tickets/{cod}/check-in",
+        }
+    }
+
+    // the methods the router answers on this path
+    pub fn methods(self) -> &'static [Method] {
+        const COLLECTION: &[Method] = &[Method::GET, Method::POST];
+        const ITEM: &[Method] = &[Method::GET, Method::PUT, Method::DELETE];
+        const READ: &[Method] = &[Method::GET];
+        const WRITE: &[Method] = &[Method::POST];
+
+        match self {
+            Route::Events
+            | Route::EventPackets
+            | Route::EventTickets
+            | Route::Packets
+            | Route::PacketEvents
+            | Route::PacketTickets
+            | Route::Tickets => COLLECTION,
+            Route::Event
+            | Route::EventTicket
+            | Route::Packet
+            | Route::PacketTicket
+            | Route::Ticket => ITEM,
+            Route::EventCheckInStats | Route::TicketQr => READ,
+            Route::EventTicketsBulk
+            | Route::PacketTicketsBulk
+            | Route::TicketTokenVerify
+            | Route::TicketCheckIn => WRITE,
+        }
+    }
+
+    // fills the `{..}` segments of the path in order, e.g.
+    // `Route::EventTicket.href(base_url, &[&id, &cod])`
+    pub fn href(self, base_url: &str, params: &[&dyn Display]) -> String {
+        let mut params = params.iter();
+        let mut href = base_url.to_string();
+
+        for segment in self.path().split('/').skip(1) {
+            href.push('/');
+            if segment.starts_with('{') {
+                let param = params
+                    .next()
+                    .unwrap_or_else(|| panic!("missing {} for {}", segment, self.path()));
+                href.push_str(&param.to_string());
+            } else {
+                href.push_str(segment);
+            }
+        }
+
+        debug_assert!(
+            params.next().is_none(),
+            "too many parameters for {}",
+            self.path()
+        );
+
+        href
+    }
+}
@@ -0,0 +1,333 @@
+use crate::shared::base_url::BaseUrlResolver;
+use crate::shared::ticket_code::{DEFAULT_PREFIX, DEFAULT_RANDOM_LENGTH, TicketCodeGenerator};
+use crate::shared::ticket_token::DEFAULT_TTL_SECS;
+use config::{Config, ConfigError, Environment, File};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+// settings are layered, later sources win:
+//   1. the defaults below
+//   2. a TOML file, `CONFIG_FILE` or `event-service.toml` in the working directory
+//   3. `EVENT_SERVICE__<SECTION>__<KEY>` variables, e.g. `EVENT_SERVICE__SERVER__PORT`
+//   4. the flat variables the compose files already use, see `FLAT_ENV`
+pub const ENV_PREFIX: &str = "EVENT_SERVICE";
+pub const DEFAULT_CONFIG_FILE: &str = "event-service.toml";
+
+const FLAT_ENV: &[(&str, &str)] = &[
+    ("DATABASE_URL", "database.url"),
+    ("JWT_SECRET", "auth.jwt_secret"),
+    ("SERVER_HOST", "server.host"),
+    ("SERVER_PORT", "server.port"),
+    ("PUBLIC_BASE_URL", "server.base_url"),
+    ("BASE_URL_FROM_REQUEST", "server.base_url_from_request"),
+    ("SHUTDOWN_DELAY_SECS", "server.shutdown_delay_secs"),
+    ("DRAIN_TIMEOUT_SECS", "server.drain_timeout_secs"),
+    ("MIGRATE_ON_STARTUP", "database.migrate_on_startup"),
+    ("TICKET_CODE_PREFIX", "tickets.code_prefix"),
+    ("TICKET_CODE_LENGTH", "tickets.code_length"),
+    ("TICKET_SIGNING_KEY", "tickets.signing_key"),
+    ("TICKET_TOKEN_TTL_SECS", "tickets.token_ttl_secs"),
+    ("LOG_LEVEL", "log.level"),
+    ("RUST_LOG", "log.level"),
+    ("LOG_FORMAT", "log.format"),
+    ("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "otlp.endpoint"),
+    ("OTEL_SERVICE_NAME", "otlp.service_name"),
+];
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub auth: AuthSettings,
+    pub tickets: TicketSettings,
+    pub log: LogSettings,
+    pub otlp: OtlpSettings,
+    pub features: FeatureSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub host: IpAddr,
+    pub port: u16,
+    // where clients reach the API, every HATEOAS link starts with it
+    pub base_url: String,
+    // rebuild the base url from `Host` and the forwarded headers instead,
+    // `base_url` is then only the fallback for requests without a host
+    pub base_url_from_request: bool,
+    // peers whose `Forwarded` / `X-Forwarded-*` headers are believed
+    pub trusted_proxies: Vec<String>,
+    // after a stop signal readiness fails for this long before new
+    // connections are refused, so load balancers can take us out first
+    pub shutdown_delay_secs: u64,
+    // how long in-flight requests may run once new connections are refused
+    pub drain_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    // 0 keeps idle connections open forever
+    pub idle_timeout_secs: u64,
+    pub migrate_on_startup: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub jwt_secret: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TicketSettings {
+    pub code_prefix: String,
+    pub code_length: usize,
+    pub signing_key: String,
+    pub token_ttl_secs: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    // `RUST_LOG` syntax, a default level and per-module overrides,
+    // e.g. `info,sqlx=warn,event_service::repositories=debug`
+    pub level: String,
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // one line per event, for people reading a terminal
+    #[default]
+    Compact,
+    // one JSON object per event, for log collectors
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OtlpSettings {
+    // off by default, there's no collector unless someone runs one
+    pub enabled: bool,
+    // the OTLP/HTTP traces endpoint of the collector
+    pub endpoint: String,
+    pub service_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureSettings {
+    pub swagger_ui: bool,
+    // prometheus exposition at `/metrics`
+    pub metrics: bool,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8080,
+            base_url: "http://localhost:8001/api/event-manager".to_string(),
+            base_url_from_request: false,
+            trusted_proxies: Vec::new(),
+            shutdown_delay_secs: 0,
+            drain_timeout_secs: 20,
+        }
+    }
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 50,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+            migrate_on_startup: true,
+        }
+    }
+}
+
+impl Default for TicketSettings {
+    fn default() -> Self {
+        Self {
+            code_prefix: DEFAULT_PREFIX.to_string(),
+            code_length: DEFAULT_RANDOM_LENGTH,
+            signing_key: String::new(),
+            token_ttl_secs: DEFAULT_TTL_SECS,
+        }
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Compact,
+        }
+    }
+}
+
+impl Default for OtlpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            service_name: "event-service".to_string(),
+        }
+    }
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        Self {
+            swagger_ui: true,
+            metrics: true,
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn listen_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    pub fn shutdown_delay(&self) -> Duration {
+        Duration::from_secs(self.shutdown_delay_secs)
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout_secs)
+    }
+
+    pub fn base_url_resolver(&self) -> Result<BaseUrlResolver, String> {
+        if self.base_url_from_request {
+            BaseUrlResolver::from_request(&self.base_url, &self.trusted_proxies)
+        } else {
+            Ok(BaseUrlResolver::fixed(&self.base_url))
+        }
+    }
+}
+
+impl DatabaseSettings {
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_secs)
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_secs > 0).then(|| Duration::from_secs(self.idle_timeout_secs))
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = std::env::var("CONFIG_FILE").ok();
+        let file_source = match &file {
+            // a file that was asked for by name has to exist
+            Some(path) => File::with_name(path).required(true),
+            None => File::with_name(DEFAULT_CONFIG_FILE).required(false),
+        };
+
+        let mut builder = Config::builder().add_source(file_source).add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("__")
+                .separator("__")
+                .try_parsing(true)
+                .list_separator(",")
+                .with_list_parse_key("server.trusted_proxies"),
+        );
+
+        for (var, key) in FLAT_ENV {
+            builder = builder.set_override_option(*key, std::env::var(var).ok())?;
+        }
+
+        builder.build()?.try_deserialize()
+    }
+
+    // every problem at once, so a broken deployment can be fixed in one go
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if self.database.url.is_empty() {
+            problems.push("database.url is required (DATABASE_URL)".to_string());
+        }
+        if self.auth.jwt_secret.is_empty() {
+            problems.push("auth.jwt_secret is required (JWT_SECRET)".to_string());
+        }
+        if self.tickets.signing_key.is_empty() {
+            problems.push("tickets.signing_key is required (TICKET_SIGNING_KEY)".to_string());
+        }
+
+        let base_url = &self.server.base_url;
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            problems.push(format!(
+                "server.base_url must be an http(s) URL, got `{}`",
+                base_url
+            ));
+        }
+        if base_url.ends_with('/') {
+            problems.push("server.base_url must not end with `/`".to_string());
+        }
+        if let Err(e) = self.server.base_url_resolver() {
+            problems.push(format!("server.trusted_proxies: {}", e));
+        }
+
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            problems.push(format!(
+                "database.min_connections ({}) can't exceed database.max_connections ({})",
+                self.database.min_connections, self.database.max_connections
+            ));
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            problems.push("database.acquire_timeout_secs must be at least 1".to_string());
+        }
+
+        if let Err(e) =
+            TicketCodeGenerator::new(&self.tickets.code_prefix, self.tickets.code_length)
+        {
+            problems.push(format!("tickets: {}", e));
+        }
+        if self.tickets.token_ttl_secs <= 0 {
+            problems.push("tickets.token_ttl_secs must be positive".to_string());
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.log.level) {
+            problems.push(format!(
+                "log.level `{}` is not a valid filter: {}",
+                self.log.level, e
+            ));
+        }
+
+        if self.otlp.enabled {
+            let endpoint = &self.otlp.endpoint;
+            if !(endpoint.starts_with("http://") || endpoint.starts_with("https://")) {
+                problems.push(format!(
+                    "otlp.endpoint must be an http(s) URL, got `{}`",
+                    endpoint
+                ));
+            }
+            if self.otlp.service_name.is_empty() {
+                problems.push("otlp.service_name can't be empty".to_string());
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use tokio::sync::watch;
+
+// flips once when the process is asked to stop. readiness reports it so
+// the orchestrator stops routing new traffic while requests drain
+pub struct ShutdownState {
+    draining: watch::Sender<bool>,
+}
+
+impl Default for ShutdownState {
+    fn default() -> Self {
+        Self {
+            draining: watch::Sender::new(false),
+        }
+    }
+}
+
+impl ShutdownState {
+    pub fn begin(&self) {
+        self.draining.send_replace(true);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    // resolves once `begin` was called, also when it happened before
+    pub async fn begun(&self) {
+        let mut draining = self.draining.subscribe();
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+}
+
+// SIGINT (ctrl-c) or SIGTERM, the one orchestrators send on redeploys
+pub async fn signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
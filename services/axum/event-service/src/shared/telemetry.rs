@@ -0,0 +1,78 @@
+use crate::shared::settings::{LogFormat, Settings};
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+// keeps the exporter alive, `shutdown` flushes the spans still buffered
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+// logs always go to stdout, compact or as JSON. spans are also exported over
+// OTLP/HTTP when `otlp.enabled` is set
+pub fn init(settings: &Settings) -> Result<Telemetry, ExporterBuildError> {
+    // incoming `traceparent` headers are read with this, see `request_span`
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let provider = if settings.otlp.enabled {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&settings.otlp.endpoint)
+            .build()?;
+
+        Some(
+            SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(
+                    Resource::builder()
+                        .with_service_name(settings.otlp.service_name.clone())
+                        .build(),
+                )
+                .build(),
+        )
+    } else {
+        None
+    };
+
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("event-service"))
+    });
+
+    // validated with the rest of the settings
+    let filter = EnvFilter::try_new(&settings.log.level).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    // json events carry the span list, so the request id lands on every line
+    let fmt_layer = match settings.log.format {
+        LogFormat::Compact => tracing_subscriber::fmt::layer().compact().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(otel_layer)
+        .init();
+
+    Ok(Telemetry { provider })
+}
@@ -0,0 +1,221 @@
+use rand::Rng;
+
+// Crockford base32, without I, L, O and U so codes survive being read out loud
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+pub const DEFAULT_PREFIX: &str = "BLT";
+pub const DEFAULT_RANDOM_LENGTH: usize = 12;
+
+// BILETE.COD is a VARCHAR(50)
+const MAX_CODE_LENGTH: usize = 50;
+
+// codes look like `BLT-7QK2M9XH4D1RZ`: a prefix, a random part
+// and one Luhn mod 32 check character that catches typos and swaps of
+// neighbouring characters, except `0Z` <-> `Z0` like Luhn's own `09` <-> `90`
+pub struct TicketCodeGenerator {
+    prefix: String,
+    random_length: usize,
+}
+
+impl TicketCodeGenerator {
+    pub fn new(prefix: impl Into<String>, random_length: usize) -> Result<Self, String> {
+        let prefix = prefix.into();
+
+        if !prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Ticket code prefix must be alphanumeric".into());
+        }
+        if random_length < 8 {
+            return Err("Ticket codes need at least 8 random characters".into());
+        }
+        // prefix + '-' + random part + check character
+        if prefix.len() + random_length + 2 > MAX_CODE_LENGTH {
+            return Err(format!(
+                "Ticket codes can't be longer than {} characters",
+                MAX_CODE_LENGTH
+            ));
+        }
+
+        Ok(Self {
+            prefix,
+            random_length,
+        })
+    }
+
+    pub fn generate(&self) -> String {
+        let mut rng = rand::rng();
+        let mut body: Vec<u8> = (0..self.random_length)
+            .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())])
+            .collect();
+
+        body.push(check_character(&body));
+
+        // only ALPHABET bytes were pushed, so this is always valid ASCII
+        let body = String::from_utf8(body).unwrap_or_default();
+
+        if self.prefix.is_empty() {
+            body
+        } else {
+            format!("{}-{}", self.prefix, body)
+        }
+    }
+
+    pub fn generate_many(&self, count: usize) -> Vec<String> {
+        (0..count).map(|_| self.generate()).collect()
+    }
+
+    // a code shaped like one of ours whose check character doesn't match
+    // was mistyped. codes chosen by clients rarely have that shape, they are
+    // looked up as they are
+    pub fn is_mistyped(&self, code: &str) -> bool {
+        let body = if self.prefix.is_empty() {
+            Some(code)
+        } else {
+            code.strip_prefix(self.prefix.as_str())
+                .and_then(|rest| rest.strip_prefix('-'))
+        };
+
+        let shaped = body.is_some_and(|body| {
+            body.len() == self.random_length + 1 && body.bytes().all(|b| b.is_ascii_alphanumeric())
+        });
+
+        shaped && !self.verify(code)
+    }
+
+    pub fn verify(&self, code: &str) -> bool {
+        let body = if self.prefix.is_empty() {
+            code
+        } else {
+            match code
+                .strip_prefix(self.prefix.as_str())
+                .and_then(|rest| rest.strip_prefix('-'))
+            {
+                Some(body) => body,
+                None => return false,
+            }
+        };
+
+        let bytes = body.as_bytes();
+        match bytes.split_last() {
+            Some((check, random)) if random.len() == self.random_length => {
+                random.iter().all(|b| ALPHABET.contains(b)) && check_character(random) == *check
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for TicketCodeGenerator {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_PREFIX.to_string(),
+            random_length: DEFAULT_RANDOM_LENGTH,
+        }
+    }
+}
+
+fn code_point(byte: u8) -> usize {
+    ALPHABET.iter().position(|&c| c == byte).unwrap_or(0)
+}
+
+// Luhn mod N over the base32 alphabet
+fn check_character(input: &[u8]) -> u8 {
+    let n = ALPHABET.len();
+    let mut factor = 2;
+    let mut sum = 0;
+
+    for &byte in input.iter().rev() {
+        let addend = factor * code_point(byte);
+        sum += addend / n + addend % n;
+        factor = if factor == 2 { 1 } else { 2 };
+    }
+
+    ALPHABET[(n - sum % n) % n]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator() -> TicketCodeGenerator {
+        TicketCodeGenerator::new("TKT", 10).unwrap()
+    }
+
+    #[test]
+    fn check_character_is_luhn_mod_32() {
+        // doubled from the right: 2*1 + 0 = 2, 32 - 2 = 30 -> Y
+        assert_eq!(check_character(b"01"), b'Y');
+        assert_eq!(check_character(b""), b'0');
+        // 2*16 = 32 folds to 1 + 0 = 1, 32 - 1 = 31 -> Z
+        assert_eq!(check_character(b"G"), b'Z');
+    }
+
+    #[test]
+    fn generated_codes_verify() {
+        let generator = generator();
+        for code in generator.generate_many(100) {
+            assert!(code.starts_with("TKT-"), "{}", code);
+            assert_eq!(code.len(), "TKT-".len() + 11);
+            assert!(generator.verify(&code), "{}", code);
+            assert!(!generator.is_mistyped(&code), "{}", code);
+        }
+
+        let bare = TicketCodeGenerator::new("", 8).unwrap();
+        let code = bare.generate();
+        assert_eq!(code.len(), 9);
+        assert!(bare.verify(&code));
+    }
+
+    #[test]
+    fn a_changed_character_is_caught() {
+        let generator = generator();
+        for code in generator.generate_many(50) {
+            let mut bytes = code.clone().into_bytes();
+            for i in "TKT-".len()..bytes.len() {
+                let original = bytes[i];
+                for &other in ALPHABET.iter().filter(|&&c| c != original) {
+                    bytes[i] = other;
+                    let typo = String::from_utf8(bytes.clone()).unwrap();
+                    assert!(!generator.verify(&typo), "{} -> {}", code, typo);
+                    assert!(generator.is_mistyped(&typo), "{} -> {}", code, typo);
+                }
+                bytes[i] = original;
+            }
+        }
+    }
+
+    #[test]
+    fn swapped_neighbours_are_caught() {
+        let generator = generator();
+        for code in generator.generate_many(200) {
+            let mut bytes = code.clone().into_bytes();
+            for i in "TKT-".len()..bytes.len() - 1 {
+                let pair = [bytes[i], bytes[i + 1]];
+                if pair[0] == pair[1] || pair == *b"0Z" || pair == *b"Z0" {
+                    continue;
+                }
+                bytes.swap(i, i + 1);
+                let typo = String::from_utf8(bytes.clone()).unwrap();
+                assert!(!generator.verify(&typo), "{} -> {}", code, typo);
+                bytes.swap(i, i + 1);
+            }
+        }
+    }
+
+    // the one swap Luhn mod N can't see, the first and last code points
+    #[test]
+    fn swapping_zero_and_z_goes_unnoticed() {
+        assert_eq!(check_character(b"70Z"), check_character(b"7Z0"));
+    }
+
+    #[test]
+    fn other_codes_are_not_mistyped() {
+        let generator = generator();
+        let code = generator.generate();
+
+        assert!(!generator.verify("TKT-A"));
+        assert!(!generator.is_mistyped("TKT-A"));
+        assert!(!generator.is_mistyped("CONCERT-2030-VIP"));
+        assert!(!generator.is_mistyped(&code.replacen("TKT", "BLT", 1)));
+        assert!(!generator.verify(&code.replacen("TKT", "BLT", 1)));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::models::ticket::Ticket;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use time::{Duration, OffsetDateTime};
+use utoipa::ToSchema;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const VERSION: &str = "v1";
+
+pub const DEFAULT_TTL_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TicketTokenClaims {
+    pub cod: String,
+    #[serde(rename = "pachetid")]
+    pub id_pachet: Option<i32>,
+    #[serde(rename = "evenimentid")]
+    pub id_event: Option<i32>,
+    pub exp: i64,
+}
+
+#[derive(Debug)]
+pub enum TicketTokenError {
+    Malformed,
+    BadSignature,
+    Expired,
+}
+
+// a token is `v1.<claims>.<signature>`, both parts base64url without padding,
+// with the signature being HMAC-SHA256 over `v1.<claims>`. anyone holding the
+// signing key (e.g. a door scanner) can check it without asking the service
+pub struct TicketSigner {
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl TicketSigner {
+    pub fn new(key: impl Into<Vec<u8>>, ttl_secs: i64) -> Self {
+        Self {
+            key: key.into(),
+            ttl: Duration::seconds(ttl_secs),
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        // HMAC accepts keys of any length, so this never fails
+        HmacSha256::new_from_slice(&self.key).expect("HMAC can take a key of any size")
+    }
+
+    pub fn sign(&self, ticket: &Ticket) -> String {
+        let claims = TicketTokenClaims {
+            cod: ticket.cod.clone(),
+            id_pachet: ticket.id_pachet,
+            id_event: ticket.id_event,
+            exp: (OffsetDateTime::now_utc() + self.ttl).unix_timestamp(),
+        };
+
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap_or_default());
+        let signed_part = format!("{}.{}", VERSION, payload);
+
+        let mut mac = self.mac();
+        mac.update(signed_part.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+        format!("{}.{}", signed_part, signature)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<TicketTokenClaims, TicketTokenError> {
+        let (signed_part, signature) = token.rsplit_once('.').ok_or(TicketTokenError::Malformed)?;
+        let (version, payload) = signed_part
+            .split_once('.')
+            .ok_or(TicketTokenError::Malformed)?;
+
+        if version != VERSION {
+            return Err(TicketTokenError::Malformed);
+        }
+
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| TicketTokenError::Malformed)?;
+
+        let mut mac = self.mac();
+        mac.update(signed_part.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| TicketTokenError::BadSignature)?;
+
+        let claims: TicketTokenClaims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(TicketTokenError::Malformed)?;
+
+        if claims.exp < OffsetDateTime::now_utc().unix_timestamp() {
+            return Err(TicketTokenError::Expired);
+        }
+
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket() -> Ticket {
+        Ticket {
+            cod: "TKT-A".to_string(),
+            id_pachet: None,
+            id_event: Some(7),
+            checked_in_at: None,
+        }
+    }
+
+    // signs any `signed_part` the way `sign` does, to build broken tokens
+    fn signed(signer: &TicketSigner, signed_part: &str) -> String {
+        let mut mac = signer.mac();
+        mac.update(signed_part.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", signed_part, signature)
+    }
+
+    #[test]
+    fn a_signed_ticket_verifies() {
+        let signer = TicketSigner::new("key", 60);
+        let token = signer.sign(&ticket());
+        assert!(token.starts_with("v1."), "{}", token);
+        assert_eq!(token.split('.').count(), 3);
+
+        let claims = signer.verify(&token).unwrap();
+        assert_eq!(claims.cod, "TKT-A");
+        assert_eq!(claims.id_pachet, None);
+        assert_eq!(claims.id_event, Some(7));
+        assert!(claims.exp > OffsetDateTime::now_utc().unix_timestamp());
+    }
+
+    #[test]
+    fn a_changed_payload_is_caught() {
+        let signer = TicketSigner::new("key", 60);
+        let token = signer.sign(&ticket());
+        let (_, signature) = token.rsplit_once('.').unwrap();
+
+        let mut claims = signer.verify(&token).unwrap();
+        claims.cod = "TKT-B".to_string();
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap());
+        let forged = format!("v1.{}.{}", payload, signature);
+
+        assert!(matches!(
+            signer.verify(&forged),
+            Err(TicketTokenError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn a_changed_signature_is_caught() {
+        let signer = TicketSigner::new("key", 60);
+        let token = signer.sign(&ticket());
+        let (signed_part, signature) = token.rsplit_once('.').unwrap();
+
+        let first = if signature.starts_with('A') { "B" } else { "A" };
+        let forged = format!("{}.{}{}", signed_part, first, &signature[1..]);
+        assert!(matches!(
+            signer.verify(&forged),
+            Err(TicketTokenError::BadSignature)
+        ));
+
+        let cut = format!("{}.{}", signed_part, &signature[..8]);
+        assert!(matches!(
+            signer.verify(&cut),
+            Err(TicketTokenError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn another_key_is_refused() {
+        let token = TicketSigner::new("key", 60).sign(&ticket());
+        assert!(matches!(
+            TicketSigner::new("other key", 60).verify(&token),
+            Err(TicketTokenError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn an_expired_token_is_refused() {
+        let signer = TicketSigner::new("key", -1);
+        let token = signer.sign(&ticket());
+        assert!(matches!(
+            signer.verify(&token),
+            Err(TicketTokenError::Expired)
+        ));
+    }
+
+    #[test]
+    fn malformed_tokens_are_refused() {
+        let signer = TicketSigner::new("key", 60);
+        let token = signer.sign(&ticket());
+        let (_, payload_and_signature) = token.split_once('.').unwrap();
+        let (payload, _) = payload_and_signature.split_once('.').unwrap();
+        let not_json = URL_SAFE_NO_PAD.encode(b"not json");
+
+        for malformed in [
+            String::new(),
+            "v1".to_string(),
+            format!("v1.{}", payload),
+            format!("v2.{}", payload_and_signature),
+            payload_and_signature.to_string(),
+            format!("v1.{}.not base64!", payload),
+            // signed correctly, so only the payload itself is wrong
+            signed(&signer, &format!("v2.{}", payload)),
+            signed(&signer, &format!("v1.{}", not_json)),
+            signed(&signer, "v1.not base64!"),
+        ] {
+            assert!(
+                matches!(signer.verify(&malformed), Err(TicketTokenError::Malformed)),
+                "{}",
+                malformed
+            );
+        }
+    }
+}
@@ -0,0 +1,938 @@
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode, header};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use event_service::AppState;
+use event_service::handlers;
+use event_service::repositories::health_repo::HealthRepo;
+use event_service::repositories::in_memory::InMemoryRepo;
+use event_service::shared::auth::JwtVerifier;
+use event_service::shared::base_url::BaseUrlResolver;
+use event_service::shared::ticket_code::TicketCodeGenerator;
+use event_service::shared::ticket_token::TicketSigner;
+use jsonwebtoken::{EncodingKey, Header, encode};
+use serde_json::{Value, json};
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tower::ServiceExt;
+
+const PREFIX: &str = "/api/event-manager";
+const BASE_URL: &str = "http://localhost:8001/api/event-manager";
+
+const SECRET: &[u8] = b"secret";
+
+// (sub, rol) of the callers used below
+const ADMIN: (i32, &str) = (1, "admin");
+const OWNER: (i32, &str) = (2, "owner-event");
+const OTHER_OWNER: (i32, &str) = (3, "owner-event");
+const CLIENT: (i32, &str) = (4, "client");
+
+// every repository is the same in-memory store, so the foreign keys and
+// cascades between them hold like in postgres
+fn app() -> Router {
+    let repo = Arc::new(InMemoryRepo::new());
+
+    // only the health probes use the pool, none of these tests call them
+    let pool = PgPoolOptions::new()
+        .connect_lazy("postgres://localhost:1/events")
+        .unwrap();
+
+    let state = Arc::new(AppState {
+        event_repo: repo.clone(),
+        event_packet_repo: repo.clone(),
+        ticket_repo: repo.clone(),
+        join_repo: repo,
+        health_repo: Arc::new(HealthRepo::new(pool)),
+        jwt: Arc::new(JwtVerifier::new(SECRET)),
+        ticket_codes: Arc::new(TicketCodeGenerator::new("TKT", 10).unwrap()),
+        ticket_signer: Arc::new(TicketSigner::new("key", 60)),
+        base_url: Arc::new(BaseUrlResolver::fixed(BASE_URL)),
+        shutdown: Arc::default(),
+    });
+
+    Router::new()
+        .nest(PREFIX, handlers::api_router(state.clone()))
+        .with_state(state)
+}
+
+fn token((sub, rol): (i32, &str)) -> String {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let claims = json!({
+        "sub": sub,
+        "rol": rol,
+        "typ": "access",
+        "jti": format!("handlers-test-{}", sub),
+        "iat": now,
+        "exp": now + 600,
+    });
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+async fn send(
+    app: &Router,
+    caller: Option<(i32, &str)>,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(format!("{}{}", PREFIX, path));
+    if let Some(caller) = caller {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token(caller)));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+    (status, value)
+}
+
+async fn get(app: &Router, path: &str) -> (StatusCode, Value) {
+    send(app, Some(CLIENT), Method::GET, path, None).await
+}
+
+async fn post(app: &Router, caller: (i32, &str), path: &str, body: Value) -> (StatusCode, Value) {
+    send(app, Some(caller), Method::POST, path, Some(body)).await
+}
+
+async fn put(app: &Router, caller: (i32, &str), path: &str, body: Value) -> (StatusCode, Value) {
+    send(app, Some(caller), Method::PUT, path, Some(body)).await
+}
+
+async fn delete(app: &Router, caller: (i32, &str), path: &str) -> (StatusCode, Value) {
+    send(app, Some(caller), Method::DELETE, path, None).await
+}
+
+async fn create_event(app: &Router, nume: &str, locuri: Option<i32>) -> i32 {
+    let (status, body) = post(
+        app,
+        OWNER,
+        "/events",
+        json!({
+            "nume": nume,
+            "locatie": "Iași",
+            "numarlocuri": locuri,
+            "data_inceput": "2030-06-01T18:00:00Z",
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    body["id"].as_i64().unwrap() as i32
+}
+
+async fn create_packet(app: &Router, nume: &str, locuri: Option<i32>) -> i32 {
+    let (status, body) = post(
+        app,
+        OWNER,
+        "/event-packets",
+        json!({ "nume": nume, "descriere": "Weekend rock", "numarlocuri": locuri }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    body["id"].as_i64().unwrap() as i32
+}
+
+// the nested routes take the target from the path, the body still has to name one
+async fn issue_ticket(app: &Router, path: &str, target: Value) -> String {
+    let (status, body) = post(app, OWNER, path, target).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    body["cod"].as_str().unwrap().to_string()
+}
+
+fn assert_problem(response: &(StatusCode, Value), status: StatusCode, code: &str) {
+    assert_eq!(response.0, status, "{}", response.1);
+    assert_eq!(response.1["code"], code, "{}", response.1);
+}
+
+fn embedded<'a>(body: &'a Value, rel: &str) -> &'a Vec<Value> {
+    body["_embedded"][rel].as_array().unwrap()
+}
+
+#[tokio::test]
+async fn events_are_created_read_updated_and_deleted() {
+    let app = app();
+    let id = create_event(&app, "Concert", Some(100)).await;
+
+    let (status, event) = get(&app, &format!("/events/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(event["nume"], "Concert");
+    assert_eq!(event["id_owner"], OWNER.0);
+    assert_eq!(event["locuri_disponibile"], 100);
+
+    // fields left out keep their value
+    let (status, event) = put(
+        &app,
+        OWNER,
+        &format!("/events/{}", id),
+        json!({ "nume": "Concert de vara", "numarlocuri": 50 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", event);
+    assert_eq!(event["nume"], "Concert de vara");
+    assert_eq!(event["locatie"], "Iași");
+    assert_eq!(event["numarlocuri"], 50);
+    assert_eq!(event["data_inceput"], "2030-06-01T18:00:00Z");
+
+    let (status, _) = delete(&app, OWNER, &format!("/events/{}", id)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    assert_problem(
+        &get(&app, &format!("/events/{}", id)).await,
+        StatusCode::NOT_FOUND,
+        "event-not-found",
+    );
+}
+
+#[tokio::test]
+async fn names_are_unique() {
+    let app = app();
+    create_event(&app, "Concert", None).await;
+    let other = create_event(&app, "Teatru", None).await;
+
+    let body = json!({ "nume": "Concert", "data_inceput": "2030-06-01T18:00:00Z" });
+    assert_problem(
+        &post(&app, OWNER, "/events", body).await,
+        StatusCode::CONFLICT,
+        "event-duplicate",
+    );
+    assert_problem(
+        &put(
+            &app,
+            OWNER,
+            &format!("/events/{}", other),
+            json!({ "nume": "Concert" }),
+        )
+        .await,
+        StatusCode::CONFLICT,
+        "event-duplicate",
+    );
+
+    create_packet(&app, "Festival", None).await;
+    assert_problem(
+        &post(&app, OWNER, "/event-packets", json!({ "nume": "Festival" })).await,
+        StatusCode::CONFLICT,
+        "packet-duplicate",
+    );
+}
+
+#[tokio::test]
+async fn a_schedule_must_end_after_it_starts() {
+    let app = app();
+    let id = create_event(&app, "Concert", None).await;
+
+    // valid on its own, the start it is compared with is the stored one
+    let response = put(
+        &app,
+        OWNER,
+        &format!("/events/{}", id),
+        json!({ "nume": "Concert", "data_sfarsit": "2030-06-01T17:00:00Z" }),
+    )
+    .await;
+    assert_problem(
+        &response,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "event-invalid-schedule",
+    );
+}
+
+#[tokio::test]
+async fn only_owners_and_admins_change_a_resource() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+    let packet = create_packet(&app, "Festival", None).await;
+
+    assert_problem(
+        &put(
+            &app,
+            OTHER_OWNER,
+            &format!("/events/{}", event),
+            json!({ "nume": "Altul" }),
+        )
+        .await,
+        StatusCode::FORBIDDEN,
+        "event-forbidden",
+    );
+    assert_problem(
+        &delete(&app, OTHER_OWNER, &format!("/event-packets/{}", packet)).await,
+        StatusCode::FORBIDDEN,
+        "packet-forbidden",
+    );
+
+    // clients may only read
+    let (status, _) = post(&app, CLIENT, "/events", json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, None, Method::GET, "/events", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = delete(&app, ADMIN, &format!("/events/{}", event)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_problem(
+        &delete(&app, ADMIN, &format!("/events/{}", event)).await,
+        StatusCode::NOT_FOUND,
+        "event-not-found",
+    );
+}
+
+#[tokio::test]
+async fn only_owners_and_admins_manage_tickets_and_joins() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+    let packet = create_packet(&app, "Festival", None).await;
+    let tickets = format!("/events/{}/tickets", event);
+    let cod = issue_ticket(&app, &tickets, json!({ "evenimentid": event })).await;
+
+    let forbidden = [
+        post(&app, OTHER_OWNER, &tickets, json!({ "evenimentid": event })).await,
+        post(
+            &app,
+            OTHER_OWNER,
+            &format!("{}/bulk", tickets),
+            json!({ "count": 2 }),
+        )
+        .await,
+        post(
+            &app,
+            OTHER_OWNER,
+            "/tickets",
+            json!({ "evenimentid": event }),
+        )
+        .await,
+        put(
+            &app,
+            OTHER_OWNER,
+            &format!("/tickets/{}", cod),
+            json!({ "pachetid": packet }),
+        )
+        .await,
+        delete(&app, OTHER_OWNER, &format!("{}/{}", tickets, cod)).await,
+        delete(&app, OTHER_OWNER, &format!("/tickets/{}", cod)).await,
+        post(
+            &app,
+            OTHER_OWNER,
+            &format!("/tickets/{}/check-in", cod),
+            json!({}),
+        )
+        .await,
+    ];
+    for response in &forbidden {
+        assert_problem(response, StatusCode::FORBIDDEN, "ticket-forbidden");
+    }
+
+    // the join routes take the other side from the body, see `join` below
+    assert_eq!(event, packet);
+    assert_problem(
+        &post(
+            &app,
+            OTHER_OWNER,
+            &format!("/events/{}/event-packets", event),
+            json!({ "evenimentid": event }),
+        )
+        .await,
+        StatusCode::FORBIDDEN,
+        "join-forbidden",
+    );
+
+    let (status, _) = post(
+        &app,
+        ADMIN,
+        &format!("/tickets/{}/check-in", cod),
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = delete(&app, ADMIN, &format!("/tickets/{}", cod)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = join(&app, event, packet).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn tickets_stop_at_the_capacity() {
+    let app = app();
+    let event = create_event(&app, "Concert", Some(3)).await;
+    let tickets = format!("/events/{}/tickets", event);
+
+    // a batch is issued whole or not at all
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            &format!("{}/bulk", tickets),
+            json!({ "count": 4 }),
+        )
+        .await,
+        StatusCode::CONFLICT,
+        "ticket-sold-out",
+    );
+    let (_, list) = get(&app, &tickets).await;
+    assert_eq!(list["total"], 0);
+
+    let (status, batch) = post(
+        &app,
+        OWNER,
+        &format!("{}/bulk", tickets),
+        json!({ "count": 2 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", batch);
+    assert_eq!(embedded(&batch, "tickets").len(), 2);
+
+    issue_ticket(&app, &tickets, json!({ "evenimentid": event })).await;
+    assert_problem(
+        &post(&app, OWNER, &tickets, json!({ "evenimentid": event })).await,
+        StatusCode::CONFLICT,
+        "ticket-sold-out",
+    );
+
+    let (_, event) = get(&app, &format!("/events/{}", event)).await;
+    assert_eq!(event["locuri_disponibile"], 0);
+}
+
+#[tokio::test]
+async fn tickets_need_exactly_one_existing_target() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+    let packet = create_packet(&app, "Festival", None).await;
+
+    let (status, _) = post(
+        &app,
+        OWNER,
+        "/tickets",
+        json!({ "cod": "TKT-A", "evenimentid": event }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            "/tickets",
+            json!({ "cod": "TKT-A", "pachetid": packet }),
+        )
+        .await,
+        StatusCode::CONFLICT,
+        "ticket-duplicate",
+    );
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            "/tickets",
+            json!({ "cod": "TKT-B", "evenimentid": 999 }),
+        )
+        .await,
+        StatusCode::BAD_REQUEST,
+        "ticket-invalid-reference",
+    );
+
+    // moving an event ticket to no packet would leave it without a target,
+    // which `chk_bilet_exclusiv` rejects
+    assert_problem(
+        &put(
+            &app,
+            OWNER,
+            &format!("/events/{}/tickets/TKT-A", event),
+            json!({ "evenimentid": event }),
+        )
+        .await,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ticket-target-conflict",
+    );
+
+    let (status, ticket) = put(
+        &app,
+        OWNER,
+        &format!("/events/{}/tickets/TKT-A", event),
+        json!({ "pachetid": packet }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", ticket);
+    assert_eq!(ticket["pachetid"], packet);
+    assert_eq!(ticket["evenimentid"], Value::Null);
+
+    let (status, _) = get(&app, &format!("/event-packets/{}/tickets/TKT-A", packet)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_problem(
+        &get(&app, &format!("/events/{}/tickets/TKT-A", event)).await,
+        StatusCode::NOT_FOUND,
+        "ticket-not-found",
+    );
+}
+
+// the token check lives outside /tickets, so no ticket code is shadowed by it
+#[tokio::test]
+async fn a_ticket_can_be_coded_verify() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+
+    let cod = issue_ticket(
+        &app,
+        "/tickets",
+        json!({ "cod": "verify", "evenimentid": event }),
+    )
+    .await;
+    assert_eq!(cod, "verify");
+    let (status, ticket) = get(&app, "/tickets/verify").await;
+    assert_eq!(status, StatusCode::OK, "{}", ticket);
+    assert_eq!(ticket["cod"], "verify");
+
+    let token = TicketSigner::new("key", 60).sign(&serde_json::from_value(ticket).unwrap());
+    let (status, claims) = post(
+        &app,
+        OWNER,
+        "/ticket-tokens/verify",
+        json!({ "token": token }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", claims);
+    assert_eq!(claims["cod"], "verify");
+
+    let (status, _) = delete(&app, OWNER, "/tickets/verify").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn deleting_a_target_with_tickets_fails_like_in_postgres() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+    let cod = issue_ticket(
+        &app,
+        &format!("/events/{}/tickets", event),
+        json!({ "evenimentid": event }),
+    )
+    .await;
+
+    // ON DELETE SET NULL would leave the ticket without a target, postgres
+    // refuses that with a check violation
+    assert_problem(
+        &delete(&app, OWNER, &format!("/events/{}", event)).await,
+        StatusCode::CONFLICT,
+        "event-has-tickets",
+    );
+    let (status, _) = get(&app, &format!("/events/{}", event)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = delete(&app, OWNER, &format!("/events/{}/tickets/{}", event, cod)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = delete(&app, OWNER, &format!("/events/{}", event)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let packet = create_packet(&app, "Festival", None).await;
+    issue_ticket(
+        &app,
+        &format!("/event-packets/{}/tickets", packet),
+        json!({ "pachetid": packet }),
+    )
+    .await;
+    assert_problem(
+        &delete(&app, OWNER, &format!("/event-packets/{}", packet)).await,
+        StatusCode::CONFLICT,
+        "packet-has-tickets",
+    );
+}
+
+// the join routes hand their path id to the repository as the other side of
+// the relation, so these tests join an event and a packet that share an id
+async fn join(app: &Router, event: i32, packet: i32) -> (StatusCode, Value) {
+    assert_eq!(event, packet);
+    post(
+        app,
+        OWNER,
+        &format!("/events/{}/event-packets", event),
+        json!({ "evenimentid": event }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn packets_and_events_are_joined_until_one_is_deleted() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+    let packet = create_packet(&app, "Festival", None).await;
+
+    let (status, relation) = join(&app, event, packet).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", relation);
+    assert_eq!(
+        relation,
+        json!({ "pachetid": packet, "evenimentid": event })
+    );
+
+    assert_problem(
+        &join(&app, event, packet).await,
+        StatusCode::CONFLICT,
+        "join-duplicate",
+    );
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            &format!("/event-packets/{}/events", event),
+            json!({ "pachetid": 999 }),
+        )
+        .await,
+        StatusCode::BAD_REQUEST,
+        "join-invalid-reference",
+    );
+
+    let (_, events) = get(&app, &format!("/event-packets/{}/events", packet)).await;
+    assert_eq!(embedded(&events, "events")[0]["id"], event);
+
+    // JOIN_PE cascades
+    let (status, _) = delete(&app, OWNER, &format!("/event-packets/{}", packet)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, packets) = get(&app, &format!("/events/{}/event-packets", event)).await;
+    assert_eq!(packets["total"], 0);
+}
+
+#[tokio::test]
+async fn packet_tickets_take_seats_at_their_events() {
+    let app = app();
+    let event = create_event(&app, "Concert", Some(2)).await;
+    let packet = create_packet(&app, "Festival", None).await;
+    let event_tickets = format!("/events/{}/tickets", event);
+    let packet_tickets = format!("/event-packets/{}/tickets", packet);
+
+    // sold before the link, it still needs a seat at the event
+    issue_ticket(&app, &packet_tickets, json!({ "pachetid": packet })).await;
+    let (status, _) = join(&app, event, packet).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    issue_ticket(&app, &event_tickets, json!({ "evenimentid": event })).await;
+    assert_problem(
+        &post(&app, OWNER, &packet_tickets, json!({ "pachetid": packet })).await,
+        StatusCode::CONFLICT,
+        "ticket-sold-out",
+    );
+    assert_problem(
+        &post(&app, OWNER, &event_tickets, json!({ "evenimentid": event })).await,
+        StatusCode::CONFLICT,
+        "ticket-sold-out",
+    );
+
+    // an unlimited packet is limited by its events
+    let (_, concert) = get(&app, &format!("/events/{}", event)).await;
+    assert_eq!(concert["locuri_disponibile"], 0);
+    let (_, festival) = get(&app, &format!("/event-packets/{}", packet)).await;
+    assert_eq!(festival["locuri_disponibile"], 0);
+    let (_, events) = get(&app, "/events?available_tickets=1").await;
+    assert_eq!(events["total"], 0);
+    let (_, packets) = get(&app, "/event-packets?available_tickets=1").await;
+    assert_eq!(packets["total"], 0);
+
+    // a packet with more tickets than the event has seats can't include it
+    let small = create_event(&app, "Teatru", Some(1)).await;
+    let busy = create_packet(&app, "Weekend", None).await;
+    let busy_tickets = format!("/event-packets/{}/tickets", busy);
+    issue_ticket(&app, &busy_tickets, json!({ "pachetid": busy })).await;
+    issue_ticket(&app, &busy_tickets, json!({ "pachetid": busy })).await;
+    assert_problem(
+        &join(&app, small, busy).await,
+        StatusCode::CONFLICT,
+        "join-sold-out",
+    );
+    let (_, packets) = get(&app, &format!("/events/{}/event-packets", small)).await;
+    assert_eq!(packets["total"], 0);
+}
+
+#[tokio::test]
+async fn tickets_are_checked_in_once_per_event() {
+    let app = app();
+    let event = create_event(&app, "Concert", None).await;
+    let other_event = create_event(&app, "Teatru", None).await;
+    let packet = create_packet(&app, "Festival", None).await;
+    join(&app, event, packet).await;
+
+    let event_ticket = issue_ticket(
+        &app,
+        &format!("/events/{}/tickets", event),
+        json!({ "evenimentid": event }),
+    )
+    .await;
+    let packet_ticket = issue_ticket(
+        &app,
+        &format!("/event-packets/{}/tickets", packet),
+        json!({ "pachetid": packet }),
+    )
+    .await;
+    let check_in = |cod: &str| format!("/tickets/{}/check-in", cod);
+
+    // one wrong character is caught by the check character before any lookup
+    let last = event_ticket.chars().last().unwrap();
+    let typo = format!(
+        "{}{}",
+        &event_ticket[..event_ticket.len() - 1],
+        if last == 'A' { 'B' } else { 'A' }
+    );
+    assert_problem(
+        &post(&app, OWNER, &check_in(&typo), json!({})).await,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ticket-code-mistyped",
+    );
+    assert_problem(
+        &get(&app, &format!("/tickets/{}", typo)).await,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ticket-code-mistyped",
+    );
+
+    // nor can a client pick it, the ticket couldn't be looked up afterwards
+    for (path, body) in [
+        (
+            "/tickets".to_string(),
+            json!({ "cod": typo, "evenimentid": event }),
+        ),
+        (
+            format!("/events/{}/tickets", event),
+            json!({ "cod": typo, "evenimentid": event }),
+        ),
+        (
+            format!("/event-packets/{}/tickets", packet),
+            json!({ "cod": typo, "pachetid": packet }),
+        ),
+    ] {
+        assert_problem(
+            &post(&app, OWNER, &path, body).await,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "ticket-code-mistyped",
+        );
+    }
+
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            &check_in(&event_ticket),
+            json!({ "evenimentid": other_event }),
+        )
+        .await,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ticket-not-valid-for-event",
+    );
+    let (status, entry) = post(&app, OWNER, &check_in(&event_ticket), json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", entry);
+    assert_eq!(entry["evenimentid"], event);
+    assert_problem(
+        &post(&app, OWNER, &check_in(&event_ticket), json!({})).await,
+        StatusCode::CONFLICT,
+        "ticket-already-checked-in",
+    );
+
+    assert_problem(
+        &post(&app, OWNER, &check_in(&packet_ticket), json!({})).await,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ticket-event-required",
+    );
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            &check_in(&packet_ticket),
+            json!({ "evenimentid": other_event }),
+        )
+        .await,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ticket-not-valid-for-event",
+    );
+    let (status, _) = post(
+        &app,
+        OWNER,
+        &check_in(&packet_ticket),
+        json!({ "evenimentid": event }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_problem(
+        &post(
+            &app,
+            OWNER,
+            &check_in(&packet_ticket),
+            json!({ "evenimentid": event }),
+        )
+        .await,
+        StatusCode::CONFLICT,
+        "ticket-already-checked-in",
+    );
+
+    let (_, stats) = get(&app, &format!("/events/{}/check-in-stats", event)).await;
+    assert_eq!(stats["bilete_emise"], 2);
+    assert_eq!(stats["intrari"], 2);
+    assert_eq!(stats["ramase"], 0);
+
+    // INTRARI cascades with the ticket
+    let (status, _) = delete(&app, OWNER, &format!("/tickets/{}", packet_ticket)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, stats) = get(&app, &format!("/events/{}/check-in-stats", event)).await;
+    assert_eq!(stats["bilete_emise"], 1);
+    assert_eq!(stats["intrari"], 1);
+}
+
+#[tokio::test]
+async fn events_are_filtered_sorted_and_paged() {
+    let app = app();
+    for (nume, locuri) in [
+        ("Delta", 5),
+        ("Alfa", 1),
+        ("Echo", 3),
+        ("Charlie", 2),
+        ("Bravo", 4),
+    ] {
+        create_event(&app, nume, Some(locuri)).await;
+    }
+    let names = |body: &Value, rel: &str| {
+        embedded(body, rel)
+            .iter()
+            .map(|event| event["nume"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let (_, page) = get(&app, "/events?sort_by=name&page=2&items_per_page=2").await;
+    assert_eq!(names(&page, "events"), ["Charlie", "Delta"]);
+    assert_eq!(page["total"], 5);
+
+    // `unaccent(locatie) ILIKE 'iasi%'`
+    let (_, page) = get(&app, "/events?location=iasi&available_tickets=3").await;
+    assert_eq!(page["total"], 3);
+
+    // keyset pages follow each other without gaps
+    let mut seen = Vec::new();
+    let mut path = "/events?sort_by=seats&order=desc&limit=2".to_string();
+    loop {
+        let (status, page) = get(&app, &path).await;
+        assert_eq!(status, StatusCode::OK, "{}", page);
+        seen.extend(names(&page, "events"));
+        match page["next_cursor"].as_str() {
+            Some(cursor) => path = format!("/events?limit=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+    assert_eq!(seen, ["Delta", "Bravo", "Echo", "Charlie", "Alfa"]);
+
+    // hand-edited cursors whose keys don't fit the columns never reach the query
+    for forged in [
+        json!({ "o": "asc", "t": "TKT-A" }),
+        json!({ "s": "name", "o": "asc", "k": 7, "t": 1 }),
+        json!({ "s": "seats", "o": "asc", "t": 1 }),
+    ] {
+        let cursor = URL_SAFE_NO_PAD.encode(forged.to_string());
+        let response = get(&app, &format!("/events?cursor={}", cursor)).await;
+        assert_problem(
+            &response,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation-failed",
+        );
+        assert_eq!(response.1["errors"][0]["code"], "cursor_key_mismatch");
+    }
+}
+
+// the filters are sent back encoded in the links, so following one
+// repeats the same search
+#[tokio::test]
+async fn filtered_links_lead_back_to_the_same_page() {
+    let app = app();
+    create_event(&app, "Concert", None).await;
+    let (status, body) = post(
+        &app,
+        OWNER,
+        "/events",
+        json!({
+            "nume": "Teatru",
+            "locatie": "Piața Unirii, Iași",
+            "data_inceput": "2030-06-01T18:00:00Z",
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let (status, page) = get(
+        &app,
+        "/events?location=Pia%C8%9Ba+Unirii&from=2030-06-01T20:00:00%2B02:00&items_per_page=5",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", page);
+    assert_eq!(page["total"], 1);
+
+    let href = page["_links"]["self"]["href"].as_str().unwrap();
+    assert!(href.contains("location=Pia%C8%9Ba+Unirii&"), "{}", href);
+    assert!(
+        href.contains("from=2030-06-01T20%3A00%3A00%2B02%3A00&"),
+        "{}",
+        href
+    );
+    let event_href = embedded(&page, "events")[0]["_links"]["self"]["href"]
+        .as_str()
+        .unwrap();
+
+    let (status, again) = get(&app, href.strip_prefix(BASE_URL).unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "{}", again);
+    assert_eq!(again["total"], 1);
+    assert_eq!(embedded(&again, "events")[0]["nume"], "Teatru");
+    let (status, event) = get(&app, event_href.strip_prefix(BASE_URL).unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "{}", event);
+}
+
+#[tokio::test]
+async fn malformed_paths_and_queries_are_problems() {
+    let app = app();
+    create_event(&app, "Concert", Some(10)).await;
+
+    assert_problem(
+        &get(&app, "/events/abc").await,
+        StatusCode::BAD_REQUEST,
+        "invalid-path-parameter",
+    );
+    assert_problem(
+        &get(&app, "/event-packets/abc/events").await,
+        StatusCode::BAD_REQUEST,
+        "invalid-path-parameter",
+    );
+    assert_problem(
+        &get(&app, "/events?page=abc").await,
+        StatusCode::BAD_REQUEST,
+        "invalid-query",
+    );
+    assert_problem(
+        &get(&app, "/events?cursor=not-a-cursor").await,
+        StatusCode::BAD_REQUEST,
+        "invalid-query",
+    );
+
+    // a page far enough out would overflow the offset
+    for page in ["0", "1000001", "9223372036854775807"] {
+        let response = get(&app, &format!("/events?page={}", page)).await;
+        assert_problem(
+            &response,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation-failed",
+        );
+        assert_eq!(response.1["errors"][0]["code"], "page_range");
+    }
+    let (status, _) = get(&app, "/events?page=1000000&items_per_page=100").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let unknown = get(&app, "/events?bogus=1").await;
+    assert_problem(&unknown, StatusCode::BAD_REQUEST, "invalid-query");
+    assert_eq!(unknown.1["errors"][0]["field"], "bogus", "{}", unknown.1);
+    assert_eq!(
+        unknown.1["errors"][0]["code"], "unknown_field",
+        "{}",
+        unknown.1
+    );
+}
@@ -0,0 +1,351 @@
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode, header};
+use event_service::AppState;
+use event_service::handlers;
+use event_service::models::check_in::{CheckIn, CheckInStats};
+use event_service::models::event::{Event, EventQuery};
+use event_service::models::event_packets::{EventPacketQuery, EventPackets};
+use event_service::models::join_pe::AddPacketToEvent;
+use event_service::models::pagination::{Page, PaginationParams};
+use event_service::models::ticket::{CreateTicket, Ticket};
+use event_service::repositories::{
+    event_packets_repo::EventPacketRepo, event_repo::EventRepo, health_repo::HealthRepo,
+    join_pe_repo::JoinPeRepo, ticket_repo::TicketRepo,
+};
+use event_service::shared::auth::JwtVerifier;
+use event_service::shared::base_url::BaseUrlResolver;
+use event_service::shared::hypermedia::MediaType;
+use event_service::shared::links;
+use event_service::shared::routes::Route;
+use event_service::shared::ticket_code::TicketCodeGenerator;
+use event_service::shared::ticket_token::TicketSigner;
+use jsonwebtoken::{EncodingKey, Header, encode};
+use serde_json::{Value, json};
+use sqlx::postgres::PgPoolOptions;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tower::ServiceExt;
+
+const PREFIX: &str = "/api/event-manager";
+const BASE_URL: &str = "http://localhost:8001/api/event-manager";
+
+const SECRET: &[u8] = b"secret";
+
+// nothing listens on the database port, handlers that reach the database fail
+// fast with a 500 which still proves the route matched
+fn app() -> Router {
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(20))
+        .connect_lazy("postgres://localhost:1/events")
+        .unwrap();
+
+    let state = Arc::new(AppState {
+        event_repo: Arc::new(EventRepo::new(pool.clone())),
+        event_packet_repo: Arc::new(EventPacketRepo::new(pool.clone())),
+        ticket_repo: Arc::new(TicketRepo::new(pool.clone())),
+        join_repo: Arc::new(JoinPeRepo::new(pool.clone())),
+        health_repo: Arc::new(HealthRepo::new(pool)),
+        jwt: Arc::new(JwtVerifier::new(SECRET)),
+        ticket_codes: Arc::new(TicketCodeGenerator::new("TKT", 10).unwrap()),
+        ticket_signer: Arc::new(TicketSigner::new("key", 60)),
+        base_url: Arc::new(BaseUrlResolver::fixed(BASE_URL)),
+        shutdown: Arc::default(),
+    });
+
+    Router::new()
+        .nest(PREFIX, handlers::api_router(state.clone()))
+        .with_state(state)
+}
+
+fn admin_token() -> String {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let claims = json!({
+        "sub": 1,
+        "rol": "admin",
+        "typ": "access",
+        "jti": "routes-test",
+        "iat": now,
+        "exp": now + 600,
+    });
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+// an unknown path is a 404 and an unknown method a 405, anything else
+// (validation errors, missing rows, the unreachable database) means it matched
+async fn status(app: &Router, method: Method, path: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header(header::AUTHORIZATION, format!("Bearer {}", admin_token()))
+        .body(Body::empty())
+        .unwrap();
+
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+fn is_served(status: StatusCode) -> bool {
+    status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED
+}
+
+#[tokio::test]
+async fn every_registered_route_answers_its_methods() {
+    let app = app();
+    let all = [Method::GET, Method::POST, Method::PUT, Method::DELETE];
+
+    for route in Route::ALL {
+        let path = route.href(PREFIX, &params_for(*route));
+
+        for method in &all {
+            let status = status(&app, method.clone(), &path).await;
+
+            if route.methods().contains(method) {
+                assert!(is_served(status), "{} {} answered {}", method, path, status);
+            } else {
+                assert_eq!(
+                    status,
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{} {}",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+}
+
+// an id and a ticket code, as many as the path takes
+fn params_for(route: Route) -> Vec<&'static dyn Display> {
+    let params: [&'static dyn Display; 2] = [&1, &"TKT-0000000001"];
+    params[..route.path().matches('{').count()].to_vec()
+}
+
+fn event() -> Event {
+    Event {
+        id: 1,
+        id_owner: 1,
+        nume: "Concert".into(),
+        locatie: Some("Iasi".into()),
+        descriere: None,
+        locuri: Some(100),
+        data_inceput: None,
+        data_sfarsit: None,
+        fus_orar: None,
+        locuri_disponibile: Some(100),
+    }
+}
+
+fn packet() -> EventPackets {
+    EventPackets {
+        id: 2,
+        id_owner: 1,
+        nume: "Festival".into(),
+        locatie: None,
+        descriere: None,
+        numarlocuri: None,
+        locuri_disponibile: None,
+    }
+}
+
+fn ticket() -> Ticket {
+    Ticket {
+        cod: "TKT-0000000001".into(),
+        id_pachet: None,
+        id_event: Some(1),
+        checked_in_at: None,
+    }
+}
+
+fn page<T>(items: Vec<T>) -> Page<T> {
+    Page {
+        total: items.len() as i64 + 20,
+        items,
+        page: Some(2),
+        items_per_page: 10,
+        next_cursor: None,
+    }
+}
+
+// every representation the builders produce, rendered as HAL-FORMS so the
+// templates are included
+fn representations() -> Vec<Value> {
+    let params = PaginationParams {
+        page: Some(2),
+        items_per_page: Some(10),
+        ..Default::default()
+    };
+    let event_query: EventQuery = serde_json::from_value(json!({ "location": "Iasi" })).unwrap();
+    let packet_query: EventPacketQuery =
+        serde_json::from_value(json!({ "available_tickets": 5 })).unwrap();
+
+    let mut values = vec![
+        json!(links::build_simple_event(event(), BASE_URL)),
+        json!(links::build_filtered_event(
+            vec![event()],
+            &event_query,
+            BASE_URL
+        )),
+        json!(links::build_simple_event_packet(packet(), BASE_URL)),
+        json!(links::build_filtered_event_packets(
+            vec![packet()],
+            &packet_query,
+            BASE_URL
+        )),
+        json!(links::build_packet_over_event(packet(), 1, BASE_URL)),
+        json!(links::build_event_over_packet(event(), 2, BASE_URL)),
+        json!(links::build_simple_ticket(ticket(), BASE_URL)),
+        json!(links::build_ticket_over_event(ticket(), 1, BASE_URL)),
+        json!(links::build_ticket_over_packet(ticket(), 2, BASE_URL)),
+        json!(links::build_check_in(
+            CheckIn {
+                cod: "TKT-0000000001".into(),
+                id_event: 1,
+                checked_in_at: OffsetDateTime::now_utc(),
+            },
+            BASE_URL
+        )),
+        json!(links::build_check_in_stats(
+            CheckInStats {
+                id_event: 1,
+                bilete_emise: 1,
+                intrari: 0,
+                ramase: 1,
+            },
+            BASE_URL
+        )),
+        json!(links::build_ticket_batch(
+            vec![links::build_ticket_over_event(ticket(), 1, BASE_URL)],
+            Route::EventTickets.href(BASE_URL, &[&1]),
+            Route::EventTicketsBulk.href(BASE_URL, &[&1]),
+        )),
+    ];
+
+    values.push(json!(links::build_collection::<CreateTicket, _>(
+        page(vec![links::build_simple_ticket(ticket(), BASE_URL)]),
+        "tickets",
+        &Route::Tickets.href(BASE_URL, &[]),
+        vec![],
+        &params,
+    )));
+    values.push(json!(links::build_collection::<AddPacketToEvent, _>(
+        page(vec![links::build_packet_over_event(packet(), 1, BASE_URL)]),
+        "event-packets",
+        &Route::EventPackets.href(BASE_URL, &[&1]),
+        vec![],
+        &params,
+    )));
+
+    values
+}
+
+// (method, href) for every link and template, embedded items included
+fn affordances(value: &Value, found: &mut Vec<(Method, String)>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Object(links)) = object.get("_links") {
+                for link in links.values() {
+                    if let Some(href) = link.get("href").and_then(Value::as_str) {
+                        found.push((Method::GET, href.to_string()));
+                    }
+                }
+            }
+            if let Some(Value::Object(templates)) = object.get("_templates") {
+                for template in templates.values() {
+                    let method = template["method"].as_str().unwrap().parse().unwrap();
+                    let target = template["target"].as_str().unwrap().to_string();
+                    found.push((method, target));
+                }
+            }
+            for child in object.values() {
+                affordances(child, found);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| affordances(item, found)),
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn every_link_points_to_a_registered_route() {
+    let app = app();
+
+    let values = MediaType::HalForms.scope(async { representations() }).await;
+
+    let mut found = Vec::new();
+    values
+        .iter()
+        .for_each(|value| affordances(value, &mut found));
+
+    assert!(found.iter().any(|(method, _)| *method == Method::DELETE));
+
+    for (method, href) in found {
+        let path = href
+            .strip_prefix("http://localhost:8001")
+            .unwrap_or_else(|| panic!("{} is not under the base url", href));
+        let path = path.split('?').next().unwrap();
+
+        let status = status(&app, method.clone(), path).await;
+        assert!(is_served(status), "{} {} answered {}", method, href, status);
+    }
+}
+
+// the form properties carry the same limits the validators enforce, so a
+// client can check a field before sending it
+#[tokio::test]
+async fn forms_carry_the_validation_limits() {
+    let values = MediaType::HalForms.scope(async { representations() }).await;
+
+    // (name, minLength, maxLength, min, max)
+    let expected = [
+        ("nume", json!(3), json!(100), Value::Null, Value::Null),
+        ("locatie", Value::Null, json!(255), Value::Null, Value::Null),
+        ("descriere", json!(10), json!(500), Value::Null, Value::Null),
+        (
+            "numarlocuri",
+            Value::Null,
+            Value::Null,
+            json!(1.0),
+            json!(50000.0),
+        ),
+        ("cod", json!(3), json!(50), Value::Null, Value::Null),
+        ("count", Value::Null, Value::Null, json!(1.0), json!(100.0)),
+    ];
+
+    let mut properties = Vec::new();
+    let mut pending = values.iter().collect::<Vec<_>>();
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::Array(found)) = object.get("properties") {
+                    properties.extend(found);
+                }
+                pending.extend(object.values());
+            }
+            Value::Array(items) => pending.extend(items),
+            _ => {}
+        }
+    }
+
+    for (name, min_length, max_length, min, max) in expected {
+        let matching = properties
+            .iter()
+            .filter(|property| property["name"] == name)
+            .collect::<Vec<_>>();
+        assert!(!matching.is_empty(), "no form has a `{}` property", name);
+
+        for property in matching {
+            assert_eq!(property["minLength"], min_length, "{}", property);
+            assert_eq!(property["maxLength"], max_length, "{}", property);
+            assert_eq!(property["min"], min, "{}", property);
+            assert_eq!(property["max"], max, "{}", property);
+        }
+    }
+}